@@ -0,0 +1,174 @@
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use anyhow::Result;
+use foxglove::schemas::log::Level;
+use mcap::records::ChunkIndex;
+use serde::Deserialize;
+use tracing::warn;
+
+use crate::diagnostics;
+
+/// What to do with records that can't be read, e.g. a corrupt chunk in a damaged file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ErrorPolicy {
+    /// Resume at the next chunk, reporting the bad record on /sdk-diagnostics and in the TUI.
+    Skip,
+    /// End the replay with an error.
+    Stop,
+    /// Like skip, and also log a warning for every bad record.
+    #[default]
+    Log,
+}
+
+/// A record the linear reader couldn't read, and how far it had got.
+///
+/// Offsets let the caller resume at the next chunk boundary. They are `None` when the reader
+/// doesn't expose them, in which case the file has to be read again with one that does.
+#[derive(Debug)]
+pub struct CorruptRecord {
+    /// File offset just past the last record read successfully.
+    pub last_good: Option<u64>,
+    /// File offset the reader had been fed up to.
+    pub consumed: Option<u64>,
+    /// Log time of the last message sent before the error.
+    pub sent_until: Option<u64>,
+    pub error: mcap::McapError,
+}
+
+impl fmt::Display for CorruptRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.consumed {
+            Some(offset) => write!(f, "bad record before offset {offset}: {}", self.error),
+            None => write!(f, "bad record: {}", self.error),
+        }
+    }
+}
+
+impl std::error::Error for CorruptRecord {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl CorruptRecord {
+    /// Returns the offset of the chunk to resume at, or `None` if no chunk follows the bad
+    /// record. `chunks` must be sorted by offset.
+    ///
+    /// A chunk is only handed out once it has been read in full, so when the error came after
+    /// the last good record, that chunk was read and the next one starts at or after
+    /// `consumed`. Otherwise the bad record started at `last_good`, and its length can't be
+    /// trusted, so reading resumes at the first chunk after that.
+    pub fn resume_offset(&self, chunks: &[ChunkIndex]) -> Option<u64> {
+        let (last_good, consumed) = (self.last_good?, self.consumed?);
+        chunks
+            .iter()
+            .map(|index| index.chunk_start_offset)
+            .find(|&offset| {
+                if last_good == consumed {
+                    offset >= consumed
+                } else {
+                    offset > last_good
+                }
+            })
+    }
+}
+
+/// Counts the records skipped under an [`ErrorPolicy`], shared between the reader and the TUI.
+#[derive(Debug, Clone, Default)]
+pub struct BadRecords {
+    policy: ErrorPolicy,
+    count: Arc<AtomicU64>,
+}
+
+impl BadRecords {
+    pub fn new(policy: ErrorPolicy) -> Self {
+        Self {
+            policy,
+            count: Arc::default(),
+        }
+    }
+
+    pub fn policy(&self) -> ErrorPolicy {
+        self.policy
+    }
+
+    /// Returns the number of bad records skipped so far.
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// Counts and reports a bad record that is being skipped, `then` describing where reading
+    /// continues. Under [`ErrorPolicy::Stop`], returns the error instead.
+    pub fn skip(&self, error: anyhow::Error, then: &str) -> Result<()> {
+        if self.policy == ErrorPolicy::Stop {
+            return Err(error);
+        }
+        let count = self.count.fetch_add(1, Ordering::Relaxed) + 1;
+        let message = format!("Skipped bad record #{count}, {then}: {error:#}");
+        if self.policy == ErrorPolicy::Log {
+            // Reaches /sdk-diagnostics through the log layer.
+            warn!("{message}");
+        } else {
+            diagnostics::publish(Level::Warning, "bad_record", message);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::chunk_index;
+
+    fn corrupt(last_good: Option<u64>, consumed: Option<u64>) -> CorruptRecord {
+        CorruptRecord {
+            last_good,
+            consumed,
+            sent_until: None,
+            error: mcap::McapError::BadMagic,
+        }
+    }
+
+    #[test]
+    fn resumes_at_chunk_after_bad_record() {
+        let chunks = [
+            chunk_index(8, 0, 0),
+            chunk_index(108, 0, 0),
+            chunk_index(208, 0, 0),
+        ];
+        // The record at 108 is bad, and the reader had been fed past it.
+        assert_eq!(
+            corrupt(Some(108), Some(150)).resume_offset(&chunks),
+            Some(208)
+        );
+        // A chunk ending at `consumed` was read in full, so the next one starts there.
+        assert_eq!(
+            corrupt(Some(108), Some(108)).resume_offset(&chunks),
+            Some(108)
+        );
+        assert_eq!(
+            corrupt(Some(100), Some(100)).resume_offset(&chunks),
+            Some(108)
+        );
+    }
+
+    #[test]
+    fn no_resume_past_last_chunk_or_without_offsets() {
+        let chunks = [chunk_index(8, 0, 0), chunk_index(108, 0, 0)];
+        assert_eq!(corrupt(Some(108), Some(150)).resume_offset(&chunks), None);
+        assert_eq!(corrupt(None, None).resume_offset(&chunks), None);
+        assert_eq!(corrupt(Some(0), Some(10)).resume_offset(&[]), None);
+    }
+
+    #[test]
+    fn stop_policy_returns_the_error() {
+        let bad_records = BadRecords::new(ErrorPolicy::Stop);
+        assert!(bad_records
+            .skip(anyhow::anyhow!("bad"), "stopping")
+            .is_err());
+        assert_eq!(bad_records.count(), 0);
+    }
+}
@@ -0,0 +1,210 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context, Result};
+use foxglove::McapWriter;
+use tokio::sync::mpsc;
+use tracing::info;
+
+use crate::mcap_replay::{OwnedMessage, Summary, SummaryOptions};
+use crate::source::MessageSource;
+
+/// Number of messages buffered between the reader and the benchmark loop.
+const READ_CAPACITY: usize = 1024;
+
+/// Where published messages go during a benchmark.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum BenchSink {
+    /// Don't publish; only measure reading and decompression.
+    None,
+    /// Publish into an MCAP writer that discards its output, measuring serialization without
+    /// any network.
+    #[default]
+    Null,
+    /// Publish to a websocket server, reaching whichever clients are connected and subscribed.
+    Websocket,
+}
+
+/// How to run a benchmark.
+#[derive(Debug, Clone)]
+pub struct BenchOptions {
+    pub sink: BenchSink,
+    /// Address for [`BenchSink::Websocket`].
+    pub host: String,
+    pub port: u16,
+}
+
+/// Throughput of each stage of the replay path, measured without pacing.
+#[derive(Debug, Default)]
+pub struct BenchReport {
+    /// Size of the file and time to read it raw, for file sources.
+    pub file: Option<(u64, Duration)>,
+    pub messages: u64,
+    /// Total message payload, after decompression.
+    pub payload_bytes: u64,
+    /// Time to read, decompress, and parse every message.
+    pub read: Duration,
+    /// Time spent publishing, excluding reading, for sinks other than [`BenchSink::None`].
+    pub publish: Option<Duration>,
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mb = |bytes: u64| bytes as f64 / 1e6;
+        if let Some((bytes, elapsed)) = self.file {
+            writeln!(
+                f,
+                "file I/O:     {:>10.1} MB in {:>8.3}s  {:>12.1} MB/s",
+                mb(bytes),
+                elapsed.as_secs_f64(),
+                mb(bytes) / elapsed.as_secs_f64()
+            )?;
+        }
+        let stage = |f: &mut fmt::Formatter<'_>, name: &str, elapsed: Duration| {
+            let secs = elapsed.as_secs_f64();
+            writeln!(
+                f,
+                "{name:<13} {:>10} msgs in {:>6.3}s  {:>12.0} msgs/s  {:>10.1} MB/s",
+                self.messages,
+                secs,
+                self.messages as f64 / secs,
+                mb(self.payload_bytes) / secs
+            )
+        };
+        stage(f, "read/decode:", self.read)?;
+        if let Some(publish) = self.publish {
+            stage(f, "publish:", publish)?;
+        }
+        Ok(())
+    }
+}
+
+/// Replays `source` as fast as possible and reports the throughput of each stage.
+///
+/// For file sources, the file is first read raw to measure I/O. Every message is then read
+/// through the source once to measure reading and decompression, and, unless the sink is
+/// [`BenchSink::None`], once more while publishing to the sink, timing only the publishing.
+pub fn bench(source: Arc<dyn MessageSource>, options: &BenchOptions) -> Result<BenchReport> {
+    if source.is_streaming() && options.sink != BenchSink::None {
+        return Err(anyhow!("streams can only be read once; use a sink of none"));
+    }
+    let mut report = BenchReport::default();
+    if source.path().is_file() {
+        report.file = Some(read_raw(source.path())?);
+    }
+
+    let summary = source
+        .load_summary(&SummaryOptions::default())
+        .context("load summary")?;
+    let runtime = tokio::runtime::Runtime::new().context("start bench runtime")?;
+
+    info!("Reading {}", source.path().display());
+    let start = Instant::now();
+    let (messages, payload_bytes) = runtime.block_on(drain(&source, |_| {}))?;
+    report.read = start.elapsed();
+    report.messages = messages;
+    report.payload_bytes = payload_bytes;
+
+    match options.sink {
+        BenchSink::None => {}
+        BenchSink::Null => {
+            let writer = McapWriter::new()
+                .create(NullWriter::default())
+                .context("start null writer")?;
+            report.publish = Some(runtime.block_on(publish_all(&source, &summary))?);
+            writer.close().context("finish null writer")?;
+        }
+        BenchSink::Websocket => {
+            let server = foxglove::WebSocketServer::new()
+                .name("bench")
+                .bind(&options.host, options.port)
+                .start_blocking()
+                .context("start server")?;
+            report.publish = Some(runtime.block_on(publish_all(&source, &summary))?);
+            server.stop();
+        }
+    }
+    Ok(report)
+}
+
+/// Reads the whole file without parsing it.
+fn read_raw(path: &Path) -> Result<(u64, Duration)> {
+    let start = Instant::now();
+    let mut file = File::open(path).with_context(|| format!("open {}", path.display()))?;
+    let mut buf = vec![0; 1 << 20];
+    let mut bytes = 0;
+    loop {
+        let n = file.read(&mut buf).context("read file")?;
+        if n == 0 {
+            break;
+        }
+        bytes += n as u64;
+    }
+    Ok((bytes, start.elapsed()))
+}
+
+/// Reads every message from the source, passing each to `handle`. Returns the message count
+/// and total payload size.
+async fn drain(
+    source: &Arc<dyn MessageSource>,
+    mut handle: impl FnMut(&OwnedMessage),
+) -> Result<(u64, u64)> {
+    let (tx, mut rx) = mpsc::channel(READ_CAPACITY);
+    let reader = tokio::spawn(source.read_messages(tx, Arc::new(AtomicBool::new(false)), None));
+    let (mut messages, mut bytes) = (0, 0);
+    while let Some(message) = rx.recv().await {
+        handle(&message);
+        messages += 1;
+        bytes += message.data.len() as u64;
+    }
+    reader.await.context("reading task")??;
+    Ok((messages, bytes))
+}
+
+/// Publishes every message without pacing, returning the time spent publishing.
+async fn publish_all(source: &Arc<dyn MessageSource>, summary: &Summary) -> Result<Duration> {
+    let mut file_stream = summary.file_stream();
+    let mut elapsed = Duration::ZERO;
+    drain(source, |message| {
+        let start = Instant::now();
+        file_stream.publish(&message.header, &message.data);
+        elapsed += start.elapsed();
+    })
+    .await?;
+    Ok(elapsed)
+}
+
+/// A seekable writer that discards everything.
+#[derive(Default)]
+struct NullWriter {
+    position: u64,
+    len: u64,
+}
+
+impl Write for NullWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.position += buf.len() as u64;
+        self.len = self.len.max(self.position);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for NullWriter {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.position = match pos {
+            SeekFrom::Start(offset) => offset,
+            SeekFrom::End(offset) => self.len.saturating_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.saturating_add_signed(offset),
+        };
+        Ok(self.position)
+    }
+}
@@ -1,5 +1,75 @@
-use crate::logger;
+use crate::interpolate::{PoseInterpolator, TransitionSettings};
+use crate::logger::{self, CameraTopics, DepthMode, DepthSettings, ImageSettings, StaticTransform};
+use crate::noise::{CameraNoise, NoiseSettings};
+use crate::overlay::{Overlay, OverlaySettings, PlaybackStatus};
+use serde::{Deserialize, Serialize};
 use std::f64::consts::PI;
+use std::sync::Arc;
+
+/// Tunable physics for the camera motion
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CameraPhysics {
+    pub max_velocity: f64,
+    pub velocity_step: f64,
+    pub steering_step: f64,
+    pub roll_step: f64,
+    pub max_steer: f64, // maximum radial velocity
+    pub max_roll_rate: f64,
+    pub damping: f64, // fraction of velocity kept on each update
+}
+
+impl Default for CameraPhysics {
+    fn default() -> Self {
+        Self {
+            max_velocity: 0.2,
+            velocity_step: 0.05,
+            steering_step: 0.01,
+            roll_step: 0.01,
+            max_steer: 0.3,
+            max_roll_rate: 0.3,
+            damping: 0.8,
+        }
+    }
+}
+
+/// Position and orientation of the camera, as exchanged with remote controllers
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CameraPose {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub heading: f64, // radians in the XZ plane
+    pub roll: f64,    // radians
+}
+
+impl CameraPose {
+    /// Returns the orientation as a quaternion (x, y, z, w)
+    pub fn rotation(&self) -> [f64; 4] {
+        let q = heading_roll_rotation(self.heading, self.roll);
+        [q[0], q[1], q[2], q[3]]
+    }
+
+    /// Creates a pose from a translation and a quaternion (x, y, z, w). Only heading and roll
+    /// are kept; any pitch in the rotation is dropped.
+    pub fn from_transform(translation: [f64; 3], rotation: [f64; 4]) -> Self {
+        let [x, y, z, w] = rotation;
+        // The rotated X axis is (cos h cos r, sin r, -sin h cos r) for a heading h followed by
+        // a roll r.
+        let axis = [
+            1.0 - 2.0 * (y * y + z * z),
+            2.0 * (x * y + w * z),
+            2.0 * (x * z - w * y),
+        ];
+        Self {
+            x: translation[0],
+            y: translation[1],
+            z: translation[2],
+            heading: (-axis[2]).atan2(axis[0]).rem_euclid(2.0 * PI),
+            roll: axis[1].atan2(axis[0].hypot(axis[2])).rem_euclid(2.0 * PI),
+        }
+    }
+}
 
 /// Manages the state of the camera including position, orientation, and physics
 #[derive(Clone)]
@@ -14,10 +84,18 @@ pub struct CameraState {
     steer: f64, // radial velocity
     roll: f64, // roll angle in radians
     roll_rate: f64, // roll angular velocity
-    max_velocity: f64,
-    velocity_step: f64,
-    steering_step: f64,
-    roll_step: f64,
+    physics: CameraPhysics,
+    image: ImageSettings,
+    depth: DepthSettings,
+    noise: Option<CameraNoise>,
+    static_transforms: Vec<StaticTransform>,
+    transition: TransitionSettings,
+    // in-progress transition started by `move_to`, and the pose it ends at
+    interpolator: Option<(PoseInterpolator, CameraPose)>,
+    // own topics instead of the shared /sdk-camera, /sdk-image, and /sdk-tf
+    topics: Option<Arc<CameraTopics>>,
+    // HUD drawn onto the published image
+    overlay: Option<Overlay>,
 }
 
 impl CameraState {
@@ -33,23 +111,87 @@ impl CameraState {
             steer: 0.0, // radial velocity
             roll: 0.0, // 0 radians means no roll
             roll_rate: 0.0, // roll angular velocity
-            max_velocity: 0.2,
-            velocity_step: 0.05,
-            steering_step: 0.01,
-            roll_step: 0.01,
+            physics: CameraPhysics::default(),
+            image: ImageSettings::default(),
+            depth: DepthSettings::default(),
+            noise: None,
+            static_transforms: Vec::new(),
+            transition: TransitionSettings::default(),
+            interpolator: None,
+            topics: None,
+            overlay: None,
+        }
+    }
+
+    /// Replaces the motion physics
+    pub fn with_physics(mut self, physics: CameraPhysics) -> Self {
+        self.physics = physics;
+        self
+    }
+
+    /// Replaces the published image and calibration settings
+    pub fn with_image_settings(mut self, image: ImageSettings) -> Self {
+        self.image = image;
+        self
+    }
+
+    /// Publishes a synthetic depth image alongside the RGB image
+    pub fn with_depth(mut self, depth: DepthSettings) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Adds noise to the published transform, without affecting the camera's own motion
+    pub fn with_noise(mut self, noise: NoiseSettings) -> Self {
+        self.noise = CameraNoise::new(noise);
+        self
+    }
+
+    /// Publishes fixed child frames (e.g. an optical frame) alongside the camera transform,
+    /// batched as `foxglove.FrameTransforms` on /sdk-transforms; the camera stays on /sdk-tf
+    pub fn with_static_transforms(mut self, static_transforms: Vec<StaticTransform>) -> Self {
+        self.static_transforms = static_transforms;
+        self
+    }
+
+    /// Replaces the duration and easing of transitions started by `move_to`
+    pub fn with_transition(mut self, transition: TransitionSettings) -> Self {
+        self.transition = transition;
+        self
+    }
+
+    /// Publishes on the given topics instead of the shared ones. Depth images and static
+    /// transforms are only published on the shared topics
+    pub fn with_topics(mut self, topics: CameraTopics) -> Self {
+        self.topics = Some(Arc::new(topics));
+        self
+    }
+
+    /// Draws a HUD onto the published image; call `update_overlay` on every tick to refresh it
+    pub fn with_overlay(mut self, settings: OverlaySettings) -> Self {
+        self.overlay = Some(Overlay::new(settings));
+        self
+    }
+
+    /// Refreshes the HUD, if any, with the playback state and the current pose
+    pub fn update_overlay(&mut self, status: &PlaybackStatus) {
+        let pose = self.pose();
+        let velocity = self.velocity;
+        if let Some(overlay) = &mut self.overlay {
+            overlay.update(status, &pose, velocity);
         }
     }
 
      /// Increases forward velocity by the specified factor
     pub fn accelerate(&mut self, step_factor: f64) {
-        let step = step_factor * self.velocity_step;
-        self.velocity = (self.velocity + step).min(self.max_velocity);
+        let step = step_factor * self.physics.velocity_step;
+        self.velocity = (self.velocity + step).min(self.physics.max_velocity);
     }
 
     /// Decreases forward velocity by the specified factor
     pub fn decelerate(&mut self, step_factor: f64) {
-        let step = step_factor * self.velocity_step;
-        self.velocity = (self.velocity - step).max(-self.max_velocity);
+        let step = step_factor * self.physics.velocity_step;
+        self.velocity = (self.velocity - step).max(-self.physics.max_velocity);
     }
 
     /// Immediately stops all movement
@@ -61,34 +203,46 @@ impl CameraState {
 
     /// Steers left (counterclockwise in XZ plane) by the specified factor
     pub fn steer_left(&mut self, step_factor: f64) {
-        let step = step_factor * self.steering_step;
+        let step = step_factor * self.physics.steering_step;
         self.steer -= step;
-        self.steer = self.steer.clamp(-0.3, 0.3);
+        self.steer = self.steer.clamp(-self.physics.max_steer, self.physics.max_steer);
     }
 
     /// Steers right (clockwise in XZ plane) by the specified factor
     pub fn steer_right(&mut self, step_factor: f64) {
-        let step = step_factor * self.steering_step;
+        let step = step_factor * self.physics.steering_step;
         self.steer += step;
-        self.steer = self.steer.clamp(-0.3, 0.3);
+        self.steer = self.steer.clamp(-self.physics.max_steer, self.physics.max_steer);
     }
 
     /// Roll counterclockwise (Q key) by the specified factor
     pub fn roll_counterclockwise(&mut self, step_factor: f64) {
-        let step = step_factor * self.roll_step;
+        let step = step_factor * self.physics.roll_step;
         self.roll_rate -= step;
-        self.roll_rate = self.roll_rate.clamp(-0.3, 0.3);
+        self.roll_rate = self.roll_rate.clamp(-self.physics.max_roll_rate, self.physics.max_roll_rate);
     }
 
     /// Roll clockwise (E key) by the specified factor
     pub fn roll_clockwise(&mut self, step_factor: f64) {
-        let step = step_factor * self.roll_step;
+        let step = step_factor * self.physics.roll_step;
         self.roll_rate += step;
-        self.roll_rate = self.roll_rate.clamp(-0.3, 0.3);
+        self.roll_rate = self.roll_rate.clamp(-self.physics.max_roll_rate, self.physics.max_roll_rate);
     }
 
     /// Updates the camera position based on current velocity and direction
     pub fn update(&mut self) {
+        if let Some((interpolator, target)) = &self.interpolator {
+            if interpolator.is_done() {
+                let target = target.clone();
+                self.set_pose(&target);
+            } else {
+                let (translation, rotation) = interpolator.sample();
+                self.translation = translation.to_vec();
+                self.rotation = rotation.to_vec();
+                return;
+            }
+        }
+
         self.heading += self.steer;
         self.roll += self.roll_rate;
 
@@ -120,37 +274,31 @@ impl CameraState {
             self.translation[1] += dy;
             self.translation[2] += dz;
 
-            self.velocity *= 0.8;
+            self.velocity *= self.physics.damping;
         }
 
         // Apply damping to steering rate and roll rate
-        self.steer *= 0.8;
-        self.roll_rate *= 0.8;
-
-        // Create quaternion from heading (y-axis rotation) and roll (z-axis rotation)
-        // First calculate quaternion components for heading (y-axis rotation)
-        let half_heading = self.heading / 2.0;
-        let qy_w = half_heading.cos();
-        let qy_x = 0.0;
-        let qy_y = half_heading.sin();
-        let qy_z = 0.0;
-        
-        // Calculate quaternion components for roll (z-axis rotation)
-        let half_roll = self.roll / 2.0;
-        let qz_w = half_roll.cos();
-        let qz_x = 0.0;
-        let qz_y = 0.0;
-        let qz_z = half_roll.sin();
-        
-        // Multiply quaternions to combine rotations (heading * roll)
-        // (w1, x1, y1, z1) * (w2, x2, y2, z2)
-        let w = qy_w * qz_w - qy_x * qz_x - qy_y * qz_y - qy_z * qz_z;
-        let x = qy_w * qz_x + qz_w * qy_x + qy_y * qz_z - qy_z * qz_y;
-        let y = qy_w * qz_y + qz_w * qy_y + qy_z * qz_x - qy_x * qz_z;
-        let z = qy_w * qz_z + qz_w * qy_z + qy_x * qz_y - qy_y * qz_x;
-        
-        // Set rotation quaternion [x, y, z, w]
-        self.rotation = vec![x, y, z, w];
+        self.steer *= self.physics.damping;
+        self.roll_rate *= self.physics.damping;
+
+        self.rotation = heading_roll_rotation(self.heading, self.roll);
+    }
+
+    /// Gets the camera's frame id
+    pub fn frame_id(&self) -> &str {
+        &self.frame_id
+    }
+
+    /// Gets the frame id the camera moves in
+    pub fn parent_frame_id(&self) -> &str {
+        &self.parent_frame_id
+    }
+
+    /// Moves the camera into another frame, e.g. the frame of a target it follows
+    pub fn set_parent_frame_id(&mut self, parent_frame_id: &str) {
+        if self.parent_frame_id != parent_frame_id {
+            self.parent_frame_id = parent_frame_id.to_string();
+        }
     }
 
     /// Gets the current velocity
@@ -160,7 +308,7 @@ impl CameraState {
 
     /// Gets the maximum velocity
     pub fn get_max_velocity(&self) -> f64 {
-        self.max_velocity
+        self.physics.max_velocity
     }
 
     /// Gets the current translation vector
@@ -173,15 +321,121 @@ impl CameraState {
         self.roll
     }
 
-    /// Logs the current camera state (calibration, image, and transform)
-    pub fn log_state(&self) {
-        logger::log_camera_calibration(&self.frame_id);
-        logger::log_raw_image(&self.frame_id);
-        logger::log_frame_transform(
-            &self.parent_frame_id,
-            &self.frame_id,
-            self.translation.clone(),
-            self.rotation.clone(),
+    /// Gets the current heading in radians
+    pub fn get_heading(&self) -> f64 {
+        self.heading
+    }
+
+    /// Moves the camera to the given position
+    pub fn set_translation(&mut self, x: f64, y: f64, z: f64) {
+        self.translation = vec![x, y, z];
+    }
+
+    /// Sets the heading in radians (applied on the next update)
+    pub fn set_heading(&mut self, heading: f64) {
+        self.heading = heading.rem_euclid(2.0 * PI);
+    }
+
+    /// Gets the current pose
+    pub fn pose(&self) -> CameraPose {
+        CameraPose {
+            x: self.translation[0],
+            y: self.translation[1],
+            z: self.translation[2],
+            heading: self.heading,
+            roll: self.roll,
+        }
+    }
+
+    /// Jumps to the given pose and stops any movement, including a transition in progress
+    pub fn set_pose(&mut self, pose: &CameraPose) {
+        self.interpolator = None;
+        self.stop();
+        self.set_translation(pose.x, pose.y, pose.z);
+        self.set_heading(pose.heading);
+        self.set_roll(pose.roll);
+    }
+
+    /// Moves smoothly to the given pose over the configured transition, then stops there.
+    /// Velocity and steering input are ignored until the transition ends.
+    pub fn move_to(&mut self, pose: &CameraPose) {
+        if self.transition.duration <= 0.0 {
+            self.set_pose(pose);
+            return;
+        }
+        self.stop();
+        let rotation = heading_roll_rotation(pose.heading, pose.roll);
+        let interpolator = PoseInterpolator::new(
+            (&self.translation, &self.rotation),
+            (&[pose.x, pose.y, pose.z], &rotation),
+            &self.transition,
         );
+        self.interpolator = Some((interpolator, pose.clone()));
     }
+
+    /// Returns true while a transition started by `move_to` is in progress
+    pub fn is_transitioning(&self) -> bool {
+        self.interpolator.is_some()
+    }
+
+    /// Sets the roll angle in radians (applied on the next update)
+    pub fn set_roll(&mut self, roll: f64) {
+        self.roll = roll.rem_euclid(2.0 * PI);
+    }
+
+    /// Logs the current camera state (calibration, image, and transform), stamped `now_ns`
+    pub fn log_state(&self, now_ns: u64) {
+        if let Some(topics) = &self.topics {
+            let transform = self.published_transform();
+            topics.log(now_ns, &self.parent_frame_id, &self.frame_id, &self.image, self.overlay.as_ref(), transform);
+            return;
+        }
+        logger::log_camera_calibration(now_ns, &self.frame_id, &self.image);
+        logger::log_raw_image(now_ns, &self.frame_id, &self.image, self.overlay.as_ref());
+        if self.depth.mode != DepthMode::Off {
+            logger::log_depth_image(now_ns, &self.frame_id, &self.image, &self.depth, self.translation[1], self.roll);
+        }
+        let (translation, rotation) = self.published_transform();
+        logger::log_frame_transform(now_ns, &self.parent_frame_id, &self.frame_id, translation, rotation);
+        if !self.static_transforms.is_empty() {
+            logger::log_static_transforms(now_ns, &self.frame_id, &self.static_transforms);
+        }
+    }
+
+    /// Gets the translation and rotation quaternion (x, y, z, w) as published, including noise
+    /// sampled at the time of the call
+    pub fn published_transform(&self) -> (Vec<f64>, Vec<f64>) {
+        match &self.noise {
+            Some(noise) => noise.apply(&self.translation, &self.rotation),
+            None => (self.translation.clone(), self.rotation.clone()),
+        }
+    }
+}
+
+/// Creates the quaternion (x, y, z, w) for a heading (y-axis rotation) followed by a roll
+/// (z-axis rotation)
+fn heading_roll_rotation(heading: f64, roll: f64) -> Vec<f64> {
+    // First calculate quaternion components for heading (y-axis rotation)
+    let half_heading = heading / 2.0;
+    let qy_w = half_heading.cos();
+    let qy_x = 0.0;
+    let qy_y = half_heading.sin();
+    let qy_z = 0.0;
+    
+    // Calculate quaternion components for roll (z-axis rotation)
+    let half_roll = roll / 2.0;
+    let qz_w = half_roll.cos();
+    let qz_x = 0.0;
+    let qz_y = 0.0;
+    let qz_z = half_roll.sin();
+    
+    // Multiply quaternions to combine rotations (heading * roll)
+    // (w1, x1, y1, z1) * (w2, x2, y2, z2)
+    let w = qy_w * qz_w - qy_x * qz_x - qy_y * qz_y - qy_z * qz_z;
+    let x = qy_w * qz_x + qz_w * qy_x + qy_y * qz_z - qy_z * qz_y;
+    let y = qy_w * qz_y + qz_w * qy_y + qy_z * qz_x - qy_x * qz_z;
+    let z = qy_w * qz_z + qz_w * qy_z + qy_x * qz_y - qy_y * qz_x;
+    
+    // Rotation quaternion [x, y, z, w]
+    vec![x, y, z, w]
 }
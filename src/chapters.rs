@@ -0,0 +1,172 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+
+use crate::events::Marker;
+
+/// Name of the MCAP metadata records written for chapters.
+pub const CHAPTER_METADATA: &str = "chapter";
+
+/// A named segment of the replay, in seconds from the start of the replayed file, as given on
+/// the command line or in a chapters file.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChapterSpec {
+    pub name: String,
+    pub start: f64,
+    pub end: f64,
+}
+
+impl ChapterSpec {
+    /// Returns the chapter in log time of a file whose first message is at `start_time_ns`.
+    pub fn resolve(&self, start_time_ns: u64) -> Result<Chapter> {
+        let offset = |secs: f64| {
+            Duration::try_from_secs_f64(secs)
+                .map(|d| start_time_ns.saturating_add(d.as_nanos() as u64))
+                .map_err(|e| anyhow!("invalid time in chapter {:?}: {e}", self.name))
+        };
+        Chapter::new(self.name.clone(), offset(self.start)?, offset(self.end)?)
+    }
+}
+
+/// Parses a `<name>=<start>..<end>` chapter, with times in seconds from the start of the file.
+pub fn parse_chapter(spec: &str) -> Result<ChapterSpec> {
+    let invalid = || anyhow!("expected <name>=<start>..<end>, got {spec:?}");
+    let (name, range) = spec.rsplit_once('=').ok_or_else(invalid)?;
+    let (start, end) = range.split_once("..").ok_or_else(invalid)?;
+    if name.is_empty() {
+        return Err(invalid());
+    }
+    Ok(ChapterSpec {
+        name: name.to_string(),
+        start: start.trim().parse().map_err(|_| invalid())?,
+        end: end.trim().parse().map_err(|_| invalid())?,
+    })
+}
+
+/// Loads a YAML (or JSON) list of chapters, e.g. `- {name: merge, start: 12.5, end: 30}`.
+pub fn load_chapters(path: &Path) -> Result<Vec<ChapterSpec>> {
+    let file = File::open(path).with_context(|| format!("open {}", path.display()))?;
+    serde_yaml::from_reader(BufReader::new(file))
+        .with_context(|| format!("parse chapters in {}", path.display()))
+}
+
+/// A named segment of the replay, in log time of the replayed file, which is also the log time
+/// of replayed messages in the recording.
+#[derive(Debug, Clone)]
+pub struct Chapter {
+    pub name: String,
+    pub start_time_ns: u64,
+    pub end_time_ns: u64,
+}
+
+impl Chapter {
+    pub fn new(name: String, start_time_ns: u64, end_time_ns: u64) -> Result<Self> {
+        if end_time_ns < start_time_ns {
+            return Err(anyhow!("chapter {name:?} ends before it starts"));
+        }
+        Ok(Self {
+            name,
+            start_time_ns,
+            end_time_ns,
+        })
+    }
+
+    /// Returns the chapter as MCAP metadata.
+    pub fn metadata(&self) -> BTreeMap<String, String> {
+        BTreeMap::from([
+            ("name".to_string(), self.name.clone()),
+            ("start_time_ns".to_string(), self.start_time_ns.to_string()),
+            ("end_time_ns".to_string(), self.end_time_ns.to_string()),
+        ])
+    }
+}
+
+/// Splits the replay at markers: each marker starts a chapter, named after its label, that runs
+/// until the next marker or `end_time_ns`. Markers are ordered by replay log time, so markers
+/// dropped after seeking back still fall in place.
+pub fn marker_chapters(markers: &[Marker], end_time_ns: u64) -> Vec<Chapter> {
+    let mut times: Vec<(u64, &str)> = markers
+        .iter()
+        .map(|m| (m.replay_log_time_ns, m.label.as_str()))
+        .collect();
+    times.sort_by_key(|(time, _)| *time);
+    let ends = times
+        .iter()
+        .skip(1)
+        .map(|(time, _)| *time)
+        .chain([end_time_ns.max(times.last().map_or(0, |(time, _)| *time))]);
+    times
+        .iter()
+        .zip(ends)
+        .enumerate()
+        .map(|(i, ((start, label), end))| {
+            let name = if label.is_empty() {
+                format!("chapter {}", i + 1)
+            } else {
+                label.to_string()
+            };
+            Chapter {
+                name,
+                start_time_ns: *start,
+                end_time_ns: end,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn marker(replay_log_time_ns: u64, label: &str) -> Marker {
+        Marker {
+            time_ns: 0,
+            replay_log_time_ns,
+            label: label.to_string(),
+        }
+    }
+
+    #[test]
+    fn parses_chapter_specs() {
+        let spec = parse_chapter("left turn=12.5..31").unwrap();
+        assert_eq!(spec.name, "left turn");
+        assert_eq!((spec.start, spec.end), (12.5, 31.0));
+        // The last `=` separates the name, so names may contain one.
+        assert_eq!(parse_chapter("a=b=1..2").unwrap().name, "a=b");
+        assert!(parse_chapter("=1..2").is_err());
+        assert!(parse_chapter("name=1-2").is_err());
+        assert!(parse_chapter("name=x..2").is_err());
+    }
+
+    #[test]
+    fn resolves_chapters_against_the_start_time() {
+        let spec = parse_chapter("a=1..2").unwrap();
+        let chapter = spec.resolve(1_000).unwrap();
+        assert_eq!(chapter.start_time_ns, 1_000_001_000);
+        assert_eq!(chapter.end_time_ns, 2_000_001_000);
+        assert!(parse_chapter("a=2..1").unwrap().resolve(0).is_err());
+        assert!(parse_chapter("a=-1..1").unwrap().resolve(0).is_err());
+    }
+
+    #[test]
+    fn markers_split_the_replay_in_time_order() {
+        let chapters = marker_chapters(&[marker(30, ""), marker(10, "merge")], 50);
+        let spans: Vec<_> = chapters
+            .iter()
+            .map(|c| (c.name.as_str(), c.start_time_ns, c.end_time_ns))
+            .collect();
+        assert_eq!(spans, [("merge", 10, 30), ("chapter 2", 30, 50)]);
+    }
+
+    #[test]
+    fn last_marker_after_end_is_empty() {
+        let chapters = marker_chapters(&[marker(60, "late")], 50);
+        assert_eq!((chapters[0].start_time_ns, chapters[0].end_time_ns), (60, 60));
+        assert!(marker_chapters(&[], 50).is_empty());
+    }
+}
@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::time::Instant;
+
+use foxglove::Channel;
+use parking_lot::Mutex;
+use serde::Deserialize;
+use serde_json::Value;
+use tracing::warn;
+
+use crate::camera_state::CameraPose;
+use crate::decode::MessageDecoder;
+use crate::ChannelId;
+
+/// Schemas whose messages are searched for the chased frame.
+const TRANSFORM_SCHEMAS: &[&str] = &["foxglove.FrameTransform", "foxglove.FrameTransforms"];
+
+/// How the chase camera trails its target (config file `[chase_settings]` section).
+///
+/// Offsets are in meters in the camera's convention (Y up), relative to the target's direction
+/// of travel.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ChaseSettings {
+    /// Distance behind the target.
+    pub behind: f64,
+    /// Height above the target.
+    pub above: f64,
+    /// Sideways offset, positive to the right.
+    pub lateral: f64,
+    /// Seconds ahead of the target, at its current velocity, that the camera aims at.
+    pub look_ahead: f64,
+    /// Time constant in seconds of the camera's lag behind its ideal position; 0 is rigid.
+    pub smoothing: f64,
+}
+
+impl Default for ChaseSettings {
+    fn default() -> Self {
+        Self {
+            behind: 6.0,
+            above: 2.0,
+            lateral: 0.0,
+            look_ahead: 0.5,
+            smoothing: 0.3,
+        }
+    }
+}
+
+/// Where the chased frame is, as of the latest replayed transform.
+#[derive(Debug, Clone)]
+pub struct TargetSample {
+    /// Frame the position and velocity are in.
+    pub parent_frame_id: String,
+    pub position: [f64; 3],
+    /// Meters per second of log time, estimated from consecutive transforms.
+    pub velocity: [f64; 3],
+}
+
+/// Watches replayed `foxglove.FrameTransform(s)` messages for one child frame, or for every
+/// frame (see [`ChaseTarget::all_frames`]).
+pub struct ChaseTarget {
+    // `None` tracks every child frame
+    frame_id: Option<String>,
+    state: Mutex<TargetState>,
+}
+
+#[derive(Default)]
+struct TargetState {
+    decoders: HashMap<ChannelId, Option<MessageDecoder>>,
+    latest: HashMap<String, (u64, TargetSample)>,
+}
+
+impl ChaseTarget {
+    pub fn new(frame_id: impl Into<String>) -> Self {
+        Self {
+            frame_id: Some(frame_id.into()),
+            state: Mutex::default(),
+        }
+    }
+
+    /// Tracks every child frame of the replayed transforms, e.g. for scripts to look up.
+    pub fn all_frames() -> Self {
+        Self {
+            frame_id: None,
+            state: Mutex::default(),
+        }
+    }
+
+    /// Returns the chased frame, or `None` if every frame is tracked.
+    pub fn frame_id(&self) -> Option<&str> {
+        self.frame_id.as_deref()
+    }
+
+    /// Updates the target from a replayed message, if it is a transform of a tracked frame.
+    pub fn observe(&self, channel: &Channel, log_time: u64, data: &[u8]) {
+        let Some(schema) = channel.schema() else {
+            return;
+        };
+        if !TRANSFORM_SCHEMAS.contains(&schema.name.as_str()) {
+            return;
+        }
+        let mut state = self.state.lock();
+        let decoder = state.decoders.entry(u64::from(channel.id())).or_insert_with(|| {
+            MessageDecoder::new(channel.message_encoding(), Some(schema))
+                .inspect_err(|e| warn!("Can't read transforms on {}: {e:#}", channel.topic()))
+                .ok()
+        });
+        let Some(message) = decoder.as_ref().and_then(|d| d.decode(data).ok()) else {
+            return;
+        };
+        let transforms = match message.get("transforms") {
+            Some(Value::Array(transforms)) => transforms.iter().collect(),
+            _ => vec![&message],
+        };
+        for transform in transforms {
+            let Some(child_frame_id) = transform["child_frame_id"].as_str() else {
+                continue;
+            };
+            if self.frame_id.as_deref().is_none_or(|f| f == child_frame_id) {
+                state.update(child_frame_id, log_time, transform);
+            }
+        }
+    }
+
+    /// Returns where the chased frame was last seen.
+    pub fn latest(&self) -> Option<TargetSample> {
+        self.sample(self.frame_id.as_deref()?)
+    }
+
+    /// Returns where a tracked frame was last seen.
+    pub fn sample(&self, frame_id: &str) -> Option<TargetSample> {
+        self.state.lock().latest.get(frame_id).map(|(_, sample)| sample.clone())
+    }
+}
+
+impl TargetState {
+    fn update(&mut self, child_frame_id: &str, log_time: u64, transform: &Value) {
+        // Default-valued fields are left out of decoded protobuf messages.
+        let axis = |name: &str| transform["translation"][name].as_f64().unwrap_or(0.0);
+        let position = [axis("x"), axis("y"), axis("z")];
+        let parent_frame_id = transform["parent_frame_id"].as_str().unwrap_or_default();
+        let velocity = match self.latest.get(child_frame_id) {
+            Some((time, prev)) if *time < log_time && prev.parent_frame_id == parent_frame_id => {
+                let dt = (log_time - time) as f64 / 1e9;
+                [0, 1, 2].map(|i| (position[i] - prev.position[i]) / dt)
+            }
+            // Repeated stamps keep the previous estimate.
+            Some((time, prev)) if *time == log_time => prev.velocity,
+            // First sample, a new parent frame, or playback jumped back.
+            _ => [0.0; 3],
+        };
+        let sample = TargetSample {
+            parent_frame_id: parent_frame_id.to_string(),
+            position,
+            velocity,
+        };
+        self.latest.insert(child_frame_id.to_string(), (log_time, sample));
+    }
+}
+
+/// A camera that trails a moving target, lagging and damping like a real chase camera.
+pub struct ChaseCam {
+    settings: ChaseSettings,
+    position: Option<[f64; 3]>,
+    // direction of travel, kept while the target stands still
+    travel_heading: f64,
+    last_update: Instant,
+}
+
+impl ChaseCam {
+    pub fn new(settings: ChaseSettings) -> Self {
+        Self {
+            settings,
+            position: None,
+            travel_heading: 0.0,
+            last_update: Instant::now(),
+        }
+    }
+
+    /// Moves the camera toward its ideal position behind `target` and returns its new pose,
+    /// in the target's parent frame.
+    pub fn update(&mut self, target: &TargetSample) -> CameraPose {
+        let s = &self.settings;
+        let [vx, _, vz] = target.velocity;
+        if vx.hypot(vz) > 1e-3 {
+            self.travel_heading = vx.atan2(vz);
+        }
+        let (sin, cos) = self.travel_heading.sin_cos();
+        let aim = [0, 1, 2].map(|i| target.position[i] + target.velocity[i] * s.look_ahead);
+        // forward is (sin, 0, cos) and right is (cos, 0, -sin) in the XZ plane
+        let ideal = [
+            aim[0] - s.behind * sin + s.lateral * cos,
+            aim[1] + s.above,
+            aim[2] - s.behind * cos - s.lateral * sin,
+        ];
+
+        let dt = self.last_update.elapsed().as_secs_f64();
+        self.last_update = Instant::now();
+        let alpha = if s.smoothing > 0.0 {
+            1.0 - (-dt / s.smoothing).exp()
+        } else {
+            1.0
+        };
+        let position = match self.position {
+            Some(p) => [0, 1, 2].map(|i| p[i] + (ideal[i] - p[i]) * alpha),
+            None => ideal,
+        };
+        self.position = Some(position);
+
+        let heading = (aim[0] - position[0]).atan2(aim[2] - position[2]);
+        CameraPose {
+            x: position[0],
+            y: position[1],
+            z: position[2],
+            heading: heading.rem_euclid(2.0 * PI),
+            roll: 0.0,
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use foxglove::websocket::{Client, ClientChannel, ClientId};
+use parking_lot::Mutex;
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::camera_state::{CameraPhysics, CameraPose, CameraState};
+use crate::control_source::{ControlCommand, ControlContext, ControlSource};
+use crate::listeners::Listener;
+use crate::logger::{CameraTopics, ImageSettings};
+
+/// Client-publish topic that drives a client's own camera.
+pub const TELEOP_TOPIC: &str = "/sdk-teleop";
+
+/// A `geometry_msgs/Twist` in JSON, as published by Foxglove's Teleop panel.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Twist {
+    pub linear: Vector,
+    pub angular: Vector,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Vector {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl Twist {
+    /// Returns the command like key presses scaled by each component: `linear.x` speeds up or
+    /// slows down, `angular.z` steers (positive to the left), and `angular.x` rolls (positive
+    /// clockwise).
+    pub fn command(&self) -> ControlCommand {
+        ControlCommand {
+            throttle: self.linear.x,
+            steer: -self.angular.z,
+            roll: self.angular.x,
+            ..Default::default()
+        }
+    }
+}
+
+/// Drives the shared camera from [`TELEOP_TOPIC`], for when clients don't get their own
+/// cameras. The latest message from any client applies on the next tick.
+#[derive(Clone, Default)]
+pub struct TeleopSource {
+    pending: Arc<Mutex<Option<ControlCommand>>>,
+}
+
+impl TeleopSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ControlSource for TeleopSource {
+    fn name(&self) -> &str {
+        "teleop"
+    }
+
+    fn poll(&mut self, _context: &ControlContext) -> Option<ControlCommand> {
+        self.pending.lock().take()
+    }
+}
+
+impl Listener for TeleopSource {
+    fn on_message_data(&self, _client: Client, channel: &ClientChannel, payload: &[u8]) {
+        if channel.topic != TELEOP_TOPIC {
+            return;
+        }
+        match serde_json::from_slice::<Twist>(payload) {
+            Ok(twist) => *self.pending.lock() = Some(twist.command()),
+            Err(err) => warn!("Ignoring {TELEOP_TOPIC} message: {err}"),
+        }
+    }
+}
+
+/// Gives every client that publishes on [`TELEOP_TOPIC`] its own camera, so several reviewers
+/// can fly independently through the same replay.
+///
+/// A client's camera starts at the shared camera's pose and publishes on its own topics,
+/// suffixed with the client id (`/sdk-tf/<client>`, `/sdk-camera/<client>`,
+/// `/sdk-image/<client>`) in frame `camera-<client>`. It is dropped when the client
+/// unadvertises the topic, which includes disconnecting.
+pub struct ClientCameras {
+    parent_frame_id: String,
+    physics: CameraPhysics,
+    image: ImageSettings,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    cameras: HashMap<ClientId, CameraState>,
+    /// Where new cameras start.
+    shared_pose: CameraPose,
+}
+
+impl ClientCameras {
+    pub fn new(parent_frame_id: &str, physics: CameraPhysics, image: ImageSettings) -> Arc<Self> {
+        Arc::new(Self {
+            parent_frame_id: parent_frame_id.to_string(),
+            physics,
+            image,
+            state: Mutex::default(),
+        })
+    }
+
+    /// Moves every client camera and publishes its state, stamped `now_ns`. `shared` is the
+    /// camera driven by the keyboard, where new client cameras start.
+    pub fn update(&self, now_ns: u64, shared: &CameraState) {
+        let mut state = self.state.lock();
+        state.shared_pose = shared.pose();
+        for camera in state.cameras.values_mut() {
+            camera.update();
+            camera.log_state(now_ns);
+        }
+    }
+
+    fn handle_teleop(&self, client: ClientId, payload: &[u8]) -> Result<()> {
+        let twist: Twist = serde_json::from_slice(payload).context("invalid teleop command")?;
+        let mut state = self.state.lock();
+        let state = &mut *state;
+        let camera = match state.cameras.entry(client) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let camera = self.create_camera(client, &state.shared_pose)?;
+                entry.insert(camera)
+            }
+        };
+        twist.command().apply(camera);
+        Ok(())
+    }
+
+    fn create_camera(&self, client: ClientId, pose: &CameraPose) -> Result<CameraState> {
+        let suffix = u32::from(client).to_string();
+        let topics = CameraTopics::with_suffix(&suffix).context("create client camera topics")?;
+        let mut camera = CameraState::new(&self.parent_frame_id, &format!("camera-{suffix}"))
+            .with_physics(self.physics.clone())
+            .with_image_settings(self.image.clone())
+            .with_topics(topics);
+        camera.set_pose(pose);
+        info!("Client {suffix} has its own camera on /sdk-tf/{suffix}");
+        Ok(camera)
+    }
+}
+
+impl Listener for ClientCameras {
+    fn on_message_data(&self, client: Client, channel: &ClientChannel, payload: &[u8]) {
+        if channel.topic != TELEOP_TOPIC {
+            return;
+        }
+        if let Err(err) = self.handle_teleop(client.id(), payload) {
+            warn!("Ignoring {TELEOP_TOPIC} message: {err:#}");
+        }
+    }
+
+    fn on_client_unadvertise(&self, client: Client, channel: &ClientChannel) {
+        if channel.topic != TELEOP_TOPIC {
+            return;
+        }
+        if self.state.lock().cameras.remove(&client.id()).is_some() {
+            info!("Client {} released its camera", u32::from(client.id()));
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use foxglove::websocket::{Client, ClientId};
+use parking_lot::{Condvar, Mutex};
+use tracing::info;
+
+use crate::listeners::{Listener, SubscribedChannel};
+use crate::ChannelId;
+
+/// Tracks client subscriptions reported by the websocket server.
+#[derive(Default)]
+pub struct ClientTracker {
+    state: Mutex<State>,
+    changed: Condvar,
+}
+
+#[derive(Default)]
+struct State {
+    /// Number of subscribed clients per channel, by channel id.
+    subscriptions: HashMap<ChannelId, usize>,
+    /// Number of subscriptions per client.
+    clients: HashMap<ClientId, usize>,
+    /// Number of subscribe events per channel since startup.
+    subscribe_events: HashMap<ChannelId, u64>,
+}
+
+impl State {
+    fn add(&mut self, client: ClientId, channel: ChannelId) {
+        *self.subscriptions.entry(channel).or_default() += 1;
+        *self.clients.entry(client).or_default() += 1;
+        *self.subscribe_events.entry(channel).or_default() += 1;
+    }
+
+    fn remove(&mut self, client: ClientId, channel: ChannelId) {
+        decrement(&mut self.subscriptions, channel);
+        decrement(&mut self.clients, client);
+    }
+}
+
+fn decrement<K: Eq + Hash>(counts: &mut HashMap<K, usize>, key: K) {
+    if let Some(count) = counts.get_mut(&key) {
+        *count = count.saturating_sub(1);
+        if *count == 0 {
+            counts.remove(&key);
+        }
+    }
+}
+
+impl ClientTracker {
+    /// Creates a new tracker, ready to be registered as a server listener.
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// Returns true if at least one client is subscribed to the channel.
+    pub fn is_subscribed(&self, channel: ChannelId) -> bool {
+        self.state.lock().subscriptions.contains_key(&channel)
+    }
+
+    /// Returns the number of clients with at least one subscription.
+    pub fn subscribed_client_count(&self) -> usize {
+        self.state.lock().clients.len()
+    }
+
+    /// Returns the number of channels the client is subscribed to.
+    pub fn client_subscriptions(&self, client: ClientId) -> usize {
+        self.state
+            .lock()
+            .clients
+            .get(&client)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Returns how many times clients have subscribed to the channel since startup.
+    ///
+    /// Publishers of latched data compare this against the last value they saw to notice new
+    /// subscribers, who would otherwise miss messages logged before they subscribed.
+    pub fn subscribe_events(&self, channel: ChannelId) -> u64 {
+        self.state
+            .lock()
+            .subscribe_events
+            .get(&channel)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Blocks until a client subscribes to a channel, the timeout elapses, or `done` is set.
+    ///
+    /// Returns true if a subscriber is present.
+    pub fn wait_for_subscriber(&self, timeout: Option<Duration>, done: &Arc<AtomicBool>) -> bool {
+        let deadline = timeout.map(|t| Instant::now() + t);
+        let mut state = self.state.lock();
+        while state.subscriptions.is_empty() && !done.load(Ordering::Relaxed) {
+            // Wake up periodically to notice the done flag.
+            let mut wake = Instant::now() + Duration::from_millis(100);
+            if let Some(deadline) = deadline {
+                if Instant::now() >= deadline {
+                    break;
+                }
+                wake = wake.min(deadline);
+            }
+            self.changed.wait_until(&mut state, wake);
+        }
+        !state.subscriptions.is_empty()
+    }
+}
+
+impl Listener for ClientTracker {
+    fn on_subscribe(&self, client: Client, channel: SubscribedChannel) {
+        info!("Client {:?} subscribed to {}", client.id(), channel.topic());
+        self.state.lock().add(client.id(), channel.id());
+        self.changed.notify_all();
+    }
+
+    fn on_unsubscribe(&self, client: Client, channel: SubscribedChannel) {
+        info!("Client {:?} unsubscribed from {}", client.id(), channel.topic());
+        self.state.lock().remove(client.id(), channel.id());
+        self.changed.notify_all();
+    }
+}
@@ -0,0 +1,160 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use foxglove::schemas::Timestamp;
+use parking_lot::Mutex;
+use serde::Deserialize;
+
+use crate::playback::PlaybackControl;
+
+/// Which notion of "now" stamps the time broadcast to clients and the messages the SDK logs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClockMode {
+    /// The system clock, regardless of where playback is.
+    Wallclock,
+    /// The log time of the most recently replayed message; it only moves when a message does.
+    File,
+    /// File time advancing smoothly between messages at the playback rate, and standing still
+    /// while paused.
+    #[default]
+    Scaled,
+}
+
+impl ClockMode {
+    const ALL: [ClockMode; 3] = [ClockMode::Wallclock, ClockMode::File, ClockMode::Scaled];
+
+    /// Returns the next mode, for cycling through them from the keyboard.
+    pub fn next(self) -> Self {
+        Self::ALL[(self as usize + 1) % Self::ALL.len()]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ClockMode::Wallclock => "wallclock",
+            ClockMode::File => "file time",
+            ClockMode::Scaled => "scaled file time",
+        }
+    }
+}
+
+/// The single source of timestamps for a session.
+///
+/// The replay loop tells the clock where playback is with [`Clock::advance`]; everything that
+/// stamps a message or broadcasts a time reads [`Clock::now_ns`], so the Foxglove timeline, the
+/// replayed data, and the camera topics agree on what "now" is. The mode can be changed while
+/// running. Until playback starts, the file-based modes fall back to the wallclock.
+pub struct Clock {
+    mode: AtomicU8,
+    control: OnceLock<Arc<PlaybackControl>>,
+    state: Mutex<ClockState>,
+}
+
+#[derive(Default)]
+struct ClockState {
+    /// Log time of the most recently replayed message.
+    file_ns: Option<u64>,
+    /// Scaled file time at `at`, and the rate and pause state it has advanced with since.
+    scaled_ns: u64,
+    at: Option<Instant>,
+    rate: f64,
+    paused: bool,
+}
+
+impl ClockState {
+    fn scaled_now(&self) -> u64 {
+        match self.at {
+            Some(at) if !self.paused => {
+                self.scaled_ns + at.elapsed().mul_f64(self.rate).as_nanos() as u64
+            }
+            _ => self.scaled_ns,
+        }
+    }
+}
+
+impl Clock {
+    pub fn new(mode: ClockMode) -> Arc<Self> {
+        Arc::new(Self {
+            mode: AtomicU8::new(mode as u8),
+            control: OnceLock::new(),
+            state: Mutex::new(ClockState {
+                rate: 1.0,
+                ..Default::default()
+            }),
+        })
+    }
+
+    /// Follows the pause state and rate of `control` in [`ClockMode::Scaled`]. Only the first
+    /// call has an effect.
+    pub fn attach(&self, control: Arc<PlaybackControl>) {
+        let _ = self.control.set(control);
+    }
+
+    pub fn mode(&self) -> ClockMode {
+        ClockMode::ALL[self.mode.load(Ordering::Relaxed) as usize]
+    }
+
+    pub fn set_mode(&self, mode: ClockMode) {
+        self.mode.store(mode as u8, Ordering::Relaxed);
+    }
+
+    /// Records that playback reached `log_time`. Scaled time never moves backwards here, since
+    /// it may have run slightly ahead of a late message; use [`Clock::reset`] for seeks and loops.
+    pub fn advance(&self, log_time: u64) {
+        let mut state = self.state.lock();
+        state.file_ns = Some(log_time);
+        state.scaled_ns = log_time.max(state.scaled_now());
+        state.at = Some(Instant::now());
+    }
+
+    /// Moves the playback position to `log_time` when the file loops or seeks, so the clock
+    /// doesn't jump to the wallclock in between. Time stands still there until the next message.
+    /// If `log_time` isn't known, the last file time is held instead.
+    pub fn reset(&self, log_time: Option<u64>) {
+        let mut state = self.state.lock();
+        state.file_ns = log_time.or(state.file_ns);
+        state.scaled_ns = state.file_ns.unwrap_or_default();
+        state.at = None;
+    }
+
+    /// Returns the current time in nanoseconds, according to the active mode.
+    pub fn now_ns(&self) -> u64 {
+        let mode = self.mode();
+        let mut state = self.state.lock();
+        if let Some(control) = self.control.get() {
+            let (paused, rate) = (control.is_paused(), control.rate());
+            if paused != state.paused || rate != state.rate {
+                // Re-anchor so the change only affects time from here on.
+                state.scaled_ns = state.scaled_now();
+                state.at = state.at.map(|_| Instant::now());
+                state.paused = paused;
+                state.rate = rate;
+            }
+        }
+        match (mode, state.file_ns) {
+            (ClockMode::File, Some(file_ns)) => file_ns,
+            (ClockMode::Scaled, Some(_)) => state.scaled_now(),
+            _ => wallclock_ns(),
+        }
+    }
+
+    /// Returns the current time as a Foxglove timestamp, or `None` if it doesn't fit.
+    pub fn timestamp(&self) -> Option<Timestamp> {
+        timestamp(self.now_ns())
+    }
+}
+
+/// Converts nanoseconds since the epoch to a Foxglove timestamp, or `None` if it doesn't fit.
+pub fn timestamp(ns: u64) -> Option<Timestamp> {
+    let sec = u32::try_from(ns / 1_000_000_000).ok()?;
+    Some(Timestamp::new(sec, (ns % 1_000_000_000) as u32))
+}
+
+/// Returns the system time in nanoseconds since the epoch.
+pub fn wallclock_ns() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos() as u64
+}
@@ -0,0 +1,189 @@
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use parking_lot::Mutex;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use crate::mcap_replay::{OwnedMessage, Summary, SummaryOptions};
+use crate::source::MessageSource;
+
+/// Topic prefix for the second file when none is given.
+pub const DEFAULT_COMPARE_PREFIX: &str = "/b";
+
+/// Number of messages buffered from each file while merging.
+const MERGE_CAPACITY: usize = 256;
+
+/// How the second file of a comparison is published.
+#[derive(Debug, Clone)]
+pub struct CompareOptions {
+    /// Prefix for the second file's topics, e.g. `/b` to publish `/camera` as `/b/camera`.
+    pub prefix: String,
+    /// Shift applied to the second file's log times, in nanoseconds. If `None`, the second
+    /// file is shifted so both files start at the same time.
+    pub offset_ns: Option<i64>,
+}
+
+impl Default for CompareOptions {
+    fn default() -> Self {
+        Self {
+            prefix: DEFAULT_COMPARE_PREFIX.to_string(),
+            offset_ns: None,
+        }
+    }
+}
+
+/// Replays two recordings as one, e.g. a recorded run and its re-simulation.
+///
+/// The second file's channels are published under a topic prefix, and its messages are shifted
+/// onto the first file's timeline and merged with the first file's in log time order. The
+/// first file's summary is used for the start and end times and for seeking.
+pub struct CompareSource {
+    a: Arc<dyn MessageSource>,
+    b: Arc<dyn MessageSource>,
+    options: CompareOptions,
+    /// Channel id offset and log time shift for the second file, set by `load_summary`.
+    mapping: Mutex<Option<Mapping>>,
+}
+
+#[derive(Clone, Copy)]
+struct Mapping {
+    id_offset: u16,
+    shift_ns: i64,
+}
+
+impl Mapping {
+    /// Moves a message from the second file onto the first file's channels and timeline.
+    /// Returns `None` for channels whose shifted id doesn't fit, which were never created.
+    fn apply(&self, mut message: OwnedMessage) -> Option<OwnedMessage> {
+        message.header.channel_id = message.header.channel_id.checked_add(self.id_offset)?;
+        message.header.log_time = shift(message.header.log_time, self.shift_ns);
+        message.header.publish_time = shift(message.header.publish_time, self.shift_ns);
+        Some(message)
+    }
+}
+
+fn shift(time: u64, shift_ns: i64) -> u64 {
+    time.saturating_add_signed(shift_ns)
+}
+
+impl CompareSource {
+    pub fn new(
+        a: Arc<dyn MessageSource>,
+        b: Arc<dyn MessageSource>,
+        options: CompareOptions,
+    ) -> Self {
+        Self {
+            a,
+            b,
+            options,
+            mapping: Mutex::default(),
+        }
+    }
+}
+
+impl MessageSource for CompareSource {
+    fn path(&self) -> &Path {
+        self.a.path()
+    }
+
+    fn load_summary(&self, options: &SummaryOptions) -> Result<Summary> {
+        if self.a.is_streaming() || self.b.is_streaming() {
+            return Err(anyhow!("comparing needs two seekable files"));
+        }
+        let summary = self.a.load_summary(options)?;
+        let b_options = SummaryOptions {
+            topic_prefix: Some(self.options.prefix.clone()),
+            ..options.clone()
+        };
+        let b_summary = self
+            .b
+            .load_summary(&b_options)
+            .with_context(|| format!("load {}", self.b.path().display()))?;
+
+        let id_offset = summary.max_channel_id().map_or(0, |id| id + 1);
+        summary.adopt_channels(&b_summary, id_offset)?;
+        let starts = (summary.start_time(), b_summary.start_time());
+        let shift_ns = match (self.options.offset_ns, starts) {
+            (Some(offset), _) => offset,
+            (None, (Some(a_start), Some(b_start))) => a_start as i64 - b_start as i64,
+            (None, _) => {
+                warn!("Missing start times, comparing without aligning the files");
+                0
+            }
+        };
+        info!(
+            "Comparing with {} under {}, shifted by {:.3}s",
+            self.b.path().display(),
+            self.options.prefix,
+            shift_ns as f64 / 1e9
+        );
+        *self.mapping.lock() = Some(Mapping { id_offset, shift_ns });
+        Ok(summary)
+    }
+
+    fn read_messages(
+        &self,
+        tx: mpsc::Sender<OwnedMessage>,
+        done: Arc<AtomicBool>,
+        skip_until: Option<u64>,
+    ) -> BoxFuture<'static, Result<()>> {
+        let Some(mapping) = *self.mapping.lock() else {
+            return async { Err(anyhow!("summary not loaded")) }.boxed();
+        };
+        let (a_tx, mut a_rx) = mpsc::channel(MERGE_CAPACITY);
+        let (b_tx, mut b_rx) = mpsc::channel(MERGE_CAPACITY);
+        let a = self.a.read_messages(a_tx, done.clone(), skip_until);
+        let b = self.b.read_messages(
+            b_tx,
+            done,
+            skip_until.map(|t| shift(t, -mapping.shift_ns)),
+        );
+        async move {
+            let a_task = tokio::spawn(a);
+            let b_task = tokio::spawn(b);
+            let mut next_a = a_rx.recv().await;
+            let mut next_b = recv_b(&mut b_rx, mapping).await;
+            loop {
+                let take_a = match (&next_a, &next_b) {
+                    (Some(a), Some(b)) => a.header.log_time <= b.header.log_time,
+                    (Some(_), None) => true,
+                    (None, Some(_)) => false,
+                    (None, None) => break,
+                };
+                let message = if take_a {
+                    std::mem::replace(&mut next_a, a_rx.recv().await)
+                } else {
+                    let next = recv_b(&mut b_rx, mapping).await;
+                    std::mem::replace(&mut next_b, next)
+                };
+                if let Some(message) = message {
+                    if tx.send(message).await.is_err() {
+                        break;
+                    }
+                }
+            }
+            // Hanging up makes both readers stop at their next message.
+            drop(a_rx);
+            drop(b_rx);
+            a_task.await.context("reading task")??;
+            b_task.await.context("comparison reading task")??;
+            Ok(())
+        }
+        .boxed()
+    }
+}
+
+/// Receives the next message from the second file that maps onto a channel.
+async fn recv_b(rx: &mut mpsc::Receiver<OwnedMessage>, mapping: Mapping) -> Option<OwnedMessage> {
+    while let Some(message) = rx.recv().await {
+        if let Some(message) = mapping.apply(message) {
+            return Some(message);
+        }
+    }
+    None
+}
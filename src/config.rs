@@ -0,0 +1,23 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use serde::de::DeserializeOwned;
+
+/// Loads a TOML or YAML configuration file, picking the format from the file extension.
+pub fn load<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("read config {}", path.display()))?;
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    match extension.as_str() {
+        "toml" => toml::from_str(&contents).context("parse TOML config"),
+        "yaml" | "yml" => serde_yaml::from_str(&contents).context("parse YAML config"),
+        _ => Err(anyhow!(
+            "unsupported config format {:?}, expected .toml, .yaml or .yml",
+            path.display()
+        )),
+    }
+}
@@ -0,0 +1,390 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+
+use crate::camera_state::{CameraPose, CameraState};
+use crate::chase::{ChaseCam, ChaseTarget};
+use crate::keyframes::KeyframePath;
+use crate::playback::PlaybackControl;
+
+/// One tick's worth of camera input, in the same units whatever the input device.
+///
+/// Rates are step factors, as passed to [`CameraState::accelerate`] and friends: a held key
+/// is 0.2 to 0.5, a full stick deflection 1.0. Fields left at their defaults don't drive the
+/// camera.
+#[derive(Debug, Clone, Default)]
+pub struct ControlCommand {
+    /// Speed up (positive) or slow down.
+    pub throttle: f64,
+    /// Steer right (positive) or left.
+    pub steer: f64,
+    /// Roll clockwise (positive) or counterclockwise.
+    pub roll: f64,
+    /// Stop all motion before applying the rest.
+    pub stop: bool,
+    /// Jump to a pose.
+    pub pose: Option<CameraPose>,
+    /// Move smoothly to a pose.
+    pub move_to: Option<CameraPose>,
+    /// Frame to move the camera into, e.g. the parent frame of a chased target.
+    pub parent_frame_id: Option<String>,
+}
+
+impl ControlCommand {
+    /// Returns true if the command doesn't drive the camera.
+    pub fn is_empty(&self) -> bool {
+        self.throttle == 0.0
+            && self.steer == 0.0
+            && self.roll == 0.0
+            && !self.stop
+            && self.pose.is_none()
+            && self.move_to.is_none()
+            && self.parent_frame_id.is_none()
+    }
+
+    /// Fills in the parts this command leaves unset from a lower-priority command. A pose
+    /// takes the camera over, so nothing is taken from `lower` once one is set.
+    pub fn or(mut self, lower: ControlCommand) -> Self {
+        if self.pose.is_some() || self.move_to.is_some() {
+            return self;
+        }
+        let fill = |value: &mut f64, lower: f64| {
+            if *value == 0.0 {
+                *value = lower;
+            }
+        };
+        fill(&mut self.throttle, lower.throttle);
+        fill(&mut self.steer, lower.steer);
+        fill(&mut self.roll, lower.roll);
+        self.stop |= lower.stop;
+        self.pose = lower.pose;
+        self.move_to = lower.move_to;
+        self.parent_frame_id = self.parent_frame_id.or(lower.parent_frame_id);
+        self
+    }
+
+    pub fn apply(&self, camera: &mut CameraState) {
+        if let Some(frame_id) = &self.parent_frame_id {
+            camera.set_parent_frame_id(frame_id);
+        }
+        if self.stop {
+            camera.stop();
+        }
+        if let Some(pose) = &self.pose {
+            camera.set_pose(pose);
+        }
+        if let Some(pose) = &self.move_to {
+            camera.move_to(pose);
+        }
+        if self.throttle > 0.0 {
+            camera.accelerate(self.throttle);
+        } else if self.throttle < 0.0 {
+            camera.decelerate(-self.throttle);
+        }
+        if self.steer > 0.0 {
+            camera.steer_right(self.steer);
+        } else if self.steer < 0.0 {
+            camera.steer_left(-self.steer);
+        }
+        if self.roll > 0.0 {
+            camera.roll_clockwise(self.roll);
+        } else if self.roll < 0.0 {
+            camera.roll_counterclockwise(-self.roll);
+        }
+    }
+}
+
+/// What sources can look at when producing a command.
+pub struct ControlContext<'a> {
+    /// Seconds of file time since the start of the current pass.
+    pub replay_secs: f64,
+    pub camera: &'a CameraState,
+}
+
+/// An input method that drives the camera: keyboard, gamepad, teleop topic, script, ...
+pub trait ControlSource {
+    /// Name used in logs and for configuring priorities.
+    fn name(&self) -> &str;
+
+    /// Returns this tick's command, or `None` when the source has no input.
+    fn poll(&mut self, context: &ControlContext) -> Option<ControlCommand>;
+}
+
+/// Priority of each source, by name (config file `[control_priorities]` section). Higher
+/// priorities win; sources that aren't listed keep their default.
+pub type ControlPriorities = HashMap<String, i32>;
+
+/// Default priorities: absolute poses (chase, keyframes, API requests) above scripts, and
+/// scripts above manual input, so a driver can nudge a scripted camera.
+pub const DEFAULT_PRIORITIES: &[(&str, i32)] = &[
+    ("keyboard", 10),
+    ("gamepad", 20),
+    ("teleop", 30),
+    ("script", 40),
+    ("api", 50),
+    ("follow", 60),
+    ("chase", 70),
+];
+
+/// Mixes the commands of several sources by priority.
+///
+/// Every tick, each source is polled and each part of the command (throttle, steering, roll,
+/// stop, pose) is taken from the highest-priority source that sets it. A source that sets a
+/// pose takes the camera over from all lower ones.
+#[derive(Default)]
+pub struct ControlMixer {
+    // sorted by descending priority
+    sources: Vec<(i32, Box<dyn ControlSource>)>,
+    priorities: ControlPriorities,
+    active: Option<String>,
+}
+
+impl ControlMixer {
+    pub fn new(priorities: ControlPriorities) -> Self {
+        Self {
+            priorities,
+            ..Default::default()
+        }
+    }
+
+    /// Adds a source at its configured or default priority.
+    pub fn add(&mut self, source: impl ControlSource + 'static) {
+        let name = source.name();
+        let priority = self.priorities.get(name).copied().unwrap_or_else(|| {
+            DEFAULT_PRIORITIES
+                .iter()
+                .find(|(n, _)| *n == name)
+                .map_or(0, |(_, p)| *p)
+        });
+        let index = self.sources.partition_point(|(p, _)| *p >= priority);
+        self.sources.insert(index, (priority, Box::new(source)));
+    }
+
+    /// Polls every source and applies the mixed command to the camera.
+    pub fn tick(&mut self, camera: &mut CameraState, replay_secs: f64) {
+        let context = ControlContext {
+            replay_secs,
+            camera: &*camera,
+        };
+        let mut mixed: Option<ControlCommand> = None;
+        let mut active = None;
+        for (_, source) in &mut self.sources {
+            let Some(command) = source.poll(&context).filter(|c| !c.is_empty()) else {
+                continue;
+            };
+            mixed = Some(match mixed {
+                Some(higher) => higher.or(command),
+                None => {
+                    active = Some(source.name().to_string());
+                    command
+                }
+            });
+        }
+        self.active = active;
+        if let Some(command) = mixed {
+            command.apply(camera);
+        }
+    }
+
+    /// Returns the highest-priority source that had input on the last tick.
+    pub fn active(&self) -> Option<&str> {
+        self.active.as_deref()
+    }
+}
+
+/// Keys pressed in the terminal, fed by [`Controls`](crate::controls::Controls).
+#[derive(Clone, Default)]
+pub struct KeyboardSource {
+    pending: Arc<Mutex<ControlCommand>>,
+}
+
+impl KeyboardSource {
+    /// Queues the command for the next tick.
+    pub fn push(&self, command: ControlCommand) {
+        *self.pending.lock() = command;
+    }
+}
+
+impl ControlSource for KeyboardSource {
+    fn name(&self) -> &str {
+        "keyboard"
+    }
+
+    fn poll(&mut self, _context: &ControlContext) -> Option<ControlCommand> {
+        Some(std::mem::take(&mut *self.pending.lock()))
+    }
+}
+
+/// Poses requested through the HTTP API.
+pub struct PoseRequestSource {
+    control: Arc<PlaybackControl>,
+}
+
+impl PoseRequestSource {
+    pub fn new(control: Arc<PlaybackControl>) -> Self {
+        Self { control }
+    }
+}
+
+impl ControlSource for PoseRequestSource {
+    fn name(&self) -> &str {
+        "api"
+    }
+
+    fn poll(&mut self, _context: &ControlContext) -> Option<ControlCommand> {
+        self.control.take_pose_request().map(|pose| ControlCommand {
+            move_to: Some(pose),
+            ..Default::default()
+        })
+    }
+}
+
+/// Flies through keyframes saved with the keyframe editor, in sync with the replay.
+pub struct FollowSource {
+    path: KeyframePath,
+}
+
+impl FollowSource {
+    pub fn new(path: KeyframePath) -> Self {
+        Self { path }
+    }
+}
+
+impl ControlSource for FollowSource {
+    fn name(&self) -> &str {
+        "follow"
+    }
+
+    fn poll(&mut self, context: &ControlContext) -> Option<ControlCommand> {
+        self.path.pose_at(context.replay_secs).map(|pose| ControlCommand {
+            pose: Some(pose),
+            ..Default::default()
+        })
+    }
+}
+
+/// Trails a frame of the replayed transforms with a chase camera.
+pub struct ChaseSource {
+    target: Arc<ChaseTarget>,
+    cam: ChaseCam,
+}
+
+impl ChaseSource {
+    pub fn new(target: Arc<ChaseTarget>, cam: ChaseCam) -> Self {
+        Self { target, cam }
+    }
+}
+
+impl ControlSource for ChaseSource {
+    fn name(&self) -> &str {
+        "chase"
+    }
+
+    fn poll(&mut self, _context: &ControlContext) -> Option<ControlCommand> {
+        let sample = self.target.latest()?;
+        Some(ControlCommand {
+            pose: Some(self.cam.update(&sample)),
+            parent_frame_id: Some(sample.parent_frame_id),
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the same command every tick.
+    struct Fixed(&'static str, ControlCommand);
+
+    impl ControlSource for Fixed {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn poll(&mut self, _context: &ControlContext) -> Option<ControlCommand> {
+            Some(self.1.clone())
+        }
+    }
+
+    fn pose(x: f64) -> CameraPose {
+        CameraPose {
+            x,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn or_fills_unset_parts_from_lower() {
+        let higher = ControlCommand {
+            throttle: 0.5,
+            ..Default::default()
+        };
+        let lower = ControlCommand {
+            throttle: -1.0,
+            steer: 0.2,
+            stop: true,
+            ..Default::default()
+        };
+        let mixed = higher.or(lower);
+        assert_eq!(mixed.throttle, 0.5);
+        assert_eq!(mixed.steer, 0.2);
+        assert!(mixed.stop);
+    }
+
+    #[test]
+    fn pose_takes_over_from_lower() {
+        let higher = ControlCommand {
+            pose: Some(pose(1.0)),
+            ..Default::default()
+        };
+        let lower = ControlCommand {
+            throttle: 1.0,
+            pose: Some(pose(2.0)),
+            ..Default::default()
+        };
+        let mixed = higher.or(lower);
+        assert_eq!(mixed.throttle, 0.0);
+        assert_eq!(mixed.pose.map(|p| p.x), Some(1.0));
+    }
+
+    #[test]
+    fn mixer_prefers_higher_priority() {
+        let mut mixer = ControlMixer::new(ControlPriorities::from([("keyboard".to_string(), 100)]));
+        mixer.add(Fixed(
+            "script",
+            ControlCommand {
+                pose: Some(pose(1.0)),
+                ..Default::default()
+            },
+        ));
+        mixer.add(Fixed(
+            "keyboard",
+            ControlCommand {
+                pose: Some(pose(2.0)),
+                ..Default::default()
+            },
+        ));
+        let mut camera = CameraState::new("world", "camera");
+        mixer.tick(&mut camera, 0.0);
+        assert_eq!(mixer.active(), Some("keyboard"));
+        assert_eq!(camera.pose().x, 2.0);
+    }
+
+    #[test]
+    fn mixer_skips_empty_commands() {
+        let mut mixer = ControlMixer::default();
+        mixer.add(Fixed("chase", ControlCommand::default()));
+        mixer.add(Fixed(
+            "keyboard",
+            ControlCommand {
+                throttle: 0.5,
+                ..Default::default()
+            },
+        ));
+        let mut camera = CameraState::new("world", "camera");
+        mixer.tick(&mut camera, 0.0);
+        assert_eq!(mixer.active(), Some("keyboard"));
+    }
+}
@@ -1,23 +1,251 @@
-use termion::event::Key;
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::Print;
+use crossterm::terminal::{self, Clear, ClearType};
+use crossterm::{execute, queue};
 use camera_state::CameraState;
+use crate::ChannelId;
 use std::io::{self, Stdout, Write};
-use termion::raw::{IntoRawMode, RawTerminal};
-use termion::input::TermRead;
-use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+use std::path::PathBuf;
+use std::sync::{Arc, Once, OnceLock, atomic::{AtomicBool, Ordering}};
 
+use crate::bad_records::BadRecords;
 use crate::camera_state;
+use crate::clock::Clock;
+use crate::control_source::{ControlCommand, KeyboardSource};
+use crate::events::Marker;
+use crate::keyframes::{Keyframe, KeyframeList};
+use crate::decode::MessageDecoder;
+use crate::logging::LogPane;
+use crate::mcap_replay::{MutedChannels, SharedChannels, TopicEcho};
+use crate::playback::PlaybackControl;
+use crate::replay_session::ReplayClock;
+use serde::Deserialize;
+use tracing::{info, warn};
 
+/// Characters bound to each camera action (matched case-insensitively)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct KeyBindings {
+    pub forward: char,
+    pub backward: char,
+    pub left: char,
+    pub right: char,
+    pub roll_ccw: char,
+    pub roll_cw: char,
+    pub stop: char,
+    pub pause: char,
+    pub clock: char,
+    pub marker: char,
+    pub keyframe: char,
+    pub save_keyframes: char,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            forward: 'w',
+            backward: 's',
+            left: 'a',
+            right: 'd',
+            roll_ccw: 'q',
+            roll_cw: 'e',
+            stop: ' ',
+            pause: 'p',
+            clock: 'c',
+            marker: 'm',
+            keyframe: 'k',
+            save_keyframes: 'o',
+        }
+    }
+}
 
 pub struct Controls {
-    rx: std::sync::mpsc::Receiver<Key>,
+    rx: std::sync::mpsc::Receiver<KeyEvent>,
     w_pressed: bool,
     a_pressed: bool,
     s_pressed: bool,
     d_pressed: bool,
     q_pressed: bool,
     e_pressed: bool,
-    stdout: RawTerminal<Stdout>,
-    done: Option<Arc<AtomicBool>>,
+    stdout: Stdout,
+    /// Shared with the input thread, which handles Ctrl-C even while nothing polls for keys.
+    done: Arc<OnceLock<Arc<AtomicBool>>>,
+    playback: Option<Arc<PlaybackControl>>,
+    clock: Option<Arc<Clock>>,
+    replay_clock: Option<Arc<ReplayClock>>,
+    /// Start and end log times of the file, for the progress gauge.
+    time_range: Option<(u64, u64)>,
+    /// Marker waiting for its label to be typed.
+    marker_input: Option<Marker>,
+    markers: Vec<Marker>,
+    log_pane: Option<LogPane>,
+    topics: Option<TopicToggles>,
+    echo: Option<EchoPane>,
+    keyframes: Option<KeyframeEditor>,
+    bindings: KeyBindings,
+    keyboard: KeyboardSource,
+    bad_records: Option<BadRecords>,
+    closed: bool,
+}
+
+/// Keyframes captured from the camera, and the file they are saved to.
+struct KeyframeEditor {
+    list: KeyframeList,
+    path: PathBuf,
+}
+
+impl KeyframeEditor {
+    fn save(&self) {
+        match self.list.save(&self.path) {
+            Ok(()) => info!(
+                "Saved {} keyframes to {}",
+                self.list.keyframes().len(),
+                self.path.display()
+            ),
+            Err(err) => warn!("Failed to save keyframes: {err:#}"),
+        }
+    }
+}
+
+/// Topic list with a cursor, for muting channels while streaming.
+struct TopicToggles {
+    channels: SharedChannels,
+    muted: MutedChannels,
+    cursor: usize,
+}
+
+impl TopicToggles {
+    /// Returns the (channel id, topic) pairs sorted by topic.
+    fn list(&self) -> Vec<(ChannelId, String)> {
+        let mut list = self
+            .channels
+            .read()
+            .values()
+            .map(|c| (u64::from(c.id()), c.topic().to_string()))
+            .collect::<Vec<_>>();
+        list.sort_by(|a, b| a.1.cmp(&b.1));
+        list
+    }
+
+    fn move_cursor(&mut self, up: bool) {
+        let len = self.channels.read().len();
+        self.cursor = if up {
+            self.cursor.saturating_sub(1)
+        } else {
+            (self.cursor + 1).min(len.saturating_sub(1))
+        };
+    }
+
+    /// Returns the channel under the cursor.
+    fn selected(&self) -> Option<(ChannelId, String)> {
+        self.list().into_iter().nth(self.cursor)
+    }
+
+    fn toggle(&self) {
+        let Some((id, topic)) = self.selected() else {
+            return;
+        };
+        let mut muted = self.muted.write();
+        if muted.remove(&id) {
+            info!("Unmuted {topic}");
+        } else {
+            muted.insert(id);
+            info!("Muted {topic}");
+        }
+    }
+}
+
+/// Pretty-printed latest message of the echoed topic.
+struct EchoPane {
+    echo: Arc<TopicEcho>,
+    /// Decoder for the echoed channel, or why it can't be decoded.
+    decoder: Option<(ChannelId, Result<MessageDecoder, String>)>,
+    /// Channel and log time of the message in `lines`, so each message is only decoded once.
+    shown: Option<(ChannelId, u64)>,
+    lines: Vec<String>,
+}
+
+impl EchoPane {
+    /// Decodes the latest message if it changed since the last draw.
+    fn refresh(&mut self, channels: &SharedChannels) {
+        let Some(id) = self.echo.selected() else {
+            self.shown = None;
+            self.lines.clear();
+            return;
+        };
+        let Some((log_time, data)) = self.echo.latest() else {
+            self.shown = None;
+            self.lines = vec!["(waiting for a message)".to_string()];
+            return;
+        };
+        if self.shown == Some((id, log_time)) {
+            return;
+        }
+        self.shown = Some((id, log_time));
+        if !matches!(&self.decoder, Some((decoder_id, _)) if *decoder_id == id) {
+            let channel = channels.read().values().find(|c| u64::from(c.id()) == id).cloned();
+            let decoder = match channel {
+                Some(channel) => MessageDecoder::new(channel.message_encoding(), channel.schema())
+                    .map_err(|e| format!("{e:#}")),
+                None => Err("unknown channel".to_string()),
+            };
+            self.decoder = Some((id, decoder));
+        }
+        let text = match &self.decoder {
+            Some((_, Ok(decoder))) => match decoder.decode(&data) {
+                Ok(value) => serde_json::to_string_pretty(&value).unwrap_or_default(),
+                Err(e) => format!("can't decode: {e:#}"),
+            },
+            Some((_, Err(e))) => format!("can't decode: {e}"),
+            None => String::new(),
+        };
+        self.lines = std::iter::once(format!("log_time: {:.9}", log_time as f64 / 1e9))
+            .chain(text.lines().map(String::from))
+            .collect();
+    }
+}
+
+/// Terminal row (zero-based) of the status line, below the instructions.
+const STATUS_ROW: u16 = 8;
+
+/// Terminal row of the replay progress gauge, below the status line.
+const PROGRESS_ROW: u16 = STATUS_ROW + 1;
+
+/// Width of the progress gauge's bar, in characters.
+const PROGRESS_BAR_WIDTH: usize = 40;
+
+/// First terminal row of the log pane, below the progress gauge.
+const LOG_PANE_ROW: u16 = PROGRESS_ROW + 2;
+
+/// Number of log lines shown under the status line.
+const LOG_PANE_LINES: usize = 12;
+
+/// First terminal row of the topic list, below the log pane.
+const TOPIC_LIST_ROW: u16 = LOG_PANE_ROW + LOG_PANE_LINES as u16 + 1;
+
+/// Number of topics shown at once; the list scrolls to keep the cursor visible.
+const TOPIC_LIST_LINES: usize = 10;
+
+/// First terminal row of the echo pane, below the topic list.
+const ECHO_ROW: u16 = TOPIC_LIST_ROW + TOPIC_LIST_LINES as u16 + 1;
+
+/// Number of lines of the echoed message shown; the rest is cut off.
+const ECHO_LINES: usize = 16;
+
+/// First terminal row of the keyframe list, below the echo pane.
+const KEYFRAME_ROW: u16 = ECHO_ROW + ECHO_LINES as u16 + 1;
+
+/// Number of keyframes shown at once; the list scrolls to keep the cursor visible.
+const KEYFRAME_LINES: usize = 6;
+
+/// Row to leave the cursor on when the controls close.
+const BOTTOM_ROW: u16 = KEYFRAME_ROW + KEYFRAME_LINES as u16;
+
+impl Default for Controls {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
  impl Controls {
@@ -26,23 +254,43 @@ pub struct Controls {
         // Set up a channel for async keyboard input
         let (tx, rx) = std::sync::mpsc::channel();
         
-        let stdin = io::stdin();
+        let done = Arc::new(OnceLock::<Arc<AtomicBool>>::new());
         // Start a thread to handle keyboard input
-        std::thread::spawn(move || {
-            for c in stdin.keys() {
-                match c {
-                    Ok(key) => tx.send(key).unwrap(),
-                    Err(_) => {}
+        std::thread::spawn({
+            let done = done.clone();
+            move || {
+                while let Ok(event) = event::read() {
+                    // Windows also reports key releases and repeats.
+                    let Event::Key(key) = event else {
+                        continue;
+                    };
+                    if key.kind != KeyEventKind::Press {
+                        continue;
+                    }
+                    // Raw mode turns Ctrl-C into a key press instead of a signal.
+                    if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+                        if let Some(done) = done.get() {
+                            done.store(true, Ordering::Relaxed);
+                        }
+                        continue;
+                    }
+                    if tx.send(key).is_err() {
+                        break;
+                    }
                 }
             }
         });
     
         // Set terminal to raw mode 
-        let mut stdout = io::stdout().into_raw_mode().unwrap();
-        write!(stdout, "{}{}Camera control simulation started!\r\nUse WASD keys to control the camera (one at a time)\r\nPress Q/E for roll control\r\nPress SPACE to stop\r\n",
-        termion::clear::All,
-        termion::cursor::Goto(1, 1)).unwrap();
-        stdout.flush().unwrap();
+        restore_terminal_on_panic();
+        terminal::enable_raw_mode().unwrap();
+        RAW_MODE.store(true, Ordering::Relaxed);
+        let mut stdout = io::stdout();
+        execute!(stdout,
+                 Clear(ClearType::All),
+                 MoveTo(0, 0),
+                 Hide,
+                 Print("Camera control simulation started!\r\nUse WASD keys to control the camera (one at a time)\r\nPress Q/E for roll control\r\nPress SPACE to stop\r\nPress P to pause/resume playback, C to switch clocks, M to drop a marker\r\nUse the arrow keys and ENTER to mute/unmute topics\r\nPress TAB to echo the selected topic\r\nPress K to add a keyframe, PGUP/PGDN to select, DEL to delete, [ and ] to reorder, O to save\r\n")).unwrap();
         Self { 
             w_pressed: false, 
             a_pressed: false, 
@@ -52,15 +300,108 @@ pub struct Controls {
             e_pressed: false,
             rx, 
             stdout,
-            done: None,
+            done,
+            playback: None,
+            clock: None,
+            replay_clock: None,
+            time_range: None,
+            marker_input: None,
+            markers: Vec::new(),
+            log_pane: None,
+            topics: None,
+            echo: None,
+            keyframes: None,
+            keyboard: KeyboardSource::default(),
+            bad_records: None,
+            bindings: KeyBindings::default(),
+            closed: false,
         }
     }
 
+    pub fn set_key_bindings(&mut self, bindings: KeyBindings) {
+        self.bindings = bindings;
+    }
+
     pub fn set_done_flag(&mut self, done: Arc<AtomicBool>) {
-        self.done = Some(done);
+        let _ = self.done.set(done);
+    }
+
+    /// Shows recent log lines under the status line instead of letting them scroll through the
+    /// raw-mode terminal.
+    pub fn set_log_pane(&mut self, pane: LogPane) {
+        pane.capture();
+        self.log_pane = Some(pane);
+    }
+
+    /// Lists the channels under the log pane; the arrow keys move the cursor and Enter mutes or
+    /// unmutes the selected topic.
+    pub fn set_topic_toggles(&mut self, channels: SharedChannels, muted: MutedChannels) {
+        self.topics = Some(TopicToggles {
+            channels,
+            muted,
+            cursor: 0,
+        });
+    }
+
+    /// Shows the latest message of a topic under the topic list; Tab echoes the selected topic,
+    /// or stops echoing it. Needs the topic list from [`Controls::set_topic_toggles`].
+    pub fn set_topic_echo(&mut self, echo: Arc<TopicEcho>) {
+        self.echo = Some(EchoPane {
+            echo,
+            decoder: None,
+            shown: None,
+            lines: Vec::new(),
+        });
+    }
+
+    /// Lets the keyframe key capture the camera pose at the current replay time, listed under
+    /// the echo pane and saved to `path` for `--follow`.
+    pub fn set_keyframe_file(&mut self, path: PathBuf) {
+        self.keyframes = Some(KeyframeEditor {
+            list: KeyframeList::default(),
+            path,
+        });
+    }
+
+    /// Lets the clock key cycle through the clock modes.
+    pub fn set_clock(&mut self, clock: Arc<Clock>) {
+        self.clock = Some(clock);
+    }
+
+    /// Stamps markers with the replay position, as well as the clock.
+    pub fn set_replay_clock(&mut self, clock: Arc<ReplayClock>) {
+        self.replay_clock = Some(clock);
+    }
+
+    /// Shows replay progress from `start_ns` to `end_ns`, e.g. the times in the file's
+    /// Statistics record. Nothing is shown unless both are known.
+    pub fn set_time_range(&mut self, start_ns: Option<u64>, end_ns: Option<u64>) {
+        self.time_range = start_ns.zip(end_ns).filter(|(start, end)| end > start);
     }
 
-    pub fn capture_keys(&mut self, camera: &mut CameraState) {
+    /// Takes the markers dropped since the last call.
+    pub fn take_markers(&mut self) -> Vec<Marker> {
+        std::mem::take(&mut self.markers)
+    }
+
+    /// Shows the number of corrupt records skipped next to the progress gauge.
+    pub fn set_bad_records(&mut self, bad_records: BadRecords) {
+        self.bad_records = Some(bad_records);
+    }
+
+    /// Lets the pause key toggle playback.
+    pub fn set_playback_control(&mut self, playback: Arc<PlaybackControl>) {
+        self.playback = Some(playback);
+    }
+
+    /// Returns the source that key presses are fed to, to be added to the control mixer.
+    pub fn keyboard_source(&self) -> KeyboardSource {
+        self.keyboard.clone()
+    }
+
+    /// Handles pending key presses, queueing camera motion on the keyboard source.
+    pub fn capture_keys(&mut self, camera: &CameraState) {
+        let mut command = ControlCommand::default();
 
         self.w_pressed = false;
         self.a_pressed = false;
@@ -77,21 +418,109 @@ pub struct Controls {
             self.d_pressed = false;
             self.q_pressed = false;
             self.e_pressed = false;
+
+            // While a marker label is being typed, keys go to the label.
+            if let Some(marker) = &mut self.marker_input {
+                match key.code {
+                    KeyCode::Char(c) => marker.label.push(c),
+                    KeyCode::Backspace => {
+                        marker.label.pop();
+                    }
+                    KeyCode::Enter => {
+                        if let Some(marker) = self.marker_input.take() {
+                            info!("Dropped marker {:?}", marker.label);
+                            self.markers.push(marker);
+                        }
+                    }
+                    KeyCode::Esc => {
+                        self.marker_input = None;
+                        info!("Cancelled marker");
+                    }
+                    _ => {}
+                }
+                return;
+            }
             
-            match key {
-                Key::Char('w') | Key::Char('W') => self.w_pressed = true,
-                Key::Char('a') | Key::Char('A') => self.a_pressed = true, 
-                Key::Char('s') | Key::Char('S') => self.s_pressed = true,
-                Key::Char('d') | Key::Char('D') => self.d_pressed = true,
-                Key::Char('q') | Key::Char('Q') => self.q_pressed = true,
-                Key::Char('e') | Key::Char('E') => self.e_pressed = true,
-                Key::Char(' ') => {
-                    camera.stop();
+            let bindings = &self.bindings;
+            let is = |c: char, bound: char| c.eq_ignore_ascii_case(&bound);
+            match key.code {
+                KeyCode::Char(c) if is(c, bindings.forward) => self.w_pressed = true,
+                KeyCode::Char(c) if is(c, bindings.left) => self.a_pressed = true,
+                KeyCode::Char(c) if is(c, bindings.backward) => self.s_pressed = true,
+                KeyCode::Char(c) if is(c, bindings.right) => self.d_pressed = true,
+                KeyCode::Char(c) if is(c, bindings.roll_ccw) => self.q_pressed = true,
+                KeyCode::Char(c) if is(c, bindings.roll_cw) => self.e_pressed = true,
+                KeyCode::Char(c) if is(c, bindings.stop) => {
+                    command.stop = true;
+                },
+                KeyCode::Char(c) if is(c, bindings.pause) => {
+                    if let Some(playback) = &self.playback {
+                        playback.set_paused(!playback.is_paused());
+                    }
+                },
+                KeyCode::Char(c) if is(c, bindings.clock) => {
+                    if let Some(clock) = &self.clock {
+                        let mode = clock.mode().next();
+                        clock.set_mode(mode);
+                        info!("Clock: {}", mode.name());
+                    }
+                },
+                KeyCode::Char(c) if is(c, bindings.marker) => {
+                    // Stamped now; the label is typed while playback continues.
+                    self.marker_input = Some(Marker {
+                        time_ns: self.clock.as_ref().map_or(0, |clock| clock.now_ns()),
+                        replay_log_time_ns: self.replay_clock.as_ref().map_or(0, |clock| clock.now_ns()),
+                        label: String::new(),
+                    });
                 },
-                Key::Ctrl('c') => {
-                    // Set the done flag if available
-                    if let Some(done) = &self.done {
-                        done.store(true, Ordering::Relaxed);
+                KeyCode::Char(c) if is(c, bindings.keyframe) => {
+                    if let Some(editor) = &mut self.keyframes {
+                        let time = self.replay_clock.as_ref().map_or(0.0, |clock| clock.elapsed_secs());
+                        editor.list.capture(Keyframe { time, pose: camera.pose() });
+                        info!("Added keyframe at {time:.2}s");
+                    }
+                },
+                KeyCode::Char(c) if is(c, bindings.save_keyframes) => {
+                    if let Some(editor) = &self.keyframes {
+                        editor.save();
+                    }
+                },
+                KeyCode::PageUp | KeyCode::PageDown => {
+                    if let Some(editor) = &mut self.keyframes {
+                        editor.list.move_cursor(key.code == KeyCode::PageUp);
+                    }
+                },
+                KeyCode::Delete => {
+                    if let Some(removed) = self.keyframes.as_mut().and_then(|e| e.list.remove_selected()) {
+                        info!("Deleted keyframe at {:.2}s", removed.time);
+                    }
+                },
+                KeyCode::Char(c @ ('[' | ']')) => {
+                    if let Some(editor) = &mut self.keyframes {
+                        editor.list.move_selected(c == '[');
+                    }
+                },
+                KeyCode::Up | KeyCode::Down => {
+                    if let Some(topics) = &mut self.topics {
+                        topics.move_cursor(key.code == KeyCode::Up);
+                    }
+                },
+                KeyCode::Enter => {
+                    if let Some(topics) = &self.topics {
+                        topics.toggle();
+                    }
+                },
+                KeyCode::Tab => {
+                    if let (Some(topics), Some(pane)) = (&self.topics, &self.echo) {
+                        if let Some((id, topic)) = topics.selected() {
+                            if pane.echo.selected() == Some(id) {
+                                pane.echo.select(None);
+                                info!("Stopped echoing {topic}");
+                            } else {
+                                pane.echo.select(Some(id));
+                                info!("Echoing {topic}");
+                            }
+                        }
                     }
                 },
                 _ => {}
@@ -100,48 +529,244 @@ pub struct Controls {
         
         // Forward/backward movement
         if self.w_pressed {
-            camera.accelerate(0.5);
+            command.throttle = 0.5;
         } 
         if self.s_pressed {
-            camera.decelerate(0.5);
+            command.throttle = -0.5;
         }
         
         // Steering
         if self.a_pressed {
-            camera.steer_left(0.2);
+            command.steer = -0.2;
         }
         if self.d_pressed {
-            camera.steer_right(0.2);
+            command.steer = 0.2;
         }
 
         // Roll control
         if self.q_pressed {
-            camera.roll_counterclockwise(0.3);
+            command.roll = -0.3;
         }
         if self.e_pressed {
-            camera.roll_clockwise(0.3);
+            command.roll = 0.3;
         }
+        self.keyboard.push(command);
     }
 
     pub fn debug_print(&mut self, camera: &CameraState) {
         // Display current position and active controls
-        write!(self.stdout, "{}Position: ({:.2}, {:.2}, {:.2})  Velocity: {:.2}  Roll: {:.2}  {}{}{}{}{}{}",
-               termion::cursor::Goto(1, 4),
-               camera.get_translation()[0],
-               camera.get_translation()[1],
-               camera.get_translation()[2],
-               camera.get_velocity(),
-               camera.get_roll(),
-               if self.w_pressed { "W " } else { "  " },
-               if self.a_pressed { "A " } else { "  " },
-               if self.s_pressed { "S " } else { "  " },
-               if self.d_pressed { "D " } else { "  " },
-               if self.q_pressed { "Q " } else { "  " },
-               if self.e_pressed { "E " } else { "  " }).unwrap();
+        queue!(self.stdout, MoveTo(0, STATUS_ROW), Clear(ClearType::CurrentLine)).unwrap();
+        if let Some(marker) = &self.marker_input {
+            write!(self.stdout, "Marker label (ENTER to drop, ESC to cancel): {}", marker.label).unwrap();
+        } else {
+            write!(self.stdout, "Position: ({:.2}, {:.2}, {:.2})  Velocity: {:.2}  Roll: {:.2}  {}{}{}{}{}{}",
+                   camera.get_translation()[0],
+                   camera.get_translation()[1],
+                   camera.get_translation()[2],
+                   camera.get_velocity(),
+                   camera.get_roll(),
+                   if self.w_pressed { "W " } else { "  " },
+                   if self.a_pressed { "A " } else { "  " },
+                   if self.s_pressed { "S " } else { "  " },
+                   if self.d_pressed { "D " } else { "  " },
+                   if self.q_pressed { "Q " } else { "  " },
+                   if self.e_pressed { "E " } else { "  " }).unwrap();
+        }
+        self.draw_progress();
+        self.draw_log_pane();
+        self.draw_topic_list();
+        self.draw_echo_pane();
+        self.draw_keyframes();
         self.stdout.flush().unwrap();
     }
+
+    fn draw_progress(&mut self) {
+        let bad = match self.bad_records.as_ref().map(|b| b.count()) {
+            Some(count) if count > 0 => format!("  {count} bad records skipped"),
+            _ => String::new(),
+        };
+        let (Some(clock), Some((start, end))) = (&self.replay_clock, self.time_range) else {
+            if !bad.is_empty() {
+                queue!(self.stdout, MoveTo(0, PROGRESS_ROW), Clear(ClearType::CurrentLine), Print(bad.trim_start())).unwrap();
+            }
+            return;
+        };
+        let played = clock.now_ns().clamp(start, end) - start;
+        let total = end - start;
+        let fraction = played as f64 / total as f64;
+        let filled = (fraction * PROGRESS_BAR_WIDTH as f64) as usize;
+        let eta = match &self.playback {
+            Some(playback) if playback.is_paused() => "paused".to_string(),
+            Some(playback) if playback.rate() <= 0.0 => "--".to_string(),
+            playback => {
+                let rate = playback.as_ref().map_or(1.0, |p| p.rate());
+                format!("ETA {}", format_duration((total - played) as f64 / 1e9 / rate))
+            }
+        };
+        let line = format!(
+            "[{}{}] {:5.1}%  {} / {}  {}{}",
+            "#".repeat(filled),
+            "-".repeat(PROGRESS_BAR_WIDTH - filled),
+            fraction * 100.0,
+            format_duration(played as f64 / 1e9),
+            format_duration(total as f64 / 1e9),
+            eta,
+            bad,
+        );
+        queue!(self.stdout, MoveTo(0, PROGRESS_ROW), Clear(ClearType::CurrentLine), Print(line)).unwrap();
+    }
+
+    fn draw_log_pane(&mut self) {
+        let Some(pane) = &self.log_pane else {
+            return;
+        };
+        let width = terminal::size().map(|(w, _)| w as usize).unwrap_or(80);
+        queue!(self.stdout, MoveTo(0, LOG_PANE_ROW - 1), Clear(ClearType::CurrentLine), Print("-- log --")).unwrap();
+        let lines = pane.recent(LOG_PANE_LINES);
+        for row in 0..LOG_PANE_LINES {
+            let line = lines.get(row).map(String::as_str).unwrap_or_default();
+            let line: String = line.chars().take(width).collect();
+            queue!(self.stdout,
+                   MoveTo(0, LOG_PANE_ROW + row as u16),
+                   Clear(ClearType::CurrentLine),
+                   Print(line)).unwrap();
+        }
+    }
+
+    fn draw_topic_list(&mut self) {
+        let Some(topics) = &self.topics else {
+            return;
+        };
+        let width = terminal::size().map(|(w, _)| w as usize).unwrap_or(80);
+        queue!(self.stdout, MoveTo(0, TOPIC_LIST_ROW - 1), Clear(ClearType::CurrentLine), Print("-- topics --")).unwrap();
+        let list = topics.list();
+        let muted = topics.muted.read();
+        let first = (topics.cursor + 1).saturating_sub(TOPIC_LIST_LINES);
+        for row in 0..TOPIC_LIST_LINES {
+            let line = match list.get(first + row) {
+                Some((id, topic)) => format!(
+                    "{} [{}] {}",
+                    if first + row == topics.cursor { ">" } else { " " },
+                    if muted.contains(id) { " " } else { "x" },
+                    topic,
+                ),
+                None => String::new(),
+            };
+            let line: String = line.chars().take(width).collect();
+            queue!(self.stdout,
+                   MoveTo(0, TOPIC_LIST_ROW + row as u16),
+                   Clear(ClearType::CurrentLine),
+                   Print(line)).unwrap();
+        }
+    }
+
+    fn draw_echo_pane(&mut self) {
+        let (Some(pane), Some(topics)) = (&mut self.echo, &self.topics) else {
+            return;
+        };
+        pane.refresh(&topics.channels);
+        let width = terminal::size().map(|(w, _)| w as usize).unwrap_or(80);
+        let title = match pane.echo.selected() {
+            Some(id) => {
+                let channels = topics.channels.read();
+                let topic = channels.values().find(|c| u64::from(c.id()) == id).map(|c| c.topic());
+                format!("-- echo {} --", topic.unwrap_or_default())
+            }
+            None => "-- echo (TAB on a topic) --".to_string(),
+        };
+        queue!(self.stdout, MoveTo(0, ECHO_ROW - 1), Clear(ClearType::CurrentLine), Print(title)).unwrap();
+        for row in 0..ECHO_LINES {
+            let line = pane.lines.get(row).map(String::as_str).unwrap_or_default();
+            let line: String = line.chars().take(width).collect();
+            queue!(self.stdout,
+                   MoveTo(0, ECHO_ROW + row as u16),
+                   Clear(ClearType::CurrentLine),
+                   Print(line)).unwrap();
+        }
+    }
+
+    fn draw_keyframes(&mut self) {
+        let Some(editor) = &self.keyframes else {
+            return;
+        };
+        let width = terminal::size().map(|(w, _)| w as usize).unwrap_or(80);
+        let keyframes = editor.list.keyframes();
+        let title = format!("-- keyframes ({}) -> {} --", keyframes.len(), editor.path.display());
+        queue!(self.stdout, MoveTo(0, KEYFRAME_ROW - 1), Clear(ClearType::CurrentLine), Print(title)).unwrap();
+        let cursor = editor.list.cursor();
+        let first = cursor.map_or(0, |c| (c + 1).saturating_sub(KEYFRAME_LINES));
+        for row in 0..KEYFRAME_LINES {
+            let line = match keyframes.get(first + row) {
+                Some(k) => format!(
+                    "{} {:8.2}s  ({:.2}, {:.2}, {:.2})  heading {:.2}  roll {:.2}",
+                    if cursor == Some(first + row) { ">" } else { " " },
+                    k.time,
+                    k.pose.x,
+                    k.pose.y,
+                    k.pose.z,
+                    k.pose.heading,
+                    k.pose.roll,
+                ),
+                None => String::new(),
+            };
+            let line: String = line.chars().take(width).collect();
+            queue!(self.stdout,
+                   MoveTo(0, KEYFRAME_ROW + row as u16),
+                   Clear(ClearType::CurrentLine),
+                   Print(line)).unwrap();
+        }
+    }
+
+    /// Restores the terminal and stops capturing logs. Also done on drop.
     pub fn close(&mut self) {
-        // Reset terminal
-        write!(self.stdout, "{}", termion::cursor::Show).unwrap();
+        if self.closed {
+            return;
+        }
+        self.closed = true;
+        restore_terminal(&mut self.stdout);
+        if let Some(pane) = &self.log_pane {
+            pane.release();
+        }
+    }
+}
+
+impl Drop for Controls {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+/// Whether the controls currently hold the terminal in raw mode.
+static RAW_MODE: AtomicBool = AtomicBool::new(false);
+
+/// Leaves raw mode and moves the cursor below the pane, if the controls entered raw mode.
+fn restore_terminal(stdout: &mut Stdout) {
+    if RAW_MODE.swap(false, Ordering::Relaxed) {
+        let _ = execute!(stdout, MoveTo(0, BOTTOM_ROW), Show, Print("\r\n"));
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Chains a panic hook that leaves raw mode before the panic message is printed, so the message
+/// is readable and the shell is usable even if the process aborts instead of unwinding.
+fn restore_terminal_on_panic() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            restore_terminal(&mut io::stdout());
+            previous(info);
+        }));
+    });
+}
+
+/// Formats seconds as `m:ss`, or `h:mm:ss` from an hour up.
+fn format_duration(secs: f64) -> String {
+    let secs = secs.max(0.0) as u64;
+    let (h, m, s) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if h > 0 {
+        format!("{h}:{m:02}:{s:02}")
+    } else {
+        format!("{m}:{s:02}")
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,49 @@
+use anyhow::{anyhow, Context, Result};
+use foxglove::Schema;
+use prost_reflect::{DescriptorPool, DynamicMessage, MessageDescriptor, SerializeOptions};
+use serde_json::Value;
+
+/// Turns raw message data into JSON, for channels whose encoding can be decoded without
+/// generated code.
+#[derive(Debug, Clone)]
+pub enum MessageDecoder {
+    /// `json` messages, parsed as-is.
+    Json,
+    /// `protobuf` messages, decoded with the `FileDescriptorSet` embedded in the schema.
+    Protobuf(MessageDescriptor),
+}
+
+impl MessageDecoder {
+    /// Returns a decoder for a channel's message encoding and schema.
+    pub fn new(message_encoding: &str, schema: Option<&Schema>) -> Result<Self> {
+        match message_encoding {
+            "json" => Ok(Self::Json),
+            "protobuf" => {
+                let schema = schema.ok_or_else(|| anyhow!("protobuf channel has no schema"))?;
+                if schema.encoding != "protobuf" {
+                    return Err(anyhow!("unexpected schema encoding {:?}", schema.encoding));
+                }
+                let pool = DescriptorPool::decode(schema.data.as_ref())
+                    .context("parse FileDescriptorSet")?;
+                let descriptor = pool
+                    .get_message_by_name(&schema.name)
+                    .ok_or_else(|| anyhow!("{} is not in the schema's descriptors", schema.name))?;
+                Ok(Self::Protobuf(descriptor))
+            }
+            other => Err(anyhow!("can't decode {other:?} messages")),
+        }
+    }
+
+    /// Decodes one message. Protobuf fields keep their names from the `.proto` file.
+    pub fn decode(&self, data: &[u8]) -> Result<Value> {
+        match self {
+            Self::Json => serde_json::from_slice(data).context("parse JSON"),
+            Self::Protobuf(descriptor) => {
+                let message =
+                    DynamicMessage::decode(descriptor.clone(), data).context("decode protobuf")?;
+                let options = SerializeOptions::new().use_proto_field_name(true);
+                Ok(message.serialize_with_options(serde_json::value::Serializer, &options)?)
+            }
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use bytes::Bytes;
+use mcap::records::{ChunkIndex, Record};
+use mcap::sans_io::read::{LinearReader, LinearReaderOptions};
+use parking_lot::{Condvar, Mutex};
+use tokio::sync::mpsc;
+use tracing::debug;
+
+use crate::bad_records::BadRecords;
+use crate::mcap_replay::{advance_reader, map_file, OwnedMessage};
+
+/// How often a waiting reader re-checks the done flag.
+const DONE_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Sizing of the chunk decompression pool used by [`IoMode::Parallel`].
+///
+/// [`IoMode::Parallel`]: crate::IoMode::Parallel
+#[derive(Debug, Clone, Copy)]
+pub struct DecodePoolConfig {
+    /// Number of worker threads decompressing chunks.
+    pub workers: usize,
+    /// Upper bound on the uncompressed size of chunks decoded ahead of playback. A single chunk
+    /// larger than the budget is still decoded, on its own.
+    pub memory_budget: u64,
+}
+
+impl Default for DecodePoolConfig {
+    fn default() -> Self {
+        Self {
+            workers: std::thread::available_parallelism().map_or(4, |n| n.get()),
+            memory_budget: 256 << 20,
+        }
+    }
+}
+
+/// Reads messages by decompressing chunks on a pool of worker threads. This blocks, so run it
+/// with `spawn_blocking`.
+///
+/// Chunks are located through the chunk index and handed to the workers in file order, as long
+/// as the chunks already decoded but not yet sent fit in the memory budget. Messages are sent
+/// in the same order as a linear read, one chunk at a time; while the pacer works through one
+/// chunk, the next ones are already being decompressed.
+///
+/// Messages logged before `skip_until` are dropped, and chunks that end before it are never
+/// read. Chunks that can't be decoded are skipped or end the read, as `bad_records` decides.
+pub fn read_messages_parallel(
+    path: &Path,
+    chunk_indexes: &[ChunkIndex],
+    config: DecodePoolConfig,
+    tx: mpsc::Sender<OwnedMessage>,
+    done: Arc<AtomicBool>,
+    skip_until: Option<u64>,
+    bad_records: &BadRecords,
+) -> Result<()> {
+    let mmap = map_file(path)?;
+    let mut chunks: Vec<&ChunkIndex> = chunk_indexes
+        .iter()
+        .filter(|index| skip_until.is_none_or(|t| index.message_end_time >= t))
+        .collect();
+    chunks.sort_by_key(|index| index.chunk_start_offset);
+    debug!(
+        "Decoding {} chunks on {} workers",
+        chunks.len(),
+        config.workers.max(1)
+    );
+
+    let queue = DecodeQueue::new(config.memory_budget);
+    std::thread::scope(|scope| {
+        for _ in 0..config.workers.max(1) {
+            scope.spawn(|| queue.work(&mmap, &chunks, skip_until));
+        }
+        let result = queue.deliver(&chunks, &tx, &done, bad_records);
+        queue.stop();
+        result
+    })
+}
+
+/// Chunks handed out to workers and decoded chunks waiting to be sent.
+struct DecodeQueue {
+    state: Mutex<QueueState>,
+    changed: Condvar,
+    budget: u64,
+}
+
+#[derive(Default)]
+struct QueueState {
+    /// Position of the next chunk to hand out.
+    next: usize,
+    /// Uncompressed bytes of chunks handed out and not yet sent.
+    reserved: u64,
+    /// Decoded chunks by position, with their reserved size.
+    decoded: BTreeMap<usize, (u64, Result<Vec<OwnedMessage>>)>,
+    stopped: bool,
+}
+
+impl DecodeQueue {
+    fn new(budget: u64) -> Self {
+        Self {
+            state: Mutex::default(),
+            changed: Condvar::new(),
+            budget,
+        }
+    }
+
+    /// Decodes chunks until every chunk is handed out or the queue is stopped.
+    ///
+    /// Chunks are handed out in order, so the chunk the reader needs next is always in flight
+    /// or decoded, and waiting for budget can't deadlock.
+    fn work(&self, mmap: &[u8], chunks: &[&ChunkIndex], skip_until: Option<u64>) {
+        loop {
+            let (position, size) = {
+                let mut state = self.state.lock();
+                loop {
+                    if state.stopped || state.next >= chunks.len() {
+                        return;
+                    }
+                    let size = chunks[state.next].uncompressed_size;
+                    if state.reserved == 0 || state.reserved + size <= self.budget {
+                        state.reserved += size;
+                        state.next += 1;
+                        break (state.next - 1, size);
+                    }
+                    self.changed.wait(&mut state);
+                }
+            };
+            let messages = decode_chunk(mmap, chunks[position], skip_until);
+            self.state.lock().decoded.insert(position, (size, messages));
+            self.changed.notify_all();
+        }
+    }
+
+    /// Sends the messages of each chunk in order, releasing its budget once it is sent.
+    fn deliver(
+        &self,
+        chunks: &[&ChunkIndex],
+        tx: &mpsc::Sender<OwnedMessage>,
+        done: &AtomicBool,
+        bad_records: &BadRecords,
+    ) -> Result<()> {
+        for (position, index) in chunks.iter().enumerate() {
+            let (size, messages) = {
+                let mut state = self.state.lock();
+                loop {
+                    if let Some(decoded) = state.decoded.remove(&position) {
+                        break decoded;
+                    }
+                    if done.load(Ordering::Relaxed) {
+                        return Ok(());
+                    }
+                    self.changed.wait_for(&mut state, DONE_POLL_INTERVAL);
+                }
+            };
+            match messages {
+                Ok(messages) => {
+                    for message in messages {
+                        if done.load(Ordering::Relaxed) || tx.blocking_send(message).is_err() {
+                            return Ok(());
+                        }
+                    }
+                }
+                Err(err) => {
+                    let then = format!("skipping the chunk at offset {}", index.chunk_start_offset);
+                    bad_records.skip(err, &then)?;
+                }
+            }
+            self.state.lock().reserved -= size;
+            self.changed.notify_all();
+        }
+        Ok(())
+    }
+
+    /// Tells idle workers to exit.
+    fn stop(&self) {
+        self.state.lock().stopped = true;
+        self.changed.notify_all();
+    }
+}
+
+/// Parses and decompresses the chunk record at the indexed offset.
+fn decode_chunk(
+    mmap: &[u8],
+    index: &ChunkIndex,
+    skip_until: Option<u64>,
+) -> Result<Vec<OwnedMessage>> {
+    let start = index.chunk_start_offset as usize;
+    let mut input = mmap
+        .get(start..start + index.chunk_length as usize)
+        .ok_or_else(|| anyhow!("chunk at {start} is past the end of the file"))?;
+    let mut reader = LinearReader::new_with_options(LinearReaderOptions {
+        skip_start_magic: true,
+        skip_end_magic: true,
+        ..Default::default()
+    });
+    let mut messages = Vec::new();
+    while advance_reader(&mut reader, &mut input, |record| {
+        if let Record::Message { header, data } = record {
+            if skip_until.is_none_or(|t| header.log_time >= t) {
+                messages.push(OwnedMessage {
+                    header,
+                    data: Bytes::copy_from_slice(&data),
+                });
+            }
+        }
+        Ok(())
+    })
+    .with_context(|| format!("decode chunk at {start}"))?
+    {}
+    Ok(messages)
+}
@@ -0,0 +1,162 @@
+use std::cell::Cell;
+use std::fmt::{self, Write};
+use std::sync::{Arc, OnceLock};
+
+use foxglove::schemas::log::Level;
+use foxglove::schemas::Log;
+use foxglove::websocket::{Client, ClientChannel};
+use foxglove::PartialMetadata;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::Layer;
+
+use crate::client_tracker::ClientTracker;
+use crate::clock::{self, Clock};
+use crate::listeners::{Listener, SubscribedChannel};
+
+foxglove::static_typed_channel!(pub DIAGNOSTICS, "/sdk-diagnostics", foxglove::schemas::Log);
+
+/// Clock the diagnostics are stamped with, once the session has one.
+static CLOCK: OnceLock<Arc<Clock>> = OnceLock::new();
+
+thread_local! {
+    /// Set while publishing, so warnings logged by the SDK while publishing don't recurse.
+    static PUBLISHING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Stamps diagnostics with the session clock, like the camera topics, so they line up with the
+/// rest of a recording. Until then, they are stamped with the wallclock. Only the first call has
+/// an effect.
+pub fn set_clock(clock: Arc<Clock>) {
+    let _ = CLOCK.set(clock);
+}
+
+/// Publishes a `foxglove.Log` on `/sdk-diagnostics`, which also lands in a `--write` recording.
+pub fn publish(level: Level, name: &str, message: impl Into<String>) {
+    publish_at(level, name, message.into(), String::new(), 0);
+}
+
+fn publish_at(level: Level, name: &str, message: String, file: String, line: u32) {
+    if PUBLISHING.with(|p| p.replace(true)) {
+        return;
+    }
+    let now_ns = CLOCK.get().map_or_else(clock::wallclock_ns, |c| c.now_ns());
+    DIAGNOSTICS.log_with_meta(
+        &Log {
+            timestamp: clock::timestamp(now_ns),
+            level: level as i32,
+            message,
+            name: name.to_string(),
+            file,
+            line,
+        },
+        PartialMetadata {
+            sequence: None,
+            log_time: Some(now_ns),
+            publish_time: Some(now_ns),
+        },
+    );
+    PUBLISHING.with(|p| p.set(false));
+}
+
+/// Forwards warnings and errors logged anywhere in the process (late replay, decode failures,
+/// failed uploads, ...) to `/sdk-diagnostics`.
+pub struct DiagnosticsLayer;
+
+impl<S: Subscriber> Layer<S> for DiagnosticsLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let meta = event.metadata();
+        let level = match *meta.level() {
+            tracing::Level::ERROR => Level::Error,
+            tracing::Level::WARN => Level::Warning,
+            _ => return,
+        };
+        let mut message = MessageVisitor::default();
+        event.record(&mut message);
+        publish_at(
+            level,
+            meta.target(),
+            message.0,
+            meta.file().unwrap_or_default().to_string(),
+            meta.line().unwrap_or_default(),
+        );
+    }
+}
+
+/// Formats an event like the terminal output: the message, then any other fields as
+/// `key=value`.
+#[derive(Default)]
+struct MessageVisitor(String);
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if !self.0.is_empty() {
+            self.0.push(' ');
+        }
+        if field.name() == "message" {
+            let _ = write!(self.0, "{value:?}");
+        } else {
+            let _ = write!(self.0, "{}={value:?}", field.name());
+        }
+    }
+}
+
+/// Reports subscription activity on `/sdk-diagnostics`: subscriptions, unsubscriptions, and
+/// client-published channels.
+///
+/// The websocket server doesn't report connections, only subscriptions, so the counts come from
+/// the session's [`ClientTracker`], which must be registered as a listener before this one.
+pub struct SubscriptionMonitor {
+    clients: Arc<ClientTracker>,
+}
+
+impl SubscriptionMonitor {
+    pub fn new(clients: Arc<ClientTracker>) -> Arc<Self> {
+        Arc::new(Self { clients })
+    }
+}
+
+impl Listener for SubscriptionMonitor {
+    fn on_subscribe(&self, client: Client, channel: SubscribedChannel) {
+        publish(
+            Level::Info,
+            "subscription",
+            format!(
+                "Client {} subscribed to {} ({} subscribed clients)",
+                u32::from(client.id()),
+                channel.topic(),
+                self.clients.subscribed_client_count()
+            ),
+        );
+    }
+
+    fn on_unsubscribe(&self, client: Client, channel: SubscribedChannel) {
+        let id = u32::from(client.id());
+        let mut message = format!("Client {id} unsubscribed from {}", channel.topic());
+        if self.clients.client_subscriptions(client.id()) == 0 {
+            // Also the case when a client disconnects, which the server doesn't report directly.
+            message += &format!(
+                ", leaving it no subscriptions ({} subscribed clients)",
+                self.clients.subscribed_client_count()
+            );
+        }
+        publish(Level::Info, "subscription", message);
+    }
+
+    fn on_client_advertise(&self, client: Client, channel: &ClientChannel) {
+        publish(
+            Level::Info,
+            "subscription",
+            format!("Client {} advertised {}", u32::from(client.id()), channel.topic),
+        );
+    }
+
+    fn on_client_unadvertise(&self, client: Client, channel: &ClientChannel) {
+        publish(
+            Level::Info,
+            "subscription",
+            format!("Client {} unadvertised {}", u32::from(client.id()), channel.topic),
+        );
+    }
+}
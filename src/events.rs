@@ -0,0 +1,65 @@
+use std::collections::BTreeMap;
+
+use foxglove::schemas::log::Level;
+use foxglove::schemas::Log;
+use foxglove::PartialMetadata;
+use tracing::warn;
+
+use crate::clock;
+
+foxglove::static_typed_channel!(pub EVENTS, "/sdk-events", foxglove::schemas::Log);
+
+/// Name of the MCAP metadata records written for markers.
+pub const MARKER_METADATA: &str = "marker";
+
+/// A moment flagged by the pilot during a review session.
+#[derive(Debug, Clone)]
+pub struct Marker {
+    /// Session clock time when the marker was dropped.
+    pub time_ns: u64,
+    /// Log time of the replayed file when the marker was dropped, so it can be found in the
+    /// source file.
+    pub replay_log_time_ns: u64,
+    /// Optional label typed by the pilot.
+    pub label: String,
+}
+
+impl Marker {
+    /// Publishes the marker as an info-level `foxglove.Log` on `/sdk-events`, so it shows up
+    /// in the Log panel and on the timeline.
+    pub fn publish(&self) {
+        let Some(timestamp) = clock::timestamp(self.time_ns) else {
+            warn!("Timestamp out of range: {}", self.time_ns);
+            return;
+        };
+        let message = if self.label.is_empty() {
+            "marker".to_string()
+        } else {
+            self.label.clone()
+        };
+        EVENTS.log_with_meta(
+            &Log {
+                timestamp: Some(timestamp),
+                level: Level::Info as i32,
+                message,
+                name: MARKER_METADATA.to_string(),
+                file: String::new(),
+                line: 0,
+            },
+            PartialMetadata {
+                sequence: None,
+                log_time: Some(self.time_ns),
+                publish_time: Some(self.time_ns),
+            },
+        );
+    }
+
+    /// Returns the marker as MCAP metadata.
+    pub fn metadata(&self) -> BTreeMap<String, String> {
+        BTreeMap::from([
+            ("label".to_string(), self.label.clone()),
+            ("time_ns".to_string(), self.time_ns.to_string()),
+            ("replay_log_time_ns".to_string(), self.replay_log_time_ns.to_string()),
+        ])
+    }
+}
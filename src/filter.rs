@@ -0,0 +1,369 @@
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use mcap::read::ChunkFlattener;
+use mcap::records::Record;
+use mcap::{Attachment, Channel, Message, Schema};
+use tracing::{info, warn};
+
+use crate::mcap_replay::map_file;
+
+/// Opcodes from here up are reserved for private records.
+const FIRST_PRIVATE_OPCODE: u8 = 0x80;
+
+/// What to keep when rewriting an MCAP file.
+#[derive(Debug, Clone, Default)]
+pub struct FilterOptions {
+    /// Only keep these topics (all topics, if `None`). Matched before remapping.
+    pub topics: Option<HashSet<String>>,
+    /// Topics to rename, from the input name to the output name.
+    pub remaps: HashMap<String, String>,
+    /// Drop messages logged earlier than this, relative to the start of the file.
+    pub start: Option<Duration>,
+    /// Drop messages logged later than this, relative to the start of the file.
+    pub end: Option<Duration>,
+}
+
+/// Parses a `<from>=<to>` topic remap.
+pub fn parse_remap(spec: &str) -> Result<(String, String)> {
+    let (from, to) = spec
+        .split_once('=')
+        .ok_or_else(|| anyhow!("expected <from>=<to>, got {spec:?}"))?;
+    if from.is_empty() || to.is_empty() {
+        return Err(anyhow!("expected <from>=<to>, got {spec:?}"));
+    }
+    Ok((from.to_string(), to.to_string()))
+}
+
+/// Number of messages written and dropped, per output topic.
+#[derive(Debug, Default)]
+pub struct FilterReport {
+    pub written: BTreeMap<String, u64>,
+    pub dropped: u64,
+    /// Attachments and metadata records copied as-is.
+    pub attachments: u64,
+    pub metadata: u64,
+    /// Private records copied as-is.
+    pub private_records: u64,
+    /// Records with a reserved opcode this version doesn't know, which can't be written.
+    pub unknown_records: u64,
+}
+
+impl fmt::Display for FilterReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (topic, count) in &self.written {
+            writeln!(f, "{count:>10}  {topic}")?;
+        }
+        write!(
+            f,
+            "{} messages written, {} dropped",
+            self.written.values().sum::<u64>(),
+            self.dropped
+        )?;
+        if self.attachments > 0 {
+            write!(f, ", {} attachments copied", self.attachments)?;
+        }
+        if self.metadata > 0 {
+            write!(f, ", {} metadata records copied", self.metadata)?;
+        }
+        if self.private_records > 0 {
+            write!(f, ", {} private records copied", self.private_records)?;
+        }
+        if self.unknown_records > 0 {
+            write!(f, ", {} unknown records dropped", self.unknown_records)?;
+        }
+        Ok(())
+    }
+}
+
+/// Copies the messages selected by `options` from `input` to a new MCAP file at `output`.
+///
+/// Schemas and channels are only written for topics that are kept, and renamed channels keep
+/// their schema, encoding, and metadata. Attachments and metadata records are copied whatever
+/// the topic and time filters, and so are private records (opcode 0x80 and up) in the data
+/// section, in place among the messages.
+pub fn filter(input: &Path, output: &Path, options: &FilterOptions) -> Result<FilterReport> {
+    let mmap = map_file(input)?;
+
+    // Time ranges are relative to the first message; without a Statistics record, it is the
+    // first message in the data section.
+    let mut start_time = mcap::Summary::read(&mmap)
+        .ok()
+        .flatten()
+        .and_then(|summary| summary.stats)
+        .map(|stats| stats.message_start_time);
+
+    let mut out = OutputFile::create(output)?;
+    let mut report = FilterReport::default();
+    let mut schemas: HashMap<u16, Arc<Schema<'static>>> = HashMap::new();
+    // Output channel for each input channel id, or `None` if the topic is filtered out.
+    let mut channels: HashMap<u16, Option<Arc<Channel<'static>>>> = HashMap::new();
+
+    for record in ChunkFlattener::new(&mmap).context("read data")? {
+        match record.context("read data")? {
+            Record::Schema { header, data } => {
+                schemas.entry(header.id).or_insert_with(|| {
+                    Arc::new(Schema {
+                        id: header.id,
+                        name: header.name,
+                        encoding: header.encoding,
+                        data: Cow::Owned(data.into_owned()),
+                    })
+                });
+            }
+            Record::Channel(channel) => {
+                if channels.contains_key(&channel.id) {
+                    continue;
+                }
+                let schema = match channel.schema_id {
+                    0 => None,
+                    id => Some(schemas.get(&id).cloned().ok_or_else(|| {
+                        anyhow!("channel {} has unknown schema {id}", channel.id)
+                    })?),
+                };
+                let channel = Channel {
+                    id: channel.id,
+                    topic: channel.topic,
+                    schema,
+                    message_encoding: channel.message_encoding,
+                    metadata: channel.metadata,
+                };
+                channels.insert(channel.id, output_channel(&channel, options));
+            }
+            Record::Message { header, data } => {
+                let start = *start_time.get_or_insert(header.log_time);
+                let offset = Duration::from_nanos(header.log_time.saturating_sub(start));
+                if options.start.is_some_and(|t| offset < t)
+                    || options.end.is_some_and(|t| offset > t)
+                {
+                    report.dropped += 1;
+                    continue;
+                }
+
+                let channel = channels.get(&header.channel_id).ok_or_else(|| {
+                    anyhow!("message on unknown channel {}", header.channel_id)
+                })?;
+                let Some(channel) = channel else {
+                    report.dropped += 1;
+                    continue;
+                };
+                out.writer
+                    .write(&Message {
+                        channel: channel.clone(),
+                        sequence: header.sequence,
+                        log_time: header.log_time,
+                        publish_time: header.publish_time,
+                        data,
+                    })
+                    .context("write message")?;
+                *report.written.entry(channel.topic.clone()).or_default() += 1;
+            }
+            Record::Attachment { header, data, .. } => {
+                out.writer
+                    .attach(&Attachment {
+                        log_time: header.log_time,
+                        create_time: header.create_time,
+                        name: header.name,
+                        media_type: header.media_type,
+                        data,
+                    })
+                    .context("write attachment")?;
+                report.attachments += 1;
+            }
+            Record::Metadata(metadata) => {
+                out.writer.write_metadata(&metadata).context("write metadata")?;
+                report.metadata += 1;
+            }
+            Record::Unknown { opcode, data } if opcode >= FIRST_PRIVATE_OPCODE => {
+                out.write_private_record(opcode, &data)?;
+                report.private_records += 1;
+            }
+            Record::Unknown { opcode, .. } => {
+                warn!("Dropping record with unknown opcode {opcode:#04x}");
+                report.unknown_records += 1;
+            }
+            // The summary section is rebuilt by the writer.
+            Record::DataEnd(_) => break,
+            _ => {}
+        }
+    }
+
+    out.finish().context("finish output")?;
+    info!("Wrote {}", output.display());
+    Ok(report)
+}
+
+/// Returns the channel to write messages from `channel` to, or `None` if its topic is filtered
+/// out.
+fn output_channel(channel: &Channel<'_>, options: &FilterOptions) -> Option<Arc<Channel<'static>>> {
+    if options
+        .topics
+        .as_ref()
+        .is_some_and(|topics| !topics.contains(&channel.topic))
+    {
+        return None;
+    }
+    let topic = options
+        .remaps
+        .get(&channel.topic)
+        .cloned()
+        .unwrap_or_else(|| channel.topic.clone());
+    Some(Arc::new(Channel {
+        id: channel.id,
+        topic,
+        schema: channel.schema.as_ref().map(|schema| {
+            Arc::new(mcap::Schema {
+                id: schema.id,
+                name: schema.name.clone(),
+                encoding: schema.encoding.clone(),
+                data: schema.data.clone().into_owned().into(),
+            })
+        }),
+        message_encoding: channel.message_encoding.clone(),
+        metadata: channel.metadata.clone(),
+    }))
+}
+
+/// An MCAP file being written, which can also take private records: `mcap::Writer` has no API
+/// for them.
+struct OutputFile<'a> {
+    path: &'a Path,
+    writer: mcap::Writer<SharedFile>,
+    file: SharedFile,
+    has_private_records: bool,
+}
+
+impl<'a> OutputFile<'a> {
+    fn create(path: &'a Path) -> Result<Self> {
+        let file = File::create(path).with_context(|| format!("create {}", path.display()))?;
+        let file = SharedFile(Rc::new(RefCell::new(BufWriter::new(file))));
+        let writer = mcap::Writer::new(file.clone()).context("start mcap writer")?;
+        Ok(Self {
+            path,
+            writer,
+            file,
+            has_private_records: false,
+        })
+    }
+
+    /// Writes a private record to the data section, between chunks.
+    fn write_private_record(&mut self, opcode: u8, data: &[u8]) -> Result<()> {
+        self.writer.flush().context("write private record")?;
+        let mut file = self.file.0.borrow_mut();
+        file.write_all(&[opcode])?;
+        file.write_all(&(data.len() as u64).to_le_bytes())?;
+        file.write_all(data)?;
+        self.has_private_records = true;
+        Ok(())
+    }
+
+    /// Writes the summary section and footer.
+    ///
+    /// The writer's data section CRC doesn't cover private records written around it, so with
+    /// any in the file, the CRC in the Data End record is recomputed from the file.
+    fn finish(mut self) -> Result<()> {
+        self.writer.flush()?;
+        let data_end = self.file.0.borrow_mut().stream_position()?;
+        self.writer.finish()?;
+        drop(self.writer);
+        self.file.0.borrow_mut().flush()?;
+        if !self.has_private_records {
+            return Ok(());
+        }
+        let crc = crc32fast::hash(&map_file(self.path)?[..data_end as usize]);
+        let mut file = File::options().write(true).open(self.path)?;
+        // skip the Data End opcode and record length
+        file.seek(SeekFrom::Start(data_end + 9))?;
+        file.write_all(&crc.to_le_bytes())?;
+        Ok(())
+    }
+}
+
+/// The output file, shared by the MCAP writer and the private records written around it.
+#[derive(Clone)]
+struct SharedFile(Rc<RefCell<BufWriter<File>>>);
+
+impl Write for SharedFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.borrow_mut().flush()
+    }
+}
+
+impl Seek for SharedFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.0.borrow_mut().seek(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mcap::records::MessageHeader;
+
+    use super::*;
+    use crate::validate::validate;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("filter-test-{}-{name}", std::process::id()))
+    }
+
+    #[test]
+    fn private_records_survive_filter() {
+        let input = temp_path("private-in.mcap");
+        let output = temp_path("private-out.mcap");
+        let mut out = OutputFile::create(&input).unwrap();
+        let kept = out.writer.add_channel(0, "/kept", "json", &BTreeMap::new()).unwrap();
+        let dropped = out.writer.add_channel(0, "/dropped", "json", &BTreeMap::new()).unwrap();
+        let header = |channel_id, log_time| MessageHeader {
+            channel_id,
+            sequence: 0,
+            log_time,
+            publish_time: log_time,
+        };
+        out.writer.write_to_known_channel(&header(kept, 1), b"{}").unwrap();
+        out.writer.write_to_known_channel(&header(dropped, 2), b"{}").unwrap();
+        out.write_private_record(0x80, b"vendor data").unwrap();
+        out.writer.write_to_known_channel(&header(kept, 4), b"{}").unwrap();
+        out.finish().unwrap();
+        assert!(validate(&input).unwrap().is_ok());
+
+        let options = FilterOptions {
+            topics: Some(HashSet::from(["/kept".to_string()])),
+            ..Default::default()
+        };
+        let report = filter(&input, &output, &options).unwrap();
+        assert_eq!(report.private_records, 1);
+        assert_eq!(report.dropped, 1);
+        let validation = validate(&output).unwrap();
+        assert!(validation.is_ok(), "{validation}");
+
+        // The private record is still between the two kept messages.
+        let mmap = map_file(&output).unwrap();
+        let records: Vec<_> = ChunkFlattener::new(&mmap)
+            .unwrap()
+            .filter_map(|record| match record.unwrap() {
+                Record::Message { header, .. } => Some(format!("message {}", header.log_time)),
+                Record::Unknown { opcode, data } => {
+                    Some(format!("{opcode:#04x} {}", String::from_utf8_lossy(&data)))
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(records, ["message 1", "0x80 vendor data", "message 4"]);
+
+        std::fs::remove_file(input).unwrap();
+        std::fs::remove_file(output).unwrap();
+    }
+}
@@ -0,0 +1,47 @@
+use anyhow::{anyhow, Result};
+use gilrs::{Axis, Button, Gilrs};
+
+use crate::control_source::{ControlCommand, ControlContext, ControlSource};
+
+/// Stick deflection below which input is ignored, so worn sticks don't drift the camera.
+const DEADZONE: f32 = 0.15;
+
+/// Drives the camera from the first connected gamepad: the left stick speeds up, slows down,
+/// and steers, the right stick rolls, and the bottom face button (A on Xbox layouts) stops.
+pub struct GamepadSource {
+    gilrs: Gilrs,
+}
+
+impl GamepadSource {
+    pub fn open() -> Result<Self> {
+        let gilrs = Gilrs::new().map_err(|e| anyhow!("open gamepads: {e}"))?;
+        Ok(Self { gilrs })
+    }
+}
+
+impl ControlSource for GamepadSource {
+    fn name(&self) -> &str {
+        "gamepad"
+    }
+
+    fn poll(&mut self, _context: &ControlContext) -> Option<ControlCommand> {
+        // Reading events updates the cached state of every gamepad.
+        while self.gilrs.next_event().is_some() {}
+        let (_, pad) = self.gilrs.gamepads().next()?;
+        let axis = |axis: Axis| {
+            let value = pad.value(axis);
+            if value.abs() < DEADZONE {
+                0.0
+            } else {
+                f64::from(value)
+            }
+        };
+        Some(ControlCommand {
+            throttle: axis(Axis::LeftStickY),
+            steer: axis(Axis::LeftStickX),
+            roll: axis(Axis::RightStickX),
+            stop: pad.is_pressed(Button::South),
+            ..Default::default()
+        })
+    }
+}
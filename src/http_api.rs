@@ -0,0 +1,172 @@
+use std::net::ToSocketAddrs;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tiny_http::{Header, Method, Request, Response, Server};
+use tracing::{info, warn};
+
+use crate::camera_state::CameraPose;
+use crate::metrics::Metrics;
+use crate::playback::PlaybackControl;
+use crate::replay_session::ReplayClock;
+
+/// Current playback state, as returned by `GET /status`.
+#[derive(Debug, Serialize)]
+pub struct PlaybackStatus {
+    pub paused: bool,
+    pub rate: f64,
+    pub log_time_ns: u64,
+    pub elapsed_secs: f64,
+    pub camera: CameraPose,
+}
+
+#[derive(Deserialize)]
+struct SeekRequest {
+    /// Seconds from the start of the file.
+    offset_secs: f64,
+}
+
+#[derive(Deserialize)]
+struct RateRequest {
+    rate: f64,
+}
+
+/// Small JSON-over-HTTP API for controlling a replay session.
+///
+/// | Method | Path      | Body                      |
+/// |--------|-----------|---------------------------|
+/// | GET    | `/status` |                           |
+/// | POST   | `/pause`  |                           |
+/// | POST   | `/resume` |                           |
+/// | POST   | `/seek`   | `{"offset_secs": 12.5}`   |
+/// | POST   | `/rate`   | `{"rate": 2.0}`           |
+/// | GET    | `/camera` |                           |
+/// | PUT    | `/camera` | a [`CameraPose`]          |
+/// | GET    | `/metrics`| (Prometheus text format)  |
+pub struct HttpApi {
+    control: Arc<PlaybackControl>,
+    clock: Arc<ReplayClock>,
+    start_time: Option<u64>,
+    metrics: Option<Arc<Metrics>>,
+}
+
+impl HttpApi {
+    /// Creates the API. `start_time` is the log time that seek offsets are relative to; when
+    /// unknown, the first replayed message is used instead.
+    pub fn new(control: Arc<PlaybackControl>, clock: Arc<ReplayClock>, start_time: Option<u64>) -> Self {
+        Self {
+            control,
+            clock,
+            start_time,
+            metrics: None,
+        }
+    }
+
+    /// Exposes the session metrics on `GET /metrics` in the Prometheus text format.
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Serves requests on a background thread.
+    pub fn spawn(self, addr: impl ToSocketAddrs) -> Result<JoinHandle<()>> {
+        let server = Server::http(addr).map_err(|e| anyhow!("start http server: {e}"))?;
+        if let Some(addr) = server.server_addr().to_ip() {
+            info!("HTTP control API listening on http://{addr}");
+        }
+        Ok(std::thread::spawn(move || {
+            for request in server.incoming_requests() {
+                self.handle_request(request);
+            }
+        }))
+    }
+
+    fn handle_request(&self, mut request: Request) {
+        let is_metrics = *request.method() == Method::Get && request.url() == "/metrics";
+        if let (true, Some(metrics)) = (is_metrics, &self.metrics) {
+            let header = Header::from_bytes(&b"Content-Type"[..], &b"text/plain; version=0.0.4"[..])
+                .expect("valid header");
+            let response = Response::from_string(metrics.prometheus()).with_header(header);
+            if let Err(e) = request.respond(response) {
+                warn!("Failed to send http response: {e}");
+            }
+            return;
+        }
+
+        let mut body = String::new();
+        let read = request.as_reader().read_to_string(&mut body);
+        let response = match read {
+            Ok(_) => self.route(request.method(), request.url(), &body),
+            Err(e) => Err((400, e.to_string())),
+        };
+        let (status, value) = match response {
+            Ok(value) => (200, value),
+            Err((status, message)) => (status, json!({ "error": message })),
+        };
+        let header = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+            .expect("valid header");
+        let response = Response::from_string(value.to_string())
+            .with_status_code(status)
+            .with_header(header);
+        if let Err(e) = request.respond(response) {
+            warn!("Failed to send http response: {e}");
+        }
+    }
+
+    fn route(&self, method: &Method, url: &str, body: &str) -> Result<serde_json::Value, (u16, String)> {
+        let path = url.split('?').next().unwrap_or_default();
+        match (method, path) {
+            (Method::Get, "/status") => {}
+            (Method::Post, "/pause") => self.control.set_paused(true),
+            (Method::Post, "/resume") => self.control.set_paused(false),
+            (Method::Post, "/seek") => {
+                let req: SeekRequest = parse_body(body)?;
+                let start = self.start_time.unwrap_or_else(|| self.clock.start_ns());
+                let offset = std::time::Duration::try_from_secs_f64(req.offset_secs.max(0.0))
+                    .map_err(|_| (400, "offset_secs must be a number of seconds".to_string()))?;
+                let log_time = u64::try_from(offset.as_nanos())
+                    .ok()
+                    .and_then(|offset| start.checked_add(offset))
+                    .ok_or_else(|| (400, "offset_secs is past the end of time".to_string()))?;
+                self.control.seek(log_time);
+            }
+            (Method::Post, "/rate") => {
+                let req: RateRequest = parse_body(body)?;
+                if req.rate <= 0.0 || !req.rate.is_finite() {
+                    return Err((400, "rate must be positive".into()));
+                }
+                self.control.set_rate(req.rate);
+            }
+            (Method::Get, "/camera") => return to_value(&self.control.pose()),
+            (Method::Put, "/camera") | (Method::Post, "/camera") => {
+                let pose: CameraPose = parse_body(body)?;
+                self.control.request_pose(pose.clone());
+                return to_value(&pose);
+            }
+            _ => return Err((404, format!("no route for {method} {path}"))),
+        }
+        to_value(&self.status())
+    }
+
+    /// Returns the current playback status.
+    pub fn status(&self) -> PlaybackStatus {
+        PlaybackStatus {
+            paused: self.control.is_paused(),
+            rate: self.control.rate(),
+            log_time_ns: self.clock.now_ns(),
+            elapsed_secs: self.clock.elapsed_secs(),
+            camera: self.control.pose(),
+        }
+    }
+}
+
+fn parse_body<T: for<'de> Deserialize<'de>>(body: &str) -> Result<T, (u16, String)> {
+    serde_json::from_str(body).map_err(|e| (400, format!("invalid body: {e}")))
+}
+
+fn to_value<T: Serialize>(value: &T) -> Result<serde_json::Value, (u16, String)> {
+    serde_json::to_value(value).map_err(|e| (500, e.to_string()))
+}
@@ -0,0 +1,245 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use tracing::warn;
+
+use crate::mcap_replay::map_file;
+
+/// Overview of an MCAP file, as printed by the `info` subcommand.
+#[derive(Debug, Default, Serialize)]
+pub struct FileInfo {
+    pub path: String,
+    pub message_count: u64,
+    pub start_time_ns: Option<u64>,
+    pub end_time_ns: Option<u64>,
+    pub duration_secs: Option<f64>,
+    pub topics: Vec<TopicInfo>,
+    pub schemas: Vec<SchemaInfo>,
+    /// Chunk counts and sizes by compression; empty for unchunked files or files without a
+    /// summary section.
+    pub compression: BTreeMap<String, CompressionInfo>,
+    /// False if the file had no summary section and was scanned instead.
+    pub has_summary: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TopicInfo {
+    pub topic: String,
+    pub channel_id: u16,
+    pub message_encoding: String,
+    pub schema_name: Option<String>,
+    pub message_count: u64,
+    /// Average message rate over the file's duration.
+    pub frequency_hz: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SchemaInfo {
+    pub id: u16,
+    pub name: String,
+    pub encoding: String,
+    pub size: usize,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct CompressionInfo {
+    pub chunks: u64,
+    pub compressed_bytes: u64,
+    pub uncompressed_bytes: u64,
+}
+
+/// Reads the file's summary section: the Statistics record for times and message counts,
+/// Channel and Schema records for topics, and ChunkIndex records for compression.
+///
+/// Files without a summary section are scanned instead, which decompresses every chunk.
+pub fn info(path: &Path) -> Result<FileInfo> {
+    let mmap = map_file(path)?;
+    let summary = match mcap::Summary::read(&mmap) {
+        Ok(summary) => summary,
+        Err(e) => {
+            warn!("Failed to read the summary section: {e}");
+            None
+        }
+    };
+    let mut info = match summary {
+        Some(summary) => from_summary(&summary),
+        None => {
+            warn!("{} has no summary section, scanning messages", path.display());
+            scan(&mmap)?
+        }
+    };
+    info.path = path.display().to_string();
+    if let (Some(start), Some(end)) = (info.start_time_ns, info.end_time_ns) {
+        let duration = Duration::from_nanos(end.saturating_sub(start)).as_secs_f64();
+        info.duration_secs = Some(duration);
+        for topic in &mut info.topics {
+            topic.frequency_hz = (duration > 0.0).then(|| topic.message_count as f64 / duration);
+        }
+    }
+    Ok(info)
+}
+
+fn from_summary(summary: &mcap::Summary) -> FileInfo {
+    let mut info = FileInfo {
+        has_summary: true,
+        ..Default::default()
+    };
+    if let Some(stats) = &summary.stats {
+        info.message_count = stats.message_count;
+        if stats.message_count > 0 {
+            info.start_time_ns = Some(stats.message_start_time);
+            info.end_time_ns = Some(stats.message_end_time);
+        }
+    }
+    let counts = summary
+        .stats
+        .as_ref()
+        .map(|s| s.channel_message_counts.clone())
+        .unwrap_or_default();
+    for channel in summary.channels.values() {
+        info.topics.push(topic_info(channel, counts.get(&channel.id).copied().unwrap_or_default()));
+    }
+    for schema in summary.schemas.values() {
+        info.schemas.push(SchemaInfo {
+            id: schema.id,
+            name: schema.name.clone(),
+            encoding: schema.encoding.clone(),
+            size: schema.data.len(),
+        });
+    }
+    for index in &summary.chunk_indexes {
+        let compression = match index.compression.as_str() {
+            "" => "none",
+            other => other,
+        };
+        let entry = info.compression.entry(compression.to_string()).or_default();
+        entry.chunks += 1;
+        entry.compressed_bytes += index.compressed_size;
+        entry.uncompressed_bytes += index.uncompressed_size;
+    }
+    sort(&mut info);
+    info
+}
+
+fn scan(mmap: &[u8]) -> Result<FileInfo> {
+    let mut info = FileInfo::default();
+    let mut topics: BTreeMap<u16, TopicInfo> = BTreeMap::new();
+    let mut schemas: BTreeMap<u16, SchemaInfo> = BTreeMap::new();
+    for message in mcap::MessageStream::new(mmap).context("read data")? {
+        let message = match message {
+            Ok(message) => message,
+            Err(e) => {
+                warn!("Stopped scanning early: {e}");
+                break;
+            }
+        };
+        info.message_count += 1;
+        let start = info.start_time_ns.get_or_insert(message.log_time);
+        *start = (*start).min(message.log_time);
+        let end = info.end_time_ns.get_or_insert(message.log_time);
+        *end = (*end).max(message.log_time);
+        topics
+            .entry(message.channel.id)
+            .or_insert_with(|| topic_info(&message.channel, 0))
+            .message_count += 1;
+        if let Some(schema) = &message.channel.schema {
+            schemas.entry(schema.id).or_insert_with(|| SchemaInfo {
+                id: schema.id,
+                name: schema.name.clone(),
+                encoding: schema.encoding.clone(),
+                size: schema.data.len(),
+            });
+        }
+    }
+    info.topics = topics.into_values().collect();
+    info.schemas = schemas.into_values().collect();
+    sort(&mut info);
+    Ok(info)
+}
+
+fn topic_info(channel: &mcap::Channel<'_>, message_count: u64) -> TopicInfo {
+    TopicInfo {
+        topic: channel.topic.clone(),
+        channel_id: channel.id,
+        message_encoding: channel.message_encoding.clone(),
+        schema_name: channel.schema.as_ref().map(|s| s.name.clone()),
+        message_count,
+        frequency_hz: None,
+    }
+}
+
+fn sort(info: &mut FileInfo) {
+    info.topics
+        .sort_by(|a, b| a.topic.cmp(&b.topic).then(a.channel_id.cmp(&b.channel_id)));
+    info.schemas.sort_by_key(|s| s.id);
+}
+
+impl fmt::Display for FileInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "file:      {}", self.path)?;
+        writeln!(f, "messages:  {}", self.message_count)?;
+        if let (Some(start), Some(end)) = (self.start_time_ns, self.end_time_ns) {
+            writeln!(f, "start:     {}", format_time(start))?;
+            writeln!(f, "end:       {}", format_time(end))?;
+        }
+        if let Some(duration) = self.duration_secs {
+            writeln!(f, "duration:  {duration:.3}s")?;
+        }
+        if !self.has_summary {
+            writeln!(f, "summary:   missing (scanned)")?;
+        }
+        if !self.compression.is_empty() {
+            writeln!(f, "compression:")?;
+            for (name, c) in &self.compression {
+                let ratio = if c.uncompressed_bytes > 0 {
+                    c.compressed_bytes as f64 / c.uncompressed_bytes as f64 * 100.0
+                } else {
+                    100.0
+                };
+                writeln!(
+                    f,
+                    "  {name}: {} chunks, {} -> {} bytes ({ratio:.1}%)",
+                    c.chunks, c.uncompressed_bytes, c.compressed_bytes
+                )?;
+            }
+        }
+        writeln!(f, "channels:")?;
+        for t in &self.topics {
+            let frequency = t
+                .frequency_hz
+                .map(|hz| format!(" ({hz:.2} Hz)"))
+                .unwrap_or_default();
+            writeln!(
+                f,
+                "  ({}) {}  {} msgs{}  [{}: {}]",
+                t.channel_id,
+                t.topic,
+                t.message_count,
+                frequency,
+                t.message_encoding,
+                t.schema_name.as_deref().unwrap_or("no schema"),
+            )?;
+        }
+        writeln!(f, "schemas:")?;
+        for s in &self.schemas {
+            writeln!(f, "  ({}) {}  [{}, {} bytes]", s.id, s.name, s.encoding, s.size)?;
+        }
+        Ok(())
+    }
+}
+
+/// Formats a log time as seconds, plus the UTC date if it looks like a wallclock time.
+fn format_time(ns: u64) -> String {
+    let secs = ns as f64 / 1e9;
+    match chrono::DateTime::from_timestamp((ns / 1_000_000_000) as i64, (ns % 1_000_000_000) as u32) {
+        // Anything before 2000 is probably time since boot rather than a Unix time.
+        Some(date) if ns >= 946_684_800_000_000_000 => {
+            format!("{secs:.9} ({})", date.format("%Y-%m-%d %H:%M:%S%.3f UTC"))
+        }
+        _ => format!("{secs:.9}"),
+    }
+}
@@ -0,0 +1,184 @@
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+
+/// Shape of a transition over its duration.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Easing {
+    /// Constant speed.
+    Linear,
+    /// Starts slowly and accelerates.
+    EaseIn,
+    /// Starts fast and slows down into the target.
+    EaseOut,
+    /// Accelerates, then slows down into the target.
+    #[default]
+    EaseInOut,
+}
+
+impl Easing {
+    /// Maps linear progress in `[0, 1]` to eased progress in `[0, 1]`.
+    pub fn apply(self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+            // Smootherstep: zero velocity and acceleration at both ends.
+            Easing::EaseInOut => t * t * t * (t * (t * 6.0 - 15.0) + 10.0),
+        }
+    }
+}
+
+/// How the camera moves to a requested pose (config file `[transition]` section).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TransitionSettings {
+    /// Length of a transition in seconds; 0 jumps straight to the target.
+    pub duration: f64,
+    pub easing: Easing,
+}
+
+impl Default for TransitionSettings {
+    fn default() -> Self {
+        Self {
+            duration: 1.0,
+            easing: Easing::default(),
+        }
+    }
+}
+
+/// Moves a pose smoothly from one transform to another over a fixed wallclock duration.
+///
+/// Translation is interpolated linearly and rotation spherically (slerp), both driven by the
+/// same eased progress so the camera turns and travels together.
+#[derive(Debug, Clone)]
+pub struct PoseInterpolator {
+    from_translation: [f64; 3],
+    to_translation: [f64; 3],
+    from_rotation: [f64; 4],
+    to_rotation: [f64; 4],
+    duration: Duration,
+    easing: Easing,
+    started: Instant,
+}
+
+impl PoseInterpolator {
+    /// Starts a transition now. Rotations are quaternions (x, y, z, w).
+    pub fn new(
+        from: (&[f64], &[f64]),
+        to: (&[f64], &[f64]),
+        settings: &TransitionSettings,
+    ) -> Self {
+        Self {
+            from_translation: [from.0[0], from.0[1], from.0[2]],
+            to_translation: [to.0[0], to.0[1], to.0[2]],
+            from_rotation: [from.1[0], from.1[1], from.1[2], from.1[3]],
+            to_rotation: [to.1[0], to.1[1], to.1[2], to.1[3]],
+            duration: Duration::from_secs_f64(settings.duration.max(0.0)),
+            easing: settings.easing,
+            started: Instant::now(),
+        }
+    }
+
+    /// Returns true once the transition has reached its target.
+    pub fn is_done(&self) -> bool {
+        self.started.elapsed() >= self.duration
+    }
+
+    /// Returns the translation and rotation for the current time.
+    pub fn sample(&self) -> ([f64; 3], [f64; 4]) {
+        let progress = if self.duration.is_zero() {
+            1.0
+        } else {
+            self.started.elapsed().as_secs_f64() / self.duration.as_secs_f64()
+        };
+        self.sample_at(progress)
+    }
+
+    /// Returns the translation and rotation at linear progress `t` in `[0, 1]`.
+    pub fn sample_at(&self, t: f64) -> ([f64; 3], [f64; 4]) {
+        let t = self.easing.apply(t);
+        let (a, b) = (self.from_translation, self.to_translation);
+        let translation = [
+            a[0] + (b[0] - a[0]) * t,
+            a[1] + (b[1] - a[1]) * t,
+            a[2] + (b[2] - a[2]) * t,
+        ];
+        (translation, slerp(self.from_rotation, self.to_rotation, t))
+    }
+}
+
+/// Spherical linear interpolation between unit quaternions (x, y, z, w), along the shorter arc.
+pub fn slerp(a: [f64; 4], b: [f64; 4], t: f64) -> [f64; 4] {
+    let mut dot = a[0] * b[0] + a[1] * b[1] + a[2] * b[2] + a[3] * b[3];
+    // q and -q are the same rotation; flip one to take the short way around.
+    let b = if dot < 0.0 {
+        dot = -dot;
+        [-b[0], -b[1], -b[2], -b[3]]
+    } else {
+        b
+    };
+    let (wa, wb) = if dot > 0.9995 {
+        // Nearly identical: fall back to lerp to avoid dividing by sin(~0).
+        (1.0 - t, t)
+    } else {
+        let theta = dot.acos();
+        let sin = theta.sin();
+        (((1.0 - t) * theta).sin() / sin, (t * theta).sin() / sin)
+    };
+    let q = [
+        wa * a[0] + wb * b[0],
+        wa * a[1] + wb * b[1],
+        wa * a[2] + wb * b[2],
+        wa * a[3] + wb * b[3],
+    ];
+    let norm = q.iter().map(|c| c * c).sum::<f64>().sqrt();
+    q.map(|c| c / norm)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: &[f64], b: &[f64]) {
+        for (a, b) in a.iter().zip(b) {
+            assert!((a - b).abs() < 1e-9, "{a} != {b}");
+        }
+    }
+
+    #[test]
+    fn easings_start_and_end_in_place() {
+        for easing in [Easing::Linear, Easing::EaseIn, Easing::EaseOut, Easing::EaseInOut] {
+            assert_eq!(easing.apply(0.0), 0.0);
+            assert_eq!(easing.apply(1.0), 1.0);
+            // Progress outside the transition is clamped.
+            assert_eq!(easing.apply(-1.0), 0.0);
+            assert_eq!(easing.apply(2.0), 1.0);
+        }
+        assert_eq!(Easing::EaseInOut.apply(0.5), 0.5);
+        assert!(Easing::EaseIn.apply(0.5) < 0.5);
+        assert!(Easing::EaseOut.apply(0.5) > 0.5);
+    }
+
+    #[test]
+    fn slerp_halfway_about_one_axis() {
+        let identity = [0.0, 0.0, 0.0, 1.0];
+        let half = std::f64::consts::FRAC_PI_4;
+        // 90 degrees about Y
+        let quarter = [0.0, half.sin(), 0.0, half.cos()];
+        let eighth = std::f64::consts::FRAC_PI_8;
+        assert_close(&slerp(identity, quarter, 0.5), &[0.0, eighth.sin(), 0.0, eighth.cos()]);
+        assert_close(&slerp(identity, quarter, 0.0), &identity);
+        assert_close(&slerp(identity, quarter, 1.0), &quarter);
+    }
+
+    #[test]
+    fn slerp_takes_the_short_way_around() {
+        let identity = [0.0, 0.0, 0.0, 1.0];
+        let negated = [0.0, 0.0, 0.0, -1.0];
+        // q and -q are the same rotation, so there is nothing to interpolate.
+        assert_close(&slerp(identity, negated, 0.5), &identity);
+    }
+}
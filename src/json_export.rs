@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context, Result};
+use foxglove::Channel;
+use mcap::records::MessageHeader;
+use serde_json::json;
+use tracing::{info, warn};
+
+use crate::decode::MessageDecoder;
+use crate::ChannelId;
+
+/// Parses a `<topic>=<file>` export.
+pub fn parse_export_spec(spec: &str) -> Result<(String, PathBuf)> {
+    let (topic, path) = spec
+        .split_once('=')
+        .ok_or_else(|| anyhow!("expected <topic>=<file>, got {spec:?}"))?;
+    if topic.is_empty() || path.is_empty() {
+        return Err(anyhow!("expected <topic>=<file>, got {spec:?}"));
+    }
+    Ok((topic.to_string(), PathBuf::from(path)))
+}
+
+/// Writes decoded messages as JSON Lines, one file per exported topic.
+///
+/// Each line is an object with the message's `log_time`, `publish_time`, `sequence`, and
+/// `topic`, and the decoded `message`. Only JSON channels and protobuf channels with an embedded
+/// descriptor can be decoded; a topic that can't be is reported once and left empty.
+pub struct JsonExport {
+    targets: HashMap<String, Target>,
+}
+
+struct Target {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    /// Decoder per channel on the topic, or `None` if the channel can't be decoded.
+    decoders: HashMap<ChannelId, Option<MessageDecoder>>,
+    failed: bool,
+}
+
+impl JsonExport {
+    /// Creates (or truncates) a file for each `(topic, path)` pair.
+    pub fn create(specs: &[(String, PathBuf)]) -> Result<Self> {
+        let mut targets = HashMap::new();
+        for (topic, path) in specs {
+            let file = File::create(path).with_context(|| format!("create {}", path.display()))?;
+            info!("Exporting {topic} to {}", path.display());
+            targets.insert(
+                topic.clone(),
+                Target {
+                    path: path.clone(),
+                    writer: BufWriter::new(file),
+                    decoders: HashMap::new(),
+                    failed: false,
+                },
+            );
+        }
+        Ok(Self { targets })
+    }
+
+    /// Appends a message if its topic is exported.
+    pub fn write(&mut self, channel: &Channel, header: &MessageHeader, data: &[u8]) {
+        let Some(target) = self.targets.get_mut(channel.topic()) else {
+            return;
+        };
+        if target.failed {
+            return;
+        }
+        let decoder = target.decoders.entry(u64::from(channel.id())).or_insert_with(|| {
+            MessageDecoder::new(channel.message_encoding(), channel.schema())
+                .inspect_err(|e| warn!("Not exporting {}: {e:#}", channel.topic()))
+                .ok()
+        });
+        let Some(decoder) = decoder else {
+            return;
+        };
+        let message = match decoder.decode(data) {
+            Ok(message) => message,
+            Err(e) => {
+                warn!("Skipping a message on {}: {e:#}", channel.topic());
+                return;
+            }
+        };
+        let line = json!({
+            "log_time": header.log_time,
+            "publish_time": header.publish_time,
+            "sequence": header.sequence,
+            "topic": channel.topic(),
+            "message": message,
+        });
+        if let Err(e) = writeln!(target.writer, "{line}") {
+            warn!("Stopped exporting to {}: {e}", target.path.display());
+            target.failed = true;
+        }
+    }
+
+    /// Flushes every file.
+    pub fn flush(&mut self) -> Result<()> {
+        for target in self.targets.values_mut() {
+            target
+                .writer
+                .flush()
+                .with_context(|| format!("write {}", target.path.display()))?;
+        }
+        Ok(())
+    }
+}
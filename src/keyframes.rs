@@ -0,0 +1,205 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::camera_state::CameraPose;
+use crate::interpolate::{slerp, Easing};
+
+/// Default file that keyframes are saved to.
+pub const DEFAULT_KEYFRAME_FILE: &str = "keyframes.json";
+
+/// A camera pose to pass through at a point in the replay.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Keyframe {
+    /// Seconds of file time since the start of the pass.
+    pub time: f64,
+    #[serde(flatten)]
+    pub pose: CameraPose,
+}
+
+/// Keyframes captured while exploring, kept in time order, with a cursor for editing.
+#[derive(Debug, Default)]
+pub struct KeyframeList {
+    keyframes: Vec<Keyframe>,
+    cursor: usize,
+}
+
+impl KeyframeList {
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    /// Index of the selected keyframe, if there are any.
+    pub fn cursor(&self) -> Option<usize> {
+        (!self.keyframes.is_empty()).then_some(self.cursor)
+    }
+
+    /// Adds a keyframe in time order and selects it.
+    pub fn capture(&mut self, keyframe: Keyframe) {
+        let index = self.keyframes.partition_point(|k| k.time <= keyframe.time);
+        self.keyframes.insert(index, keyframe);
+        self.cursor = index;
+    }
+
+    /// Moves the cursor up or down.
+    pub fn move_cursor(&mut self, up: bool) {
+        if up {
+            self.cursor = self.cursor.saturating_sub(1);
+        } else if self.cursor + 1 < self.keyframes.len() {
+            self.cursor += 1;
+        }
+    }
+
+    /// Removes the selected keyframe.
+    pub fn remove_selected(&mut self) -> Option<Keyframe> {
+        if self.keyframes.is_empty() {
+            return None;
+        }
+        let removed = self.keyframes.remove(self.cursor);
+        self.cursor = self.cursor.min(self.keyframes.len().saturating_sub(1));
+        Some(removed)
+    }
+
+    /// Swaps the selected pose with the previous or next one, keeping the times in place, so
+    /// the path visits the poses in the new order.
+    pub fn move_selected(&mut self, earlier: bool) {
+        let other = if earlier {
+            self.cursor.checked_sub(1)
+        } else {
+            Some(self.cursor + 1).filter(|&i| i < self.keyframes.len())
+        };
+        let Some(other) = other else {
+            return;
+        };
+        let pose = self.keyframes[self.cursor].pose.clone();
+        self.keyframes[self.cursor].pose = std::mem::replace(&mut self.keyframes[other].pose, pose);
+        self.cursor = other;
+    }
+
+    /// Writes the keyframes as JSON, for `--follow`.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let file = File::create(path).with_context(|| format!("create {}", path.display()))?;
+        let mut writer = BufWriter::new(file);
+        serde_json::to_writer_pretty(&mut writer, &self.keyframes).context("write keyframes")?;
+        writer.flush().context("write keyframes")?;
+        Ok(())
+    }
+}
+
+/// Flies the camera through saved keyframes in sync with the replay.
+///
+/// Each leg between consecutive keyframes is eased like a `[transition]` (linear by default):
+/// the position along a straight line and the orientation by slerp, so the camera turns the
+/// shorter way around. Before the first and after the last keyframe, the camera holds that
+/// keyframe's pose.
+pub struct KeyframePath {
+    keyframes: Vec<Keyframe>,
+    easing: Easing,
+}
+
+impl KeyframePath {
+    /// Loads keyframes saved by the keyframe editor.
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = File::open(path).with_context(|| format!("open {}", path.display()))?;
+        let mut keyframes: Vec<Keyframe> =
+            serde_json::from_reader(BufReader::new(file)).context("parse keyframes")?;
+        if keyframes.is_empty() {
+            return Err(anyhow!("{} has no keyframes", path.display()));
+        }
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Ok(Self {
+            keyframes,
+            easing: Easing::Linear,
+        })
+    }
+
+    /// Eases each leg of the path, e.g. to slow down into every keyframe.
+    pub fn with_easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.keyframes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keyframes.is_empty()
+    }
+
+    /// Returns the pose at `time` seconds since the start of the pass.
+    pub fn pose_at(&self, time: f64) -> Option<CameraPose> {
+        let next = self.keyframes.partition_point(|k| k.time <= time);
+        let (a, b) = match (next.checked_sub(1), self.keyframes.get(next)) {
+            (Some(prev), Some(b)) => (&self.keyframes[prev], b),
+            (Some(prev), None) => return Some(self.keyframes[prev].pose.clone()),
+            (None, _) => return self.keyframes.first().map(|k| k.pose.clone()),
+        };
+        let t = self.easing.apply((time - a.time) / (b.time - a.time));
+        let lerp = |from: f64, to: f64| from + (to - from) * t;
+        let (a, b) = (&a.pose, &b.pose);
+        Some(CameraPose::from_transform(
+            [lerp(a.x, b.x), lerp(a.y, b.y), lerp(a.z, b.z)],
+            slerp(a.rotation(), b.rotation(), t),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use super::*;
+
+    fn keyframe(time: f64, x: f64, heading: f64) -> Keyframe {
+        Keyframe {
+            time,
+            pose: CameraPose {
+                x,
+                heading,
+                ..Default::default()
+            },
+        }
+    }
+
+    fn path(keyframes: Vec<Keyframe>) -> KeyframePath {
+        KeyframePath {
+            keyframes,
+            easing: Easing::Linear,
+        }
+    }
+
+    #[test]
+    fn holds_the_ends() {
+        let path = path(vec![keyframe(1.0, 1.0, 0.0), keyframe(2.0, 2.0, 0.0)]);
+        assert_eq!(path.pose_at(0.0).unwrap().x, 1.0);
+        assert_eq!(path.pose_at(3.0).unwrap().x, 2.0);
+    }
+
+    #[test]
+    fn interpolates_between_keyframes() {
+        let path = path(vec![keyframe(0.0, 0.0, 0.0), keyframe(2.0, 4.0, PI / 2.0)]);
+        let pose = path.pose_at(1.0).unwrap();
+        assert!((pose.x - 2.0).abs() < 1e-9);
+        assert!((pose.heading - PI / 4.0).abs() < 1e-9);
+        assert!(pose.roll.abs() < 1e-9 || (pose.roll - 2.0 * PI).abs() < 1e-9);
+    }
+
+    #[test]
+    fn turns_the_short_way_around() {
+        let path = path(vec![keyframe(0.0, 0.0, 0.1), keyframe(1.0, 0.0, 2.0 * PI - 0.1)]);
+        let heading = path.pose_at(0.5).unwrap().heading;
+        // Through 0 rather than through PI.
+        assert!(!(1e-9..=2.0 * PI - 1e-9).contains(&heading), "heading {heading}");
+    }
+
+    #[test]
+    fn eases_each_leg() {
+        let path = path(vec![keyframe(0.0, 0.0, 0.0), keyframe(1.0, 1.0, 0.0)])
+            .with_easing(Easing::EaseIn);
+        assert!(path.pose_at(0.5).unwrap().x < 0.5);
+    }
+}
@@ -0,0 +1,68 @@
+//! Streams MCAP files to Foxglove over websocket alongside a controllable virtual camera.
+
+pub mod bad_records;
+pub mod bench;
+pub mod camera_state;
+pub mod chapters;
+pub mod chase;
+pub mod client_cameras;
+pub mod client_tracker;
+pub mod clock;
+pub mod compare;
+pub mod config;
+pub mod control_source;
+pub mod controls;
+pub mod decode;
+pub mod decode_pool;
+pub mod diagnostics;
+pub mod events;
+pub mod filter;
+#[cfg(feature = "gamepad")]
+pub mod gamepad;
+#[cfg(feature = "http")]
+pub mod http_api;
+pub mod info;
+pub mod interpolate;
+pub mod json_export;
+pub mod keyframes;
+pub mod listeners;
+pub mod logger;
+pub mod logging;
+pub mod mcap_replay;
+pub mod metrics;
+pub mod noise;
+pub mod overlay;
+pub mod playback;
+pub mod playback_remote;
+pub mod progress;
+pub mod recording;
+pub mod replay_session;
+#[cfg(feature = "ros2")]
+pub mod ros2_bridge;
+#[cfg(feature = "rosbag1")]
+pub mod rosbag1;
+#[cfg(feature = "rosbag2")]
+pub mod rosbag2;
+pub mod scene;
+pub mod schema_override;
+#[cfg(feature = "scripting")]
+pub mod scripting;
+pub mod snapshot;
+pub mod source;
+pub mod synthetic;
+#[cfg(test)]
+mod test_util;
+pub mod throttle;
+pub mod trajectory;
+#[cfg(feature = "upload")]
+pub mod upload;
+pub mod validate;
+
+pub use camera_state::CameraState as CameraRig;
+pub use mcap_replay::{FileStream, IoMode, LatePolicy, Summary, SummaryOptions, TimeTracker};
+pub use replay_session::{ReplayClock, ReplaySession, ReplaySessionBuilder};
+pub use source::MessageSource;
+
+/// Key for per-channel maps and sets. The foxglove SDK doesn't export its channel ID type, so
+/// channels are keyed by its numeric value, `u64::from(channel.id())`.
+pub type ChannelId = u64;
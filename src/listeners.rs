@@ -0,0 +1,92 @@
+use std::sync::Arc;
+
+use foxglove::websocket::{ChannelView, Client, ClientChannel, ServerListener};
+
+use crate::ChannelId;
+
+/// A channel that a client subscribed or unsubscribed to.
+///
+/// The server hands out a [`ChannelView`], which can't be cloned, so [`Listeners`] copies it into
+/// this to pass the same event on to every listener.
+#[derive(Debug, Clone, Copy)]
+pub struct SubscribedChannel<'a> {
+    id: ChannelId,
+    topic: &'a str,
+}
+
+impl SubscribedChannel<'_> {
+    pub fn id(&self) -> ChannelId {
+        self.id
+    }
+
+    pub fn topic(&self) -> &str {
+        self.topic
+    }
+}
+
+/// The websocket server events that [`Listeners`] forwards, mirroring [`ServerListener`].
+pub trait Listener: Send + Sync {
+    fn on_subscribe(&self, _client: Client, _channel: SubscribedChannel) {}
+    fn on_unsubscribe(&self, _client: Client, _channel: SubscribedChannel) {}
+    fn on_client_advertise(&self, _client: Client, _channel: &ClientChannel) {}
+    fn on_client_unadvertise(&self, _client: Client, _channel: &ClientChannel) {}
+    fn on_message_data(&self, _client: Client, _channel: &ClientChannel, _payload: &[u8]) {}
+}
+
+/// Forwards websocket server events to several listeners, since the server takes only one.
+#[derive(Default)]
+pub struct Listeners {
+    listeners: Vec<Arc<dyn Listener>>,
+}
+
+impl Listeners {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a listener; events are delivered in the order listeners were added.
+    pub fn with(mut self, listener: Arc<dyn Listener>) -> Self {
+        self.listeners.push(listener);
+        self
+    }
+}
+
+impl ServerListener for Listeners {
+    fn on_subscribe(&self, client: Client, channel: ChannelView) {
+        let channel = SubscribedChannel {
+            id: u64::from(channel.id()),
+            topic: channel.topic(),
+        };
+        for listener in &self.listeners {
+            listener.on_subscribe(client.clone(), channel);
+        }
+    }
+
+    fn on_unsubscribe(&self, client: Client, channel: ChannelView) {
+        let channel = SubscribedChannel {
+            id: u64::from(channel.id()),
+            topic: channel.topic(),
+        };
+        for listener in &self.listeners {
+            listener.on_unsubscribe(client.clone(), channel);
+        }
+    }
+
+    fn on_client_advertise(&self, client: Client, channel: &ClientChannel) {
+        for listener in &self.listeners {
+            listener.on_client_advertise(client.clone(), channel);
+        }
+    }
+
+    fn on_client_unadvertise(&self, client: Client, channel: &ClientChannel) {
+        for listener in &self.listeners {
+            listener.on_client_unadvertise(client.clone(), channel);
+        }
+    }
+
+    fn on_message_data(&self, client: Client, channel: &ClientChannel, payload: &[u8]) {
+        for listener in &self.listeners {
+            listener.on_message_data(client.clone(), channel, payload);
+        }
+    }
+}
@@ -1,48 +1,205 @@
 use std::f64::consts::PI;
 
-use foxglove::schemas::{CameraCalibration, FrameTransform, RawImage, Timestamp, Vector3, Quaternion};
-
-foxglove::static_typed_channel!(pub(crate) CAMERA, "/sdk-camera", foxglove::schemas::CameraCalibration);
-foxglove::static_typed_channel!(pub(crate) IMAGE, "/sdk-image", foxglove::schemas::RawImage);
-foxglove::static_typed_channel!(pub(crate) TF, "/sdk-tf", foxglove::schemas::FrameTransform);
-
-const IMAGE_WIDTH: u32 = 1600;
-const IMAGE_HEIGHT: u32 = 900;
-
-pub fn log_camera_calibration(frame_id: &str) {
-    let timestamp_sec = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs_f64();
-    let timestamp = match Timestamp::try_from_epoch_secs_f64(timestamp_sec) {
-        Ok(timestamp) => timestamp,
-        Err(e) => {
-            eprintln!("Error converting timestamp: {}", e);
-            return;
+use foxglove::schemas::{CameraCalibration, FrameTransform, FrameTransforms, RawImage, Timestamp, Vector3, Quaternion};
+use foxglove::{FoxgloveError, PartialMetadata, TypedChannel};
+use serde::Deserialize;
+use tracing::warn;
+
+use crate::clock;
+use crate::overlay::Overlay;
+
+foxglove::static_typed_channel!(pub CAMERA, "/sdk-camera", foxglove::schemas::CameraCalibration);
+foxglove::static_typed_channel!(pub IMAGE, "/sdk-image", foxglove::schemas::RawImage);
+foxglove::static_typed_channel!(pub TF, "/sdk-tf", foxglove::schemas::FrameTransform);
+foxglove::static_typed_channel!(pub DEPTH, "/sdk-depth", foxglove::schemas::RawImage);
+foxglove::static_typed_channel!(pub TRANSFORMS, "/sdk-transforms", foxglove::schemas::FrameTransforms);
+
+/// Channels for a camera other than the shared one, suffixed like `/sdk-tf/<suffix>`
+pub struct CameraTopics {
+    camera: TypedChannel<CameraCalibration>,
+    image: TypedChannel<RawImage>,
+    tf: TypedChannel<FrameTransform>,
+}
+
+impl CameraTopics {
+    pub fn with_suffix(suffix: &str) -> Result<Self, FoxgloveError> {
+        Ok(Self {
+            camera: TypedChannel::new(format!("{}/{suffix}", CAMERA.topic()))?,
+            image: TypedChannel::new(format!("{}/{suffix}", IMAGE.topic()))?,
+            tf: TypedChannel::new(format!("{}/{suffix}", TF.topic()))?,
+        })
+    }
+
+    /// Logs the calibration, image, and transform (translation, rotation) of a camera, stamped
+    /// `now_ns`
+    pub fn log(&self, now_ns: u64, parent_frame_id: &str, frame_id: &str, image: &ImageSettings, overlay: Option<&Overlay>, transform: (Vec<f64>, Vec<f64>)) {
+        let (translation, rotation) = transform;
+        log_camera_calibration_to(&self.camera, now_ns, frame_id, image);
+        log_raw_image_to(&self.image, now_ns, frame_id, image, overlay);
+        log_frame_transform_to(&self.tf, now_ns, parent_frame_id, frame_id, translation, rotation);
+    }
+}
+
+/// Rotation (x, y, z, w) from a body frame (X forward, Y left, Z up) to the optical frame
+/// convention (Z forward, X right, Y down), i.e. roll -90°, yaw -90°
+pub const OPTICAL_ROTATION: [f64; 4] = [-0.5, 0.5, -0.5, 0.5];
+
+/// Intrinsics and sizes for the published calibration and image
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ImageSettings {
+    pub width: u32,
+    pub height: u32,
+    pub fx: f64,
+    pub fy: f64,
+    pub cx: f64,
+    pub cy: f64,
+    // size of the transparent image published on /sdk-image
+    pub raw_width: u32,
+    pub raw_height: u32,
+}
+
+impl Default for ImageSettings {
+    fn default() -> Self {
+        Self {
+            width: 1600,
+            height: 900,
+            fx: 1266.417203046554,
+            fy: 1266.417203046554,
+            cx: 816.2670197447984,
+            cy: 491.50706579294757,
+            raw_width: 640,
+            raw_height: 480,
         }
+    }
+}
+
+/// How the synthetic depth image on /sdk-depth is generated
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DepthMode {
+    /// Don't publish a depth image
+    #[default]
+    Off,
+    /// Distance to a horizontal ground plane, cast through the camera intrinsics
+    Ground,
+    /// Depth increasing linearly from the bottom of the image to the top
+    Gradient,
+}
+
+/// Settings for the synthetic depth image (config file `[depth]` section)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DepthSettings {
+    pub mode: DepthMode,
+    // height of the ground plane in the camera's parent frame (Y up)
+    pub ground_height: f64,
+    // depth at the bottom and top rows in gradient mode, and the cap for ground mode
+    pub near: f32,
+    pub far: f32,
+}
+
+impl Default for DepthSettings {
+    fn default() -> Self {
+        Self {
+            mode: DepthMode::Off,
+            ground_height: -1.5,
+            near: 0.5,
+            far: 50.0,
+        }
+    }
+}
+
+/// A fixed transform published alongside the camera (config file `[[static_transforms]]`)
+#[derive(Debug, Clone, Deserialize)]
+pub struct StaticTransform {
+    // defaults to the camera's frame
+    #[serde(default)]
+    pub parent_frame_id: Option<String>,
+    pub child_frame_id: String,
+    #[serde(default)]
+    pub translation: [f64; 3],
+    // quaternion (x, y, z, w), identity if unset
+    #[serde(default)]
+    pub rotation: Option<[f64; 4]>,
+    // use the standard optical frame rotation instead of `rotation`
+    #[serde(default)]
+    pub optical: bool,
+}
+
+impl StaticTransform {
+    /// Gets the rotation quaternion (x, y, z, w)
+    pub fn rotation(&self) -> [f64; 4] {
+        if self.optical {
+            OPTICAL_ROTATION
+        } else {
+            self.rotation.unwrap_or([0.0, 0.0, 0.0, 1.0])
+        }
+    }
+}
+
+/// Log and publish time for a message stamped `now_ns`, so recordings match the header stamps.
+fn metadata(now_ns: u64) -> PartialMetadata {
+    PartialMetadata {
+        sequence: None,
+        log_time: Some(now_ns),
+        publish_time: Some(now_ns),
+    }
+}
+
+pub fn log_camera_calibration(now_ns: u64, frame_id: &str, image: &ImageSettings) {
+    log_camera_calibration_to(&CAMERA, now_ns, frame_id, image);
+}
+
+fn log_camera_calibration_to(channel: &TypedChannel<CameraCalibration>, now_ns: u64, frame_id: &str, image: &ImageSettings) {
+    let Some(timestamp) = clock::timestamp(now_ns) else {
+        warn!("Timestamp out of range: {now_ns}");
+        return;
     };
 
-    CAMERA.log(&CameraCalibration {
+    channel.log_with_meta(&CameraCalibration {
         timestamp: Some(timestamp),
         frame_id: frame_id.to_string(),
-        width: IMAGE_WIDTH,
-        height: IMAGE_HEIGHT,
+        width: image.width,
+        height: image.height,
         distortion_model: "plumb_bob".to_string(),
         d: vec![],
-        k: vec![1266.417203046554, 0.0, 816.2670197447984, 0.0, 1266.417203046554, 491.50706579294757, 0.0, 0.0, 1.0],
+        k: vec![image.fx, 0.0, image.cx, 0.0, image.fy, image.cy, 0.0, 0.0, 1.0],
         r: vec![1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0],
-        p: vec![1266.417203046554, 0.0, 816.2670197447984, 0.0, 0.0, 1266.417203046554, 491.50706579294757, 0.0, 0.0, 0.0, 1.0, 0.0],
-    });
+        p: vec![image.fx, 0.0, image.cx, 0.0, 0.0, image.fy, image.cy, 0.0, 0.0, 0.0, 1.0, 0.0],
+    }, metadata(now_ns));
 }
 
-pub fn log_frame_transform(parent_frame_id: &str, child_frame_id: &str, translation: Vec<f64>, rotation: Vec<f64>) {
-    let timestamp_sec = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs_f64();
-    let timestamp = match Timestamp::try_from_epoch_secs_f64(timestamp_sec) {
-        Ok(timestamp) => timestamp,
-        Err(e) => {
-            eprintln!("Error converting timestamp: {}", e);
-            return;
-        }
+pub fn log_frame_transform(now_ns: u64, parent_frame_id: &str, child_frame_id: &str, translation: Vec<f64>, rotation: Vec<f64>) {
+    log_frame_transform_to(&TF, now_ns, parent_frame_id, child_frame_id, translation, rotation);
+}
+
+fn log_frame_transform_to(channel: &TypedChannel<FrameTransform>, now_ns: u64, parent_frame_id: &str, child_frame_id: &str, translation: Vec<f64>, rotation: Vec<f64>) {
+    let Some(timestamp) = clock::timestamp(now_ns) else {
+        warn!("Timestamp out of range: {now_ns}");
+        return;
+    };
+
+    channel.log_with_meta(&frame_transform(timestamp, parent_frame_id, child_frame_id, &translation, &rotation), metadata(now_ns));
+}
+
+/// Publishes the static transforms hanging off the camera frame as one
+/// `foxglove.FrameTransforms` message, stamped like the camera transform on /sdk-tf
+pub fn log_static_transforms(now_ns: u64, camera_frame_id: &str, statics: &[StaticTransform]) {
+    let Some(timestamp) = clock::timestamp(now_ns) else {
+        warn!("Timestamp out of range: {now_ns}");
+        return;
     };
 
-    TF.log(&FrameTransform {
+    let transforms = statics.iter().map(|st| {
+        let parent = st.parent_frame_id.as_deref().unwrap_or(camera_frame_id);
+        frame_transform(timestamp, parent, &st.child_frame_id, &st.translation, &st.rotation())
+    }).collect();
+    TRANSFORMS.log_with_meta(&FrameTransforms { transforms }, metadata(now_ns));
+}
+
+fn frame_transform(timestamp: Timestamp, parent_frame_id: &str, child_frame_id: &str, translation: &[f64], rotation: &[f64]) -> FrameTransform {
+    FrameTransform {
         timestamp: Some(timestamp),
         parent_frame_id: parent_frame_id.to_string(),
         child_frame_id: child_frame_id.to_string(),
@@ -57,7 +214,7 @@ pub fn log_frame_transform(parent_frame_id: &str, child_frame_id: &str, translat
             z: rotation[2],
             w: rotation[3],
         }),
-    });
+    }
 }
 
 pub fn calculate_transform(angle: f64, radius: f64) -> (Vec<f64>, Vec<f64>) {
@@ -130,21 +287,25 @@ pub fn calculate_transform(angle: f64, radius: f64) -> (Vec<f64>, Vec<f64>) {
     (translation, rotation)
 }
 
-pub fn log_raw_image(frame_id: &str) {
-    let timestamp_sec = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs_f64();
-    let timestamp = match Timestamp::try_from_epoch_secs_f64(timestamp_sec) {
-        Ok(timestamp) => timestamp,
-        Err(e) => {
-            eprintln!("Error converting timestamp: {}", e);
-            return;
-        }
+/// Publishes a transparent image, with the HUD drawn in if an overlay is given
+pub fn log_raw_image(now_ns: u64, frame_id: &str, image: &ImageSettings, overlay: Option<&Overlay>) {
+    log_raw_image_to(&IMAGE, now_ns, frame_id, image, overlay);
+}
+
+fn log_raw_image_to(channel: &TypedChannel<RawImage>, now_ns: u64, frame_id: &str, image: &ImageSettings, overlay: Option<&Overlay>) {
+    let Some(timestamp) = clock::timestamp(now_ns) else {
+        warn!("Timestamp out of range: {now_ns}");
+        return;
     };
 
-    let width = 640;
-    let height = 480;
-    let data = vec![0u8; width * height * 4]; // RGBA format, all zeros = transparent
+    let width = image.raw_width as usize;
+    let height = image.raw_height as usize;
+    let mut data = vec![0u8; width * height * 4]; // RGBA format, all zeros = transparent
+    if let Some(overlay) = overlay {
+        overlay.draw(&mut data, width, height);
+    }
     
-    IMAGE.log(&RawImage {
+    channel.log_with_meta(&RawImage {
         timestamp: Some(timestamp),
         frame_id: frame_id.to_string(),
         width: width as u32,
@@ -152,5 +313,60 @@ pub fn log_raw_image(frame_id: &str) {
         encoding: "rgba8".to_string(),
         step: (width * 4) as u32,
         data: data.into(),
-    });
-} 
\ No newline at end of file
+    }, metadata(now_ns));
+}
+
+/// Publishes a 32FC1 depth image matching the size of the image on /sdk-image. The intrinsics
+/// are scaled from the calibration size to the image size. Pixels that don't see the ground are
+/// NaN (no return).
+pub fn log_depth_image(now_ns: u64, frame_id: &str, image: &ImageSettings, depth: &DepthSettings, camera_height: f64, roll: f64) {
+    let Some(timestamp) = clock::timestamp(now_ns) else {
+        warn!("Timestamp out of range: {now_ns}");
+        return;
+    };
+
+    let width = image.raw_width as usize;
+    let height = image.raw_height as usize;
+    let scale_x = image.raw_width as f64 / image.width as f64;
+    let scale_y = image.raw_height as f64 / image.height as f64;
+    let (fx, cx) = (image.fx * scale_x, image.cx * scale_x);
+    let (fy, cy) = (image.fy * scale_y, image.cy * scale_y);
+    let above_ground = camera_height - depth.ground_height;
+    let (sin_roll, cos_roll) = roll.sin_cos();
+
+    let mut data = Vec::with_capacity(width * height * 4);
+    for v in 0..height {
+        for u in 0..width {
+            let value = match depth.mode {
+                DepthMode::Off => return,
+                DepthMode::Gradient => {
+                    let t = 1.0 - v as f32 / (height.max(2) - 1) as f32;
+                    depth.near + t * (depth.far - depth.near)
+                }
+                DepthMode::Ground => {
+                    // Optical frame ray (x right, y down, z forward), un-rolled so that y points
+                    // straight down; the z component of the ray is 1, so depth = distance / y.
+                    let x = (u as f64 - cx) / fx;
+                    let y = (v as f64 - cy) / fy;
+                    let down = x * sin_roll + y * cos_roll;
+                    if above_ground > 0.0 && down > 1e-6 {
+                        ((above_ground / down) as f32).min(depth.far)
+                    } else {
+                        f32::NAN
+                    }
+                }
+            };
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+
+    DEPTH.log_with_meta(&RawImage {
+        timestamp: Some(timestamp),
+        frame_id: frame_id.to_string(),
+        width: width as u32,
+        height: height as u32,
+        encoding: "32FC1".to_string(),
+        step: (width * 4) as u32,
+        data: data.into(),
+    }, metadata(now_ns));
+}
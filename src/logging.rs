@@ -0,0 +1,143 @@
+use std::collections::VecDeque;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use parking_lot::Mutex;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
+
+use crate::diagnostics::DiagnosticsLayer;
+
+/// Number of recent log lines kept for the TUI.
+const PANE_CAPACITY: usize = 200;
+
+/// Recent log lines for display in the terminal UI.
+///
+/// Until the UI calls [`LogPane::capture`], lines are also echoed to stderr; while captured they
+/// are only kept here, since writing to a raw-mode terminal would corrupt the UI.
+#[derive(Clone, Default)]
+pub struct LogPane {
+    inner: Arc<PaneState>,
+}
+
+#[derive(Default)]
+struct PaneState {
+    lines: Mutex<VecDeque<String>>,
+    captured: AtomicBool,
+}
+
+impl LogPane {
+    /// Stops echoing to stderr; the UI draws the lines instead.
+    pub fn capture(&self) {
+        self.inner.captured.store(true, Ordering::Relaxed);
+    }
+
+    /// Resumes echoing to stderr, e.g. once the UI has restored the terminal.
+    pub fn release(&self) {
+        self.inner.captured.store(false, Ordering::Relaxed);
+    }
+
+    /// Returns up to `count` of the most recent lines, oldest first.
+    pub fn recent(&self, count: usize) -> Vec<String> {
+        let lines = self.inner.lines.lock();
+        lines.iter().skip(lines.len().saturating_sub(count)).cloned().collect()
+    }
+
+    fn push(&self, text: &str) {
+        if !self.inner.captured.load(Ordering::Relaxed) {
+            let _ = io::stderr().write_all(text.as_bytes());
+        }
+        let mut lines = self.inner.lines.lock();
+        for line in text.lines() {
+            if lines.len() == PANE_CAPACITY {
+                lines.pop_front();
+            }
+            lines.push_back(line.to_string());
+        }
+    }
+}
+
+impl<'a> MakeWriter<'a> for LogPane {
+    type Writer = PaneWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        PaneWriter {
+            pane: self.clone(),
+            buf: Vec::new(),
+        }
+    }
+}
+
+/// Collects one formatted event and hands it to the pane when dropped.
+pub struct PaneWriter {
+    pane: LogPane,
+    buf: Vec<u8>,
+}
+
+impl Write for PaneWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for PaneWriter {
+    fn drop(&mut self) {
+        if !self.buf.is_empty() {
+            self.pane.push(&String::from_utf8_lossy(&self.buf));
+        }
+    }
+}
+
+/// Keeps the log file writer running; drop it last to flush buffered lines.
+pub struct LogGuard {
+    _file: Option<WorkerGuard>,
+}
+
+/// Installs the global tracing subscriber, logging to the returned pane and, if given, appending
+/// to `log_file`. The level is taken from `RUST_LOG`, defaulting to `info`. Warnings and errors
+/// are also published on `/sdk-diagnostics`.
+pub fn init(log_file: Option<&Path>) -> Result<(LogPane, LogGuard)> {
+    let pane = LogPane::default();
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let pane_layer = tracing_subscriber::fmt::layer()
+        .with_ansi(false)
+        .without_time()
+        .with_writer(pane.clone());
+
+    let (file_layer, guard) = match log_file {
+        Some(path) => {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| format!("open log file {}", path.display()))?;
+            let (writer, guard) = tracing_appender::non_blocking(file);
+            let layer = tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(writer);
+            (Some(layer), Some(guard))
+        }
+        None => (None, None),
+    };
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(pane_layer)
+        .with(file_layer)
+        .with(DiagnosticsLayer)
+        .try_init()
+        .context("install log subscriber")?;
+    Ok((pane, LogGuard { _file: guard }))
+}
@@ -1,131 +1,991 @@
-use std::{fs::File, io::BufReader, path::PathBuf, sync::{
+use std::{path::PathBuf, sync::{
     atomic::{AtomicBool, Ordering},
     Arc, 
 }, time::Duration};
 
-use anyhow::Context;
-use clap::Parser;
-use controls::Controls;
-use foxglove::{websocket::Capability, McapWriter};
-
-mod logger;
-mod camera_state;
-mod controls;
-mod mcap_replay;
-
-use camera_state::CameraState;
+use camera_mover_sdk::bad_records::{BadRecords, ErrorPolicy};
+use camera_mover_sdk::bench::{self, BenchOptions, BenchSink};
+use camera_mover_sdk::camera_state::CameraPhysics;
+use camera_mover_sdk::chapters::{self, ChapterSpec, CHAPTER_METADATA};
+use camera_mover_sdk::chase::{ChaseCam, ChaseSettings};
+use camera_mover_sdk::client_cameras::{ClientCameras, TeleopSource};
+use camera_mover_sdk::client_tracker::ClientTracker;
+use camera_mover_sdk::clock::{Clock, ClockMode};
+use camera_mover_sdk::compare::{CompareOptions, DEFAULT_COMPARE_PREFIX};
+use camera_mover_sdk::config;
+use camera_mover_sdk::control_source::{
+    ChaseSource, ControlMixer, ControlPriorities, FollowSource, PoseRequestSource,
+};
+use camera_mover_sdk::controls::{Controls, KeyBindings};
+use camera_mover_sdk::decode_pool::DecodePoolConfig;
+use camera_mover_sdk::diagnostics::{self, SubscriptionMonitor};
+use camera_mover_sdk::events::MARKER_METADATA;
+use camera_mover_sdk::filter::{self, FilterOptions};
+#[cfg(feature = "gamepad")]
+use camera_mover_sdk::gamepad::GamepadSource;
+use camera_mover_sdk::info;
+use camera_mover_sdk::interpolate::TransitionSettings;
+use camera_mover_sdk::json_export::parse_export_spec;
+use camera_mover_sdk::keyframes::{KeyframePath, DEFAULT_KEYFRAME_FILE};
+use camera_mover_sdk::listeners::Listeners;
+use camera_mover_sdk::logger::{DepthMode, DepthSettings, ImageSettings, StaticTransform};
+use camera_mover_sdk::logging;
+use camera_mover_sdk::mcap_replay::DEFAULT_LATE_THRESHOLD;
+use camera_mover_sdk::metrics::Metrics;
+use camera_mover_sdk::noise::{NoiseKind, NoiseSettings};
+use camera_mover_sdk::overlay::{OverlaySettings, PlaybackStatus};
+use camera_mover_sdk::playback_remote::PlaybackRemote;
+use camera_mover_sdk::recording::{Recording, SplitPolicy};
+use camera_mover_sdk::scene::{Grid, GridSettings};
+use camera_mover_sdk::schema_override::parse_schema_override;
+use camera_mover_sdk::snapshot::{self, SnapshotFormat, SnapshotTime};
+use camera_mover_sdk::source;
+#[cfg(feature = "http")]
+use camera_mover_sdk::http_api::HttpApi;
+#[cfg(feature = "ros2")]
+use camera_mover_sdk::ros2_bridge::Ros2Bridge;
+#[cfg(feature = "scripting")]
+use camera_mover_sdk::scripting::CameraScript;
+#[cfg(feature = "upload")]
+use camera_mover_sdk::upload::{self, UploadSettings, Uploader};
+use camera_mover_sdk::synthetic::{SyntheticOptions, SyntheticSource};
+use camera_mover_sdk::throttle::{self, ThrottleConfig};
+use camera_mover_sdk::trajectory::{TrajectoryFormat, TrajectoryWriter};
+use camera_mover_sdk::validate;
+use camera_mover_sdk::{CameraRig, IoMode, LatePolicy, MessageSource, ReplaySession};
 use chrono::Local;
-use mcap::sans_io::read::LinearReader;
-use mcap_replay::{advance_reader, Summary};
-use tracing::info;
+use clap::{error::ErrorKind, CommandFactory, FromArgMatches, Parser, Subcommand};
+use serde::Deserialize;
+use foxglove::websocket::Capability;
+use tracing::{error, info, warn};
 
 const FILE_NAME_PREFIX: &str = "quickstart-rust";
-#[derive(Debug, Parser)]
+const DEFAULT_HOST: &str = "127.0.0.1";
+const DEFAULT_PORT: u16 = 8765;
+const DEFAULT_PRIORITY_TOPICS: &[&str] = &["/tf", "/tf_static", "/clock"];
+/// Attempts per file for --upload.
+#[cfg(feature = "upload")]
+const UPLOAD_ATTEMPTS: u32 = 5;
+
+/// Command line options. Every option can also be set from a `--config` file, with values given
+/// on the command line taking precedence. Flags take an optional value, so `--loop=false` turns
+/// off a flag set in the config file.
+#[derive(Debug, Default, Parser, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 struct Cli {
-    /// MCAP file to read.
+    /// Offline tools; without one, the file is replayed to Foxglove.
+    #[command(subcommand)]
+    #[serde(skip)]
+    command: Option<Command>,
+    /// TOML or YAML file to load options from.
+    #[arg(long)]
+    #[serde(skip)]
+    config: Option<PathBuf>,
+    /// MCAP file to read, or `-` for standard input.
     #[arg(short, long)]
-    file: PathBuf,
-    /// Whether to loop.
+    file: Option<PathBuf>,
+    /// Second MCAP file to replay in sync with --file, with its topics under --compare-prefix.
+    #[arg(long)]
+    compare: Option<PathBuf>,
+    /// Seconds added to the --compare file's log times [default: align the start times]
+    #[arg(long, allow_negative_numbers = true)]
+    compare_offset: Option<f64>,
+    /// Topic prefix for the --compare file [default: /b]
     #[arg(long)]
-    r#loop: bool,
+    compare_prefix: Option<String>,
+    /// Whether to loop.
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    r#loop: Option<bool>,
     /// Whether to write the file again with the camera state
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    r#write: Option<bool>,
+    /// With --write, start a new numbered file once the current one reaches this many MB.
+    #[arg(long)]
+    split_size: Option<f64>,
+    /// With --write, start a new numbered file after this many minutes.
+    #[arg(long)]
+    split_duration: Option<f64>,
+    /// Check CRCs and record references before replaying, and stop if the file is corrupt.
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    validate: Option<bool>,
+    /// Check the file as with --validate, then exit instead of replaying.
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    validate_only: Option<bool>,
+    /// Refuse to replay channels whose message and schema encodings Foxglove can't decode,
+    /// instead of advertising them with a warning.
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    strict_encodings: Option<bool>,
+    /// Append log output to this file, in addition to the log pane.
+    #[arg(long)]
+    log_file: Option<PathBuf>,
+    /// Start paused, with channels advertised, until resumed by key, service call, or HTTP.
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    paused: Option<bool>,
+    /// Start streaming immediately instead of waiting for a client to subscribe.
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    no_wait: Option<bool>,
+    /// Maximum number of seconds to wait for a client to subscribe.
+    #[arg(long)]
+    wait_timeout: Option<f64>,
+    /// Host for the websocket server to listen on [default: 127.0.0.1]
+    #[arg(long)]
+    host: Option<String>,
+    /// Port for the websocket server to listen on [default: 8765]
+    #[arg(long)]
+    port: Option<u16>,
+    /// How to read the input file [default: buffered]
+    #[arg(long, value_enum)]
+    io: Option<IoMode>,
+    /// Worker threads decompressing chunks with --io parallel [default: number of CPUs]
+    #[arg(long)]
+    decode_threads: Option<usize>,
+    /// Memory budget, in MB, for chunks decompressed ahead with --io parallel [default: 256]
+    #[arg(long)]
+    decode_memory_mb: Option<u64>,
+    /// Time source for broadcast times and the camera topics' timestamps [default: scaled]
+    #[arg(long, value_enum)]
+    clock: Option<ClockMode>,
+    /// What to do when replay falls behind the wallclock [default: burst]
+    #[arg(long, value_enum)]
+    late_policy: Option<LatePolicy>,
+    /// How far behind, in milliseconds, a message may be before --late-policy applies [default: 100]
+    #[arg(long)]
+    late_threshold_ms: Option<u64>,
+    /// What to do with records that can't be read, e.g. a corrupt chunk [default: log]
+    #[arg(long, value_enum)]
+    on_error: Option<ErrorPolicy>,
+    /// Comma-separated list of topics to replay (all topics if omitted).
+    #[arg(long, value_delimiter = ',')]
+    topics: Option<Vec<String>>,
+    /// Replace a topic's schema as <topic>=<schema-file>[#<name>]; the encoding is inferred from
+    /// the extension (.json, .fds/.desc/.binpb, .msg, .idl). May be repeated.
+    #[arg(long)]
+    schema_override: Option<Vec<String>>,
+    /// Total bandwidth budget in MB/s; messages over budget are dropped.
+    #[arg(long)]
+    max_bandwidth: Option<f64>,
+    /// Per-topic rate cap as <topic>=<hz>; may be repeated.
+    #[arg(long)]
+    topic_rate: Option<Vec<String>>,
+    /// Comma-separated topics never dropped by --max-bandwidth [default: /tf,/tf_static,/clock]
+    #[arg(long, value_delimiter = ',')]
+    priority_topics: Option<Vec<String>>,
+    /// Rhai script driving the camera through an `on_tick(t, camera)` function.
+    #[cfg(feature = "scripting")]
+    #[arg(long)]
+    script: Option<PathBuf>,
+    /// Address to serve the HTTP control API on, e.g. 127.0.0.1:8080.
+    #[cfg(feature = "http")]
+    #[arg(long)]
+    http: Option<String>,
+    /// Publish the camera transform and pose as ROS 2 topics over zenoh.
+    #[cfg(feature = "ros2")]
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    ros2: Option<bool>,
+    /// Upload the --write recording to Foxglove Data Platform once it is finished.
+    #[cfg(feature = "upload")]
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    upload: Option<bool>,
+    /// Data Platform device to attribute uploaded recordings to.
+    #[cfg(feature = "upload")]
+    #[arg(long)]
+    device_id: Option<String>,
+    /// Data Platform API key for --upload [default: $FOXGLOVE_API_KEY]
+    #[cfg(feature = "upload")]
+    #[arg(long)]
+    token: Option<String>,
+    /// Write the camera pose on every camera tick to this file, for odometry evaluation.
+    #[arg(long)]
+    export_trajectory: Option<PathBuf>,
+    /// Format of --export-trajectory [default: tum]
+    #[arg(long, value_enum)]
+    trajectory_format: Option<TrajectoryFormat>,
+    /// Append the decoded messages of a JSON or protobuf topic to a JSON Lines file, as
+    /// <topic>=<file>. May be repeated.
+    #[arg(long)]
+    export_json: Option<Vec<String>>,
+    /// Record a named chapter in the --write output as <name>=<start>..<end>, in seconds from
+    /// the start of the file. May be repeated.
+    #[arg(long)]
+    chapter: Option<Vec<String>>,
+    /// YAML file listing chapters to record, as `- {name: ..., start: ..., end: ...}`.
+    #[arg(long)]
+    chapters: Option<PathBuf>,
+    /// Also record a chapter from each marker to the next one (or the end of the replay).
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    marker_chapters: Option<bool>,
+    /// Fly the camera through keyframes saved with the K and O keys, in sync with the replay.
+    #[arg(long)]
+    follow: Option<PathBuf>,
+    /// File the O key saves keyframes to [default: keyframes.json]
+    #[arg(long)]
+    keyframe_file: Option<PathBuf>,
+    /// Follow this frame of the replayed foxglove.FrameTransform(s) messages with a chase
+    /// camera, instead of driving the camera from the keyboard.
+    #[arg(long)]
+    chase: Option<String>,
+    /// Give each client publishing on /sdk-teleop its own camera and topics (/sdk-tf/<client>).
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    client_cameras: Option<bool>,
+    /// Publish a ground grid and origin axes on /sdk-grid.
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    grid: Option<bool>,
+    /// Draw a HUD (timestamp, replay time and rate, camera pose, frame counter) onto /sdk-image.
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    overlay: Option<bool>,
+    /// Extra line of text for the HUD; may be repeated. Implies --overlay.
+    #[arg(long)]
+    overlay_text: Option<Vec<String>>,
+    /// Synthetic depth image to publish on /sdk-depth [default: off]
+    #[arg(long = "depth", value_enum)]
+    #[serde(skip)]
+    depth_mode: Option<DepthMode>,
+    /// Noise added to the published camera transform [default: none]
+    #[arg(long = "noise", value_enum)]
+    #[serde(skip)]
+    noise_kind: Option<NoiseKind>,
+    /// Camera motion physics (config file only).
+    #[arg(skip)]
+    camera: CameraPhysics,
+    /// Calibration and image settings (config file only).
+    #[arg(skip)]
+    image: ImageSettings,
+    /// Camera key bindings (config file only).
+    #[arg(skip)]
+    keys: KeyBindings,
+    /// Grid size, spacing, and height (config file only).
+    #[arg(skip)]
+    grid_settings: GridSettings,
+    /// HUD text, size, and colors (config file only).
+    #[arg(skip)]
+    overlay_settings: OverlaySettings,
+    /// Depth image ground plane and range (config file only).
+    #[arg(skip)]
+    depth: DepthSettings,
+    /// Camera noise amplitude and frequency (config file only).
+    #[arg(skip)]
+    noise: NoiseSettings,
+    /// Fixed frames published with the camera transform (config file only).
+    #[arg(skip)]
+    static_transforms: Vec<StaticTransform>,
+    /// Chase camera offset, look-ahead, and smoothing (config file only).
+    #[arg(skip)]
+    chase_settings: ChaseSettings,
+    /// Duration and easing of moves to requested poses (config file only).
+    #[arg(skip)]
+    transition: TransitionSettings,
+    /// Priority of each control source by name; higher wins (config file only).
+    #[arg(skip)]
+    control_priorities: ControlPriorities,
+    /// Drive the camera from the first connected gamepad.
+    #[cfg(feature = "gamepad")]
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    gamepad: Option<bool>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Copy selected topics and time ranges of an MCAP file into a new file, without starting
+    /// the server.
+    Filter(FilterArgs),
+    /// Print the duration, time range, topics, schemas, and chunk compression of an MCAP file.
+    Info(InfoArgs),
+    /// Replay a file (or synthetic load) as fast as possible and report read, decompression, and
+    /// publish throughput.
+    Bench(BenchArgs),
+    /// Write the last message at or before a time on every topic of an MCAP file to a small MCAP
+    /// or JSON file, found through the message indexes.
+    Snapshot(SnapshotArgs),
+}
+
+#[derive(Debug, clap::Args)]
+struct SnapshotArgs {
+    /// MCAP file to read.
+    input: PathBuf,
+    /// File to write; a `.json` extension selects JSON unless --format is given.
+    #[arg(short, long)]
+    output: PathBuf,
+    /// Seconds from the start of the file to take the snapshot at.
+    #[arg(long, required_unless_present = "at_log_time", conflicts_with = "at_log_time")]
+    at: Option<f64>,
+    /// Absolute log time in nanoseconds to take the snapshot at.
+    #[arg(long)]
+    at_log_time: Option<u64>,
+    /// Output format [default: from the output extension]
+    #[arg(long, value_enum)]
+    format: Option<SnapshotFormat>,
+}
+
+impl SnapshotArgs {
+    fn at(&self) -> anyhow::Result<SnapshotTime> {
+        match (self.at, self.at_log_time) {
+            (_, Some(log_time)) => Ok(SnapshotTime::LogTime(log_time)),
+            (Some(secs), None) => Duration::try_from_secs_f64(secs)
+                .map(SnapshotTime::Offset)
+                .map_err(|e| anyhow::anyhow!("invalid time: {e}")),
+            (None, None) => Err(anyhow::anyhow!("--at or --at-log-time is required")),
+        }
+    }
+}
+
+#[derive(Debug, clap::Args)]
+struct BenchArgs {
+    /// File to replay; omit to generate synthetic messages instead.
+    input: Option<PathBuf>,
+    /// Where to publish messages.
+    #[arg(long, value_enum, default_value_t)]
+    sink: BenchSink,
+    /// How to read the input file.
+    #[arg(long, value_enum, default_value_t)]
+    io: IoMode,
+    /// Port for --sink websocket.
+    #[arg(long, default_value_t = DEFAULT_PORT)]
+    port: u16,
+    /// Number of synthetic topics.
+    #[arg(long, default_value_t = 10)]
+    synthetic_topics: usize,
+    /// Messages per second on each synthetic topic.
+    #[arg(long, default_value_t = 100.0)]
+    synthetic_rate: f64,
+    /// Size of each synthetic message in bytes.
+    #[arg(long, default_value_t = 1024)]
+    synthetic_payload: usize,
+    /// Seconds of log time to generate.
+    #[arg(long, default_value_t = 10.0)]
+    synthetic_duration: f64,
+}
+
+#[derive(Debug, clap::Args)]
+struct InfoArgs {
+    /// MCAP file to describe.
+    input: PathBuf,
+    /// Print JSON instead of text.
     #[arg(long)]
-    r#write: bool,
+    json: bool,
+}
+
+#[derive(Debug, clap::Args)]
+struct FilterArgs {
+    /// MCAP file to read.
+    input: PathBuf,
+    /// MCAP file to write.
+    #[arg(short, long)]
+    output: PathBuf,
+    /// Comma-separated list of topics to keep (all topics if omitted).
+    #[arg(long, value_delimiter = ',')]
+    topics: Option<Vec<String>>,
+    /// Rename a topic as <from>=<to>; may be repeated.
+    #[arg(long)]
+    remap: Vec<String>,
+    /// Drop messages before this many seconds from the start of the file.
+    #[arg(long)]
+    start: Option<f64>,
+    /// Drop messages after this many seconds from the start of the file.
+    #[arg(long)]
+    end: Option<f64>,
+}
+
+impl FilterArgs {
+    fn options(&self) -> anyhow::Result<FilterOptions> {
+        let seconds = |secs: Option<f64>| {
+            secs.map(|s| Duration::try_from_secs_f64(s.max(0.0)))
+                .transpose()
+                .map_err(|e| anyhow::anyhow!("invalid time: {e}"))
+        };
+        Ok(FilterOptions {
+            topics: self.topics.as_ref().map(|t| t.iter().cloned().collect()),
+            remaps: self
+                .remap
+                .iter()
+                .map(|spec| filter::parse_remap(spec))
+                .collect::<anyhow::Result<_>>()?,
+            start: seconds(self.start)?,
+            end: seconds(self.end)?,
+        })
+    }
+}
+
+/// Runs an offline subcommand.
+fn run_command(command: Command) -> anyhow::Result<()> {
+    match command {
+        Command::Filter(args) => {
+            let report = filter::filter(&args.input, &args.output, &args.options()?)?;
+            println!("{report}");
+        }
+        Command::Info(args) => {
+            let info = info::info(&args.input)?;
+            if args.json {
+                println!("{}", serde_json::to_string_pretty(&info)?);
+            } else {
+                print!("{info}");
+            }
+        }
+        Command::Bench(args) => {
+            let source: Arc<dyn MessageSource> = match &args.input {
+                Some(path) => source::open(
+                    path,
+                    args.io,
+                    DecodePoolConfig::default(),
+                    BadRecords::new(ErrorPolicy::Stop),
+                )?,
+                None => Arc::new(SyntheticSource::new(SyntheticOptions {
+                    topics: args.synthetic_topics,
+                    rate_hz: args.synthetic_rate,
+                    payload_bytes: args.synthetic_payload,
+                    duration: Duration::try_from_secs_f64(args.synthetic_duration)
+                        .map_err(|e| anyhow::anyhow!("invalid duration: {e}"))?,
+                })?),
+            };
+            let report = bench::bench(
+                source,
+                &BenchOptions {
+                    sink: args.sink,
+                    host: DEFAULT_HOST.to_string(),
+                    port: args.port,
+                },
+            )?;
+            print!("{report}");
+        }
+        Command::Snapshot(args) => {
+            let format = args
+                .format
+                .unwrap_or_else(|| SnapshotFormat::from_path(&args.output));
+            let report = snapshot::snapshot(&args.input, &args.output, args.at()?, format)?;
+            println!("{report}");
+        }
+    }
+    Ok(())
+}
+
+impl Cli {
+    /// Returns the uploader for --upload, exiting with a usage error if it can't be set up.
+    #[cfg(feature = "upload")]
+    fn uploader(&self) -> Option<Uploader> {
+        if !self.upload.unwrap_or_default() {
+            return None;
+        }
+        let usage = |message: &str| -> ! {
+            Cli::command()
+                .error(ErrorKind::MissingRequiredArgument, message)
+                .exit()
+        };
+        if !self.r#write.unwrap_or_default() {
+            usage("--upload requires --write");
+        }
+        let Some(device_id) = self.device_id.clone() else {
+            usage("--upload requires --device-id");
+        };
+        let Some(token) = self.token.clone().or_else(|| std::env::var(upload::TOKEN_ENV).ok())
+        else {
+            usage("--upload requires --token or FOXGLOVE_API_KEY");
+        };
+        Some(Uploader::new(UploadSettings {
+            device_id,
+            token,
+            api_url: upload::DEFAULT_API_URL.to_string(),
+            attempts: UPLOAD_ATTEMPTS,
+        }))
+    }
+
+    /// Builds the output file splitting policy from --split-size and --split-duration.
+    /// Returns the chapters given with --chapter and --chapters.
+    fn chapter_specs(&self) -> anyhow::Result<Vec<ChapterSpec>> {
+        let mut specs = self
+            .chapter
+            .iter()
+            .flatten()
+            .map(|spec| chapters::parse_chapter(spec))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        if let Some(path) = &self.chapters {
+            specs.extend(chapters::load_chapters(path)?);
+        }
+        // Check the times now rather than when the recording is finished.
+        for spec in &specs {
+            spec.resolve(0)?;
+        }
+        Ok(specs)
+    }
+
+    fn split_policy(&self) -> SplitPolicy {
+        let usage = |message: String| -> ! {
+            Cli::command()
+                .error(ErrorKind::ValueValidation, message)
+                .exit()
+        };
+        let max_bytes = self.split_size.map(|mb| {
+            if !(mb > 0.0 && mb.is_finite()) {
+                usage(format!("--split-size must be a positive number of MB, got {mb}"));
+            }
+            // Saturates for sizes past u64::MAX, which never split.
+            (mb * 1e6) as u64
+        });
+        let max_duration = self.split_duration.map(|min| {
+            match Duration::try_from_secs_f64(min * 60.0) {
+                Ok(duration) if !duration.is_zero() => duration,
+                _ => usage(format!(
+                    "--split-duration must be a positive number of minutes, got {min}"
+                )),
+            }
+        });
+        SplitPolicy {
+            max_bytes,
+            max_duration,
+        }
+    }
+
+    /// Sizes the chunk decompression pool from --decode-threads and --decode-memory-mb.
+    fn decode_pool_config(&self) -> DecodePoolConfig {
+        let default = DecodePoolConfig::default();
+        DecodePoolConfig {
+            workers: self.decode_threads.unwrap_or(default.workers).max(1),
+            memory_budget: self
+                .decode_memory_mb
+                .map_or(default.memory_budget, |mb| mb << 20),
+        }
+    }
+
+    /// Builds the throttling limits from the bandwidth and rate options.
+    fn throttle_config(&self) -> anyhow::Result<ThrottleConfig> {
+        let topic_rates = self
+            .topic_rate
+            .iter()
+            .flatten()
+            .map(|s| throttle::parse_topic_rate(s))
+            .collect::<anyhow::Result<_>>()?;
+        let priority_topics = match &self.priority_topics {
+            Some(topics) => topics.iter().cloned().collect(),
+            None => DEFAULT_PRIORITY_TOPICS.iter().map(|t| t.to_string()).collect(),
+        };
+        if let Some(mb) = self.max_bandwidth {
+            if mb <= 0.0 || !mb.is_finite() {
+                return Err(anyhow::anyhow!("--max-bandwidth must be positive, got {mb}"));
+            }
+        }
+        Ok(ThrottleConfig {
+            max_bytes_per_sec: self.max_bandwidth.map(|mb| mb * 1e6),
+            topic_rates,
+            priority_topics,
+        })
+    }
+
+    /// Returns --wait-timeout, exiting with a usage error if it isn't a valid number of seconds.
+    fn wait_timeout(&self) -> Option<Duration> {
+        self.wait_timeout.map(|secs| {
+            Duration::try_from_secs_f64(secs).unwrap_or_else(|e| {
+                Cli::command()
+                    .error(ErrorKind::ValueValidation, format!("invalid --wait-timeout {secs}: {e}"))
+                    .exit()
+            })
+        })
+    }
+
+    /// Parses the command line, filling in anything not given there from the config file.
+    ///
+    /// The options given on the command line are applied on top of the config file, so every
+    /// option is covered without listing it here. Only options with a value on the command line
+    /// are applied, which is why flags are `Option<bool>` rather than defaulting to `false`.
+    fn load() -> anyhow::Result<Self> {
+        let matches = Self::command().get_matches();
+        let args = Self::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+        let Some(path) = &args.config else {
+            return Ok(args);
+        };
+        let mut config: Self = config::load(path)?;
+        config.grid_settings.validate()?;
+        config
+            .update_from_arg_matches(&matches)
+            .unwrap_or_else(|e| e.exit());
+        Ok(config)
+    }
 }
 
 fn main() {
-    let env = env_logger::Env::default().default_filter_or("debug");
-    env_logger::init_from_env(env);
+    let mut args = Cli::load().expect("Failed to load config");
+    let (log_pane, log_guard) =
+        logging::init(args.log_file.as_deref()).expect("Failed to set up logging");
 
+    if let Some(command) = args.command.take() {
+        if let Err(err) = run_command(command) {
+            error!("{err:#}");
+            drop(log_guard);
+            std::process::exit(1);
+        }
+        return;
+    }
 
-    let args = Cli::parse();
-    let read_file_name = args
-        .file
+    let Some(file) = args.file.clone() else {
+        Cli::command()
+            .error(ErrorKind::MissingRequiredArgument, "--file is required (on the command line or in --config)")
+            .exit();
+    };
+    let wait_timeout = args.wait_timeout();
+
+    if args.validate.unwrap_or_default() || args.validate_only.unwrap_or_default() {
+        if source::is_stream(&file) {
+            Cli::command()
+                .error(
+                    ErrorKind::ArgumentConflict,
+                    "--validate needs a seekable file, not standard input or a pipe",
+                )
+                .exit();
+        }
+        info!("Validating {}", file.display());
+        let report = validate::validate(&file).expect("Failed to read file");
+        println!("{report}");
+        if !report.is_ok() {
+            std::process::exit(1);
+        }
+        if args.validate_only.unwrap_or_default() {
+            return;
+        }
+    }
+
+    let read_file_name = file
         .file_name()
         .map(|n| n.to_string_lossy())
         .unwrap_or_default();
 
     let done = Arc::new(AtomicBool::default());
+    // Ctrl-C before the controls take over the terminal, SIGTERM/SIGHUP on Unix, and closing the
+    // console window on Windows. While the controls are active, Ctrl-C arrives as a key press.
     ctrlc::set_handler({
         let done = done.clone();
         move || {
-            done.store(true, Ordering::Relaxed);
+            // Only ask the replay to stop, so the recording is still finalized on the way out.
+            if done.swap(true, Ordering::Relaxed) {
+                warn!("Already shutting down, waiting for the recording to be finalized");
+            }
         }
     })
-    .expect("Failed to set SIGINT handler");
+    .expect("Failed to set Ctrl-C handler");
 
+    let client_tracker = ClientTracker::new();
+    let playback_remote = PlaybackRemote::new();
+    let client_cameras = args
+        .client_cameras
+        .unwrap_or_default()
+        .then(|| ClientCameras::new("base_link", args.camera.clone(), args.image.clone()));
+    // With client cameras, teleop messages drive the sender's own camera instead.
+    let teleop = (!args.client_cameras.unwrap_or_default()).then(TeleopSource::new);
+    let mut listeners = Listeners::new()
+        .with(client_tracker.clone())
+        .with(SubscriptionMonitor::new(client_tracker.clone()))
+        .with(playback_remote.clone());
+    if let Some(cameras) = &client_cameras {
+        listeners = listeners.with(cameras.clone());
+    }
+    if let Some(teleop) = &teleop {
+        listeners = listeners.with(Arc::new(teleop.clone()));
+    }
     let server = foxglove::WebSocketServer::new()
         .name(read_file_name)
-        .capabilities([Capability::Time])
+        .capabilities([Capability::Time, Capability::ClientPublish, Capability::Services])
+        .supported_encodings(["json"])
+        .services([playback_remote.clone().service()])
+        .listener(Arc::new(listeners))
+        .bind(
+            args.host.as_deref().unwrap_or(DEFAULT_HOST),
+            args.port.unwrap_or(DEFAULT_PORT),
+        )
         .start_blocking()
         .expect("Server failed to start");
 
-    let mcap = if args.r#write {
+    #[cfg(feature = "upload")]
+    let uploader = args.uploader();
+    let chapter_specs = args.chapter_specs().expect("Invalid chapters");
+    if !args.r#write.unwrap_or_default()
+        && (!chapter_specs.is_empty() || args.marker_chapters.unwrap_or_default())
+    {
+        warn!("Chapters are only recorded with --write");
+    }
+    // Declared before the controls so that, when unwinding, the terminal is restored first and
+    // any error finishing the file is visible.
+    let mut recording = if args.r#write.unwrap_or_default() {
         let timestamp = Local::now().format("%Y%m%d-%H%M%S");
-        let write_file_name = format!("{}-{}.mcap", FILE_NAME_PREFIX, timestamp);
-
-        println!("Writing to mcap");
-        Some(
-            McapWriter::new()
-                .create_new_buffered_file(&write_file_name)
-                .expect("Failed to start mcap writer")
-        )
+        let split = args.split_policy();
+        let recording = if split.is_enabled() {
+            Recording::create_split(format!("{}-{}", FILE_NAME_PREFIX, timestamp), split)
+        } else {
+            Recording::create(format!("{}-{}.mcap", FILE_NAME_PREFIX, timestamp))
+        }
+        .expect("Failed to start mcap writer");
+        info!("Writing to {}", recording.path().display());
+        Some(recording)
     } else {
-        println!("Not writing to mcap");
+        info!("Not writing to mcap");
         None
     };
 
-    let camera = CameraState::new("base_link", "camera");
+    let mut noise = args.noise.clone();
+    if let Some(kind) = args.noise_kind {
+        noise.kind = kind;
+    }
+    let mut depth = args.depth.clone();
+    if let Some(mode) = args.depth_mode {
+        depth.mode = mode;
+    }
+    let mut camera = CameraRig::new("base_link", "camera")
+        .with_physics(args.camera.clone())
+        .with_image_settings(args.image.clone())
+        .with_depth(depth)
+        .with_noise(noise)
+        .with_static_transforms(args.static_transforms.clone())
+        .with_transition(args.transition.clone());
+    if args.overlay.unwrap_or_default() || args.overlay_text.is_some() {
+        let mut overlay = args.overlay_settings.clone();
+        overlay.text.extend(args.overlay_text.iter().flatten().cloned());
+        camera = camera.with_overlay(overlay);
+    }
+
+    let timestamps = Clock::new(args.clock.unwrap_or_default());
+    diagnostics::set_clock(timestamps.clone());
+    let mut grid = args.grid.unwrap_or_default().then(|| {
+        let grid = Grid::new("base_link", args.grid_settings.clone())
+            .with_client_tracker(client_tracker.clone())
+            .with_clock(timestamps.clone());
+        grid.publish();
+        grid
+    });
+
+    let metrics = Metrics::new(Some(client_tracker.clone()));
+    let mut session = ReplaySession::builder(&file)
+        .looping(args.r#loop.unwrap_or_default())
+        .done_flag(done.clone())
+        .timestamp_clock(timestamps.clone())
+        .metrics(metrics.clone())
+        .io(args.io.unwrap_or_default())
+        .decode_pool(args.decode_pool_config())
+        .strict_encodings(args.strict_encodings.unwrap_or_default())
+        .throttle(args.throttle_config().expect("Invalid throttle options"))
+        .late_policy(
+            args.late_policy.unwrap_or_default(),
+            args.late_threshold_ms
+                .map(Duration::from_millis)
+                .unwrap_or(DEFAULT_LATE_THRESHOLD),
+        )
+        .error_policy(args.on_error.unwrap_or_default());
+    if let Some(topics) = args.topics.clone() {
+        session = session.topics(topics);
+    }
+    if let Some(compare) = &args.compare {
+        let options = CompareOptions {
+            prefix: args
+                .compare_prefix
+                .clone()
+                .unwrap_or_else(|| DEFAULT_COMPARE_PREFIX.to_string()),
+            offset_ns: args.compare_offset.map(|secs| (secs * 1e9) as i64),
+        };
+        session = session.compare(compare, options);
+    }
+    for spec in args.schema_override.iter().flatten() {
+        let (topic, schema) = parse_schema_override(spec).expect("Invalid --schema-override");
+        session = session.schema_override(topic, schema);
+    }
+    for spec in args.export_json.iter().flatten() {
+        let (topic, path) = parse_export_spec(spec).expect("Invalid --export-json");
+        session = session.export_json(topic, path);
+    }
+    if recording.is_none() {
+        // The recording captures every replayed channel, so only skip when nothing records.
+        session = session.skip_unsubscribed(client_tracker.clone());
+    }
+    if let Some(frame_id) = &args.chase {
+        info!("Chasing {frame_id}");
+        session = session.chase(frame_id);
+    }
+    #[cfg(feature = "scripting")]
+    if args.script.is_some() {
+        session = session.track_frames();
+    }
+    // Built before the controls take over the terminal, so the progress of scanning a file
+    // without a summary can be shown.
+    let session = session
+        .show_progress(true)
+        .build()
+        .expect("Failed to load file");
 
     // Non-blocking key check
     let mut camera = camera;
     let mut controls = Controls::new();
     controls.set_done_flag(done.clone());
+    controls.set_key_bindings(args.keys.clone());
+    controls.set_log_pane(log_pane);
+    controls.set_clock(timestamps.clone());
+    controls.set_keyframe_file(
+        args.keyframe_file
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_KEYFRAME_FILE)),
+    );
 
+    let control = session.control();
+    controls.set_playback_control(control.clone());
+    playback_remote.attach(control.clone(), session.clock(), session.summary().start_time());
+    controls.set_topic_toggles(session.summary().shared_channels(), session.muted_channels());
+    controls.set_topic_echo(session.topic_echo());
+    controls.set_replay_clock(session.clock());
+    controls.set_time_range(session.summary().start_time(), session.summary().end_time());
+    controls.set_bad_records(session.bad_records());
+    if args.paused.unwrap_or_default() {
+        // Waiting for a subscriber is moot when nothing streams until an explicit resume.
+        info!("Starting paused, press P or use the control API to resume");
+        control.set_paused(true);
+    } else if session.is_streaming() {
+        // Channels are only advertised once they are read, so there is nothing to subscribe to yet.
+        info!("Streaming input, not waiting for a client");
+    } else if !args.no_wait.unwrap_or_default() {
+        info!("Waiting for client");
+        if !client_tracker.wait_for_subscriber(wait_timeout, &done) {
+            info!("No client subscribed, starting anyway");
+        }
+    }
 
-    info!("Loading mcap summary");
-    let summary = Summary::load_from_mcap(&args.file).unwrap();
+    #[cfg(feature = "http")]
+    if let Some(addr) = &args.http {
+        HttpApi::new(session.control(), session.clock(), session.summary().start_time())
+            .with_metrics(metrics.clone())
+            .spawn(addr.as_str())
+            .expect("Failed to start HTTP control API");
+    }
 
-    info!("Waiting for client");
-    std::thread::sleep(Duration::from_secs(1));
+    let mut mixer = ControlMixer::new(args.control_priorities.clone());
+    mixer.add(controls.keyboard_source());
+    mixer.add(PoseRequestSource::new(control.clone()));
+    if let Some(teleop) = teleop {
+        mixer.add(teleop);
+    }
+    #[cfg(feature = "gamepad")]
+    if args.gamepad.unwrap_or_default() {
+        mixer.add(GamepadSource::open().expect("Failed to open gamepads"));
+    }
+    #[cfg(feature = "scripting")]
+    if let Some(path) = &args.script {
+        let script = CameraScript::load(path, session.frame_tracker());
+        mixer.add(script.expect("Failed to load camera script"));
+    }
+    #[cfg(feature = "ros2")]
+    let mut ros2 = args
+        .ros2
+        .unwrap_or_default()
+        .then(Ros2Bridge::open)
+        .transpose()
+        .expect("Failed to start ROS 2 bridge");
+    let clock = session.clock();
+    if let Some(path) = &args.follow {
+        let path = KeyframePath::load(path)
+            .expect("Failed to load keyframes")
+            .with_easing(args.transition.easing);
+        info!("Following {} keyframes", path.len());
+        mixer.add(FollowSource::new(path));
+    }
+    if let Some(target) = session.chase_target() {
+        let chase_cam = ChaseCam::new(args.chase_settings.clone());
+        mixer.add(ChaseSource::new(target, chase_cam));
+    }
 
-    info!("Starting stream");
+    let mut trajectory = args.export_trajectory.as_deref().map(|path| {
+        info!("Exporting the camera trajectory to {}", path.display());
+        TrajectoryWriter::create(path, args.trajectory_format.unwrap_or_default())
+            .expect("Failed to create trajectory file")
+    });
 
-    while !done.load(Ordering::Relaxed) {
-        let mut file_stream = summary.file_stream();
-        let mut file = BufReader::new(File::open(&args.file).unwrap());
-        let mut reader = LinearReader::new();
-        let mut last_camera_update_time = std::time::Instant::now();
-        while !done.load(Ordering::Relaxed)
-            && advance_reader(&mut reader, &mut file, |rec| {
-                file_stream.handle_record(&server, rec);
-                Ok(())
-            })
-            .context("read data").unwrap()
-        {
-            let time_since_last_camera_update = std::time::Instant::now().duration_since(last_camera_update_time);
-            if time_since_last_camera_update > std::time::Duration::from_millis(33) {
-                controls.capture_keys(&mut camera);
-                controls.debug_print(&camera);
-                camera.update();
-                camera.log_state();
-                last_camera_update_time = std::time::Instant::now();
+    let file_start_time = session.summary().start_time();
+    let mut chapter_markers = Vec::new();
+    let result = session
+        .run(&server, || {
+            controls.capture_keys(&camera);
+            mixer.tick(&mut camera, clock.elapsed_secs());
+            controls.debug_print(&camera);
+            camera.update();
+            let now_ns = timestamps.now_ns();
+            camera.update_overlay(&PlaybackStatus {
+                now_ns,
+                replay_secs: clock.elapsed_secs(),
+                rate: control.rate(),
+                paused: control.is_paused(),
+            });
+            camera.log_state(now_ns);
+            if let Some(cameras) = &client_cameras {
+                cameras.update(now_ns, &camera);
+            }
+            if let Some(writer) = &mut trajectory {
+                let (translation, rotation) = camera.published_transform();
+                if let Err(err) = writer.write(clock.now_ns(), &translation, &rotation) {
+                    warn!("Stopped exporting the trajectory: {err:#}");
+                    trajectory = None;
+                }
+            }
+            if let Some(grid) = &mut grid {
+                grid.update();
+            }
+            for marker in controls.take_markers() {
+                marker.publish();
+                if let Some(writer) = &recording {
+                    if let Err(err) = writer.write_metadata(MARKER_METADATA, marker.metadata()) {
+                        warn!("Failed to record marker: {err:#}");
+                    }
+                }
+                if args.marker_chapters.unwrap_or_default() {
+                    chapter_markers.push(marker);
+                }
+            }
+            if let Some(writer) = &mut recording {
+                if let Err(err) = writer.rotate_if_due() {
+                    error!("Failed to start the next output file: {err:#}");
+                    recording = None;
+                }
+            }
+            #[cfg(feature = "ros2")]
+            if let Some(bridge) = &ros2 {
+                if let Err(err) = bridge.publish(&camera, now_ns) {
+                    warn!("Stopped publishing to ROS 2: {err:#}");
+                    ros2 = None;
+                }
+            }
+            control.report_pose(camera.pose());
+        });
+
+    server.stop();
+    controls.close();
+    if let Some(trajectory) = trajectory {
+        if let Err(err) = trajectory.finish() {
+            error!("Failed to write trajectory: {err:#}");
+        }
+    }
+    if let Some(writer) = &recording {
+        // Streams have no summary, so their chapters are relative to the first replayed message.
+        let start_time = file_start_time.unwrap_or_else(|| clock.start_ns());
+        let all = chapter_specs
+            .iter()
+            .map(|spec| spec.resolve(start_time))
+            .filter_map(|chapter| chapter.inspect_err(|err| warn!("{err:#}")).ok())
+            .chain(chapters::marker_chapters(&chapter_markers, clock.now_ns()));
+        for chapter in all {
+            if let Err(err) = writer.write_metadata(CHAPTER_METADATA, chapter.metadata()) {
+                warn!("Failed to record chapter {:?}: {err:#}", chapter.name);
             }
         }
-        if !args.r#loop {
-            done.store(true, Ordering::Relaxed);
-        } else {
-            info!("Looping");
-            server.clear_session(None);
+    }
+    if let Some(recording) = recording {
+        match recording.close() {
+            #[cfg(feature = "upload")]
+            Ok(files) => {
+                if let Some(uploader) = &uploader {
+                    for file in &files {
+                        if let Err(err) = uploader.upload(file) {
+                            error!("Failed to upload recording: {err:#}");
+                        }
+                    }
+                }
+            }
+            #[cfg(not(feature = "upload"))]
+            Ok(_) => {}
+            Err(err) => error!("Failed to close mcap writer: {err:#}"),
         }
-      
-        // Sleep to maintain a consistent frame rate
-        std::thread::sleep(std::time::Duration::from_millis(33));
     }
-
-    server.stop();
-    if let Some(mcap) = mcap {
-        mcap.close().expect("Failed to close mcap writer");
+    if let Err(err) = result {
+        error!("Replay failed: {err:#}");
+        // `exit` skips destructors, so flush the log file first.
+        drop(log_guard);
+        std::process::exit(1);
     }
-    controls.close();
 }
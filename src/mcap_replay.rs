@@ -1,9 +1,7 @@
-
-/// Helper function to advance the mcap reader.
-
 use std::borrow::Cow;
 use std::collections::hash_map::Entry;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::fs::File;
 use std::io::{BufReader, Read, Seek, SeekFrom};
 use std::path::{Path, PathBuf};
@@ -12,22 +10,40 @@ use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use anyhow::{anyhow, Context, Result};
-use bytes::Buf;
+use bytes::{Buf, Bytes};
 use foxglove::{
     Channel, ChannelBuilder, PartialMetadata, Schema, 
     WebSocketServerBlockingHandle,
 };
+use indicatif::ProgressBar;
 
-use mcap::records::{MessageHeader, Record, SchemaHeader};
+use mcap::records::{ChunkIndex, MessageHeader, Record, SchemaHeader, Statistics};
 use mcap::sans_io::read::{LinearReader, LinearReaderOptions, ReadAction};
+use memmap2::Mmap;
+use parking_lot::{Mutex, RwLock};
+use serde::Deserialize;
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+
+use crate::bad_records::CorruptRecord;
+use crate::chase::ChaseTarget;
+use crate::client_tracker::ClientTracker;
+use crate::json_export::JsonExport;
+use crate::metrics::Metrics;
+use crate::playback::PlaybackControl;
+use crate::progress;
+use crate::schema_override::SchemaOverride;
+use crate::throttle::{Throttle, ThrottleConfig};
+use crate::ChannelId;
 
+/// Helper function to advance the mcap reader.
 pub fn advance_reader<R, F>(
     reader: &mut LinearReader,
     file: &mut R,
     mut handle_record: F,
 ) -> Result<bool>
 where
-    R: Read + Seek,
+    R: Read,
     F: FnMut(Record<'_>) -> Result<()>,
 {
     if let Some(action) = reader.next_action() {
@@ -47,16 +63,180 @@ where
     }
 }
 
+/// Controls which channels a [`Summary`] creates, and with which schemas.
+#[derive(Debug, Clone, Default)]
+pub struct SummaryOptions {
+    /// Only create channels for these topics (all topics, if `None`).
+    pub topics: Option<HashSet<String>>,
+    /// Schemas to use instead of the ones in the file, by topic.
+    pub schema_overrides: HashMap<String, SchemaOverride>,
+    /// Prefix for the published topic names, e.g. `/b` to publish `/camera` as `/b/camera`.
+    /// Topic filters and schema overrides still match the names in the file.
+    pub topic_prefix: Option<String>,
+    /// Show a progress bar on stderr while scanning a file without a summary section.
+    pub show_progress: bool,
+    /// Fail on channels whose message and schema encodings Foxglove can't decode, instead of
+    /// advertising them with a warning.
+    pub strict_encodings: bool,
+}
+
+/// Message encodings Foxglove can decode, with the schema encodings each can be paired with
+/// (`""` for channels without a schema).
+const SUPPORTED_ENCODINGS: &[(&str, &[&str])] = &[
+    ("ros1", &["ros1msg"]),
+    ("cdr", &["ros2msg", "ros2idl", "omgidl"]),
+    ("protobuf", &["protobuf"]),
+    ("flatbuffer", &["flatbuffer"]),
+    ("json", &["jsonschema", ""]),
+    ("cbor", &[""]),
+    ("msgpack", &[""]),
+];
+
+/// A channel rejected by [`SummaryOptions::strict_encodings`].
+#[derive(Debug)]
+pub struct UnsupportedEncoding {
+    pub topic: String,
+    pub message_encoding: String,
+    pub schema_encoding: String,
+}
+
+impl fmt::Display for UnsupportedEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {:?} messages with a {:?} schema can't be decoded by Foxglove",
+            self.topic, self.message_encoding, self.schema_encoding
+        )
+    }
+}
+
+impl std::error::Error for UnsupportedEncoding {}
+
+/// Returns true if Foxglove can decode messages with these encodings.
+pub fn is_supported_encoding(message_encoding: &str, schema_encoding: &str) -> bool {
+    SUPPORTED_ENCODINGS
+        .iter()
+        .any(|(message, schemas)| *message == message_encoding && schemas.contains(&schema_encoding))
+}
+
+impl SummaryOptions {
+    /// Options that only restrict the topics.
+    pub fn with_topics(topics: Option<&[String]>) -> Self {
+        Self {
+            topics: topics.map(|t| t.iter().cloned().collect()),
+            ..Default::default()
+        }
+    }
+}
+
+/// Channels created from a file, by MCAP channel id. Shared so that channels discovered while
+/// streaming are visible to everyone holding the map.
+pub type SharedChannels = Arc<RwLock<HashMap<u16, Arc<Channel>>>>;
+
+/// Channels that are muted at runtime; their messages are read and paced but not published.
+pub type MutedChannels = Arc<RwLock<HashSet<ChannelId>>>;
+
+/// Keeps a copy of the most recent message on one selected channel, for display while
+/// streaming. Messages on other channels aren't copied.
+#[derive(Default)]
+pub struct TopicEcho {
+    state: Mutex<EchoState>,
+}
+
+#[derive(Default)]
+struct EchoState {
+    channel: Option<ChannelId>,
+    latest: Option<(u64, Bytes)>,
+}
+
+impl TopicEcho {
+    /// Starts echoing `channel`, or stops echoing if `None`.
+    pub fn select(&self, channel: Option<ChannelId>) {
+        let mut state = self.state.lock();
+        state.channel = channel;
+        state.latest = None;
+    }
+
+    /// Returns the echoed channel.
+    pub fn selected(&self) -> Option<ChannelId> {
+        self.state.lock().channel
+    }
+
+    /// Keeps the message if it is on the echoed channel.
+    pub fn record(&self, channel: ChannelId, log_time: u64, data: &[u8]) {
+        let mut state = self.state.lock();
+        if state.channel == Some(channel) {
+            state.latest = Some((log_time, Bytes::copy_from_slice(data)));
+        }
+    }
+
+    /// Returns the log time and data of the latest message on the echoed channel.
+    pub fn latest(&self) -> Option<(u64, Bytes)> {
+        self.state.lock().latest.clone()
+    }
+}
+
 #[derive(Default)]
 pub struct Summary {
     path: PathBuf,
     schemas: HashMap<u16, Schema>,
-    channels: HashMap<u16, Arc<Channel>>,
+    channels: SharedChannels,
+    options: SummaryOptions,
+    statistics: Option<Statistics>,
+    /// Chunk index records from the summary section, in file order.
+    chunk_indexes: Vec<ChunkIndex>,
+    /// First and last message log times seen while scanning a file without a summary.
+    scanned_range: Option<(u64, u64)>,
+    /// Highest channel id in the file, including channels that were filtered out.
+    max_channel_id: Option<u16>,
 }
 
 impl Summary {
+    /// Creates an empty summary, to be filled in with [`Summary::handle_record`] or the schema
+    /// and channel handlers by sources that aren't MCAP files.
+    pub fn new(path: &Path, options: &SummaryOptions) -> Self {
+        Summary {
+            path: path.to_owned(),
+            options: options.clone(),
+            ..Default::default()
+        }
+    }
+
     pub fn load_from_mcap(path: &Path) -> Result<Self> {
+        Self::load_from_mcap_with_options(path, &SummaryOptions::default())
+    }
+
+    /// Loads the summary, only creating channels for the given topics (or all, if `None`).
+    pub fn load_from_mcap_with_topics(path: &Path, topics: Option<&[String]>) -> Result<Self> {
+        Self::load_from_mcap_with_options(path, &SummaryOptions::with_topics(topics))
+    }
+
+    /// Loads the summary, creating channels as configured by `options`.
+    ///
+    /// Files without a usable summary section, e.g. unfinalized or truncated recordings, fall
+    /// back to [`Summary::scan_from_mcap`].
+    pub fn load_from_mcap_with_options(path: &Path, options: &SummaryOptions) -> Result<Self> {
+        match Self::read_summary_section(path, options)? {
+            Ok(summary) => Ok(summary),
+            Err(reason) => {
+                warn!(
+                    "{} has {reason}, rebuilding channels from the data section",
+                    path.display()
+                );
+                Self::scan_from_mcap(path, options)
+            }
+        }
+    }
+
+    /// Reads the summary section, returning the reason if the file doesn't have a usable one.
+    fn read_summary_section(
+        path: &Path,
+        options: &SummaryOptions,
+    ) -> Result<Result<Self, &'static str>> {
         let mut file = BufReader::new(File::open(path)?);
+        if file.get_ref().metadata()?.len() < 28 {
+            return Ok(Err("no footer"));
+        }
 
         // Read the last 28 bytes of the file to validate the trailing magic (8 bytes) and obtain
         // the summary start value, which is the first u64 in the footer record (20 bytes).
@@ -64,13 +244,13 @@ impl Summary {
         file.seek(SeekFrom::End(-28)).context("seek footer")?;
         file.read_to_end(&mut buf).context("read footer")?;
         if !buf.ends_with(mcap::MAGIC) {
-            return Err(anyhow!("bad footer magic"));
+            return Ok(Err("bad footer magic"));
         }
 
         // Seek to summary section.
         let summary_start = buf.as_slice().get_u64_le();
         if summary_start == 0 {
-            return Err(anyhow!("missing summary section"));
+            return Ok(Err("missing summary section"));
         }
         file.seek(SeekFrom::Start(summary_start))
             .context("seek summary")?;
@@ -80,18 +260,122 @@ impl Summary {
             ..Default::default()
         });
 
-        let mut summary = Summary {
-            path: path.to_owned(),
-            schemas: HashMap::new(),
-            channels: HashMap::new(),
-        };
+        let mut summary = Summary::new(path, options);
         while advance_reader(&mut reader, &mut file, |rec| summary.handle_record(rec))
             .context("read summary")?
         {}
 
+        Ok(Ok(summary))
+    }
+
+    /// Rebuilds the summary by reading the data section front to back.
+    ///
+    /// This decompresses every chunk, so it's much slower than reading the summary section.
+    /// Scanning stops with a warning at the first damaged or truncated record, keeping whatever
+    /// was found before it. The first and last message log times stand in for the Statistics
+    /// record.
+    pub fn scan_from_mcap(path: &Path, options: &SummaryOptions) -> Result<Self> {
+        let file = File::open(path)?;
+        let bar = if options.show_progress {
+            progress::bytes_bar(file.metadata()?.len(), "Scanning")
+        } else {
+            ProgressBar::hidden()
+        };
+        let mut file = BufReader::new(bar.wrap_read(file));
+        let mut reader = LinearReader::new();
+        let mut summary = Summary::new(path, options);
+        let mut range: Option<(u64, u64)> = None;
+        loop {
+            let more = advance_reader(&mut reader, &mut file, |rec| {
+                if let Record::Message { header, .. } = &rec {
+                    let (start, end) = range.get_or_insert((header.log_time, header.log_time));
+                    *start = (*start).min(header.log_time);
+                    *end = (*end).max(header.log_time);
+                }
+                summary.handle_record(rec)
+            });
+            match more {
+                Ok(true) => {}
+                Ok(false) => break,
+                Err(e) if e.is::<UnsupportedEncoding>() => return Err(e),
+                Err(e) => {
+                    warn!("Stopped scanning {} early: {e:#}", path.display());
+                    break;
+                }
+            }
+        }
+        bar.finish_and_clear();
+        summary.scanned_range = range;
         Ok(summary)
     }
 
+    /// Returns an empty summary that registers its channels into this one.
+    ///
+    /// Streaming sources use this to discover channels during the linear pass while the replay
+    /// loop publishes from the original.
+    pub fn shared(&self) -> Self {
+        Summary {
+            path: self.path.clone(),
+            channels: self.channels.clone(),
+            options: self.options.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// Returns the channel map, which grows as channels are discovered in streamed input.
+    pub fn shared_channels(&self) -> SharedChannels {
+        self.channels.clone()
+    }
+
+    /// Returns the path of the summarized file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the log time of the first message, if the file has a Statistics record or was
+    /// scanned.
+    pub fn start_time(&self) -> Option<u64> {
+        self.statistics
+            .as_ref()
+            .map(|s| s.message_start_time)
+            .or(self.scanned_range.map(|(start, _)| start))
+    }
+
+    /// Returns the log time of the last message, if the file has a Statistics record or was
+    /// scanned.
+    pub fn end_time(&self) -> Option<u64> {
+        self.statistics
+            .as_ref()
+            .map(|s| s.message_end_time)
+            .or(self.scanned_range.map(|(_, end)| end))
+    }
+
+    /// Returns the chunk index records, which are empty for unchunked files and files without a
+    /// summary section.
+    pub fn chunk_indexes(&self) -> &[ChunkIndex] {
+        &self.chunk_indexes
+    }
+
+    /// Adds another summary's channels under ids shifted by `id_offset`, so two files can be
+    /// replayed through one channel map. Messages from the other file must have their channel
+    /// ids shifted the same way.
+    pub fn adopt_channels(&self, other: &Summary, id_offset: u16) -> Result<()> {
+        let mut channels = self.channels.write();
+        for (id, channel) in other.channels.read().iter() {
+            let id = id
+                .checked_add(id_offset)
+                .ok_or_else(|| anyhow!("too many channels to combine"))?;
+            channels.insert(id, channel.clone());
+        }
+        Ok(())
+    }
+
+    /// Returns the highest channel id in the file, including channels that were filtered out,
+    /// or `None` if there are no channels.
+    pub fn max_channel_id(&self) -> Option<u16> {
+        self.max_channel_id
+    }
+
     /// Creates a new file stream.
     pub fn file_stream(&self) -> FileStream<'_> {
         FileStream::new(&self.path, &self.channels)
@@ -102,6 +386,14 @@ impl Summary {
         match record {
             Record::Schema { header, data } => self.handle_schema(&header, data),
             Record::Channel(channel) => self.handle_channel(channel),
+            Record::Statistics(statistics) => {
+                self.statistics = Some(statistics);
+                Ok(())
+            }
+            Record::ChunkIndex(index) => {
+                self.chunk_indexes.push(index);
+                Ok(())
+            }
             _ => Ok(()),
         }
     }
@@ -122,13 +414,44 @@ impl Summary {
         Ok(())
     }
 
-    /// Registers a new channel.
+    /// Registers a new channel, using the schema override for its topic if there is one.
     pub fn handle_channel(&mut self, record: mcap::records::Channel) -> Result<(), anyhow::Error> {
-        if let Entry::Vacant(entry) = self.channels.entry(record.id) {
-            let schema = self.schemas.get(&record.schema_id).cloned();
-            let channel = ChannelBuilder::new(record.topic)
+        self.max_channel_id = self.max_channel_id.max(Some(record.id));
+        if let Some(topics) = &self.options.topics {
+            if !topics.contains(&record.topic) {
+                return Ok(());
+            }
+        }
+        if let Entry::Vacant(entry) = self.channels.write().entry(record.id) {
+            let file_schema = self.schemas.get(&record.schema_id);
+            let schema = match self.options.schema_overrides.get(&record.topic) {
+                Some(schema_override) => Some(schema_override.schema(
+                    file_schema.map(|s| s.name.as_str()),
+                    &record.message_encoding,
+                )),
+                None => file_schema.cloned(),
+            };
+            let schema_encoding = schema.as_ref().map_or("", |s| s.encoding.as_str());
+            if !is_supported_encoding(&record.message_encoding, schema_encoding) {
+                let unsupported = UnsupportedEncoding {
+                    topic: record.topic.clone(),
+                    message_encoding: record.message_encoding.clone(),
+                    schema_encoding: schema_encoding.to_string(),
+                };
+                if self.options.strict_encodings {
+                    return Err(unsupported.into());
+                }
+                warn!("{unsupported}");
+            }
+            let topic = match &self.options.topic_prefix {
+                Some(prefix) => prefixed_topic(prefix, &record.topic),
+                None => record.topic,
+            };
+            // Metadata isn't part of the websocket advertisement, but recordings keep it.
+            let channel = ChannelBuilder::new(topic)
                 .message_encoding(&record.message_encoding)
                 .schema(schema)
+                .metadata(record.metadata)
                 .build()?;
             entry.insert(channel);
         }
@@ -136,22 +459,90 @@ impl Summary {
     }
 }
 
+/// Joins a prefix and a topic with exactly one slash between them.
+pub fn prefixed_topic(prefix: &str, topic: &str) -> String {
+    format!(
+        "{}/{}",
+        prefix.trim_end_matches('/'),
+        topic.trim_start_matches('/')
+    )
+}
+
 pub struct FileStream<'a> {
     pub path: PathBuf,
-    channels: &'a HashMap<u16, Arc<Channel>>,
+    channels: &'a RwLock<HashMap<u16, Arc<Channel>>>,
     time_tracker: Option<TimeTracker>,
+    metrics: Option<Arc<Metrics>>,
+    throttle: Option<Throttle>,
+    muted: Option<MutedChannels>,
+    json_export: Option<Arc<Mutex<JsonExport>>>,
+    echo: Option<Arc<TopicEcho>>,
+    chase: Vec<Arc<ChaseTarget>>,
+    subscriptions: Option<Arc<ClientTracker>>,
 }
 
 impl<'a> FileStream<'a> {
     /// Creates a new file stream.
-    pub fn new(path: &Path, channels: &'a HashMap<u16, Arc<Channel>>) -> Self {
+    pub fn new(path: &Path, channels: &'a RwLock<HashMap<u16, Arc<Channel>>>) -> Self {
         Self {
             path: path.to_owned(),
             channels,
             time_tracker: None,
+            metrics: None,
+            throttle: None,
+            muted: None,
+            json_export: None,
+            echo: None,
+            chase: Vec::new(),
+            subscriptions: None,
         }
     }
 
+    /// Drops messages that exceed the bandwidth or per-topic rate limits.
+    pub fn with_throttle(mut self, config: ThrottleConfig) -> Self {
+        self.throttle = Some(Throttle::new(config));
+        self
+    }
+
+    /// Skips messages on channels in the muted set, which may change while streaming.
+    pub fn with_muted_channels(mut self, muted: MutedChannels) -> Self {
+        self.muted = Some(muted);
+        self
+    }
+
+    /// Appends messages on exported topics to their JSON files, whether or not they are muted
+    /// or throttled.
+    pub fn with_json_export(mut self, json_export: Arc<Mutex<JsonExport>>) -> Self {
+        self.json_export = Some(json_export);
+        self
+    }
+
+    /// Keeps the latest message on the echoed channel, whether or not it is muted or throttled.
+    pub fn with_topic_echo(mut self, echo: Arc<TopicEcho>) -> Self {
+        self.echo = Some(echo);
+        self
+    }
+
+    /// Tracks the chased frame in replayed transforms, whether or not they are muted or
+    /// throttled. May be called more than once.
+    pub fn with_chase_target(mut self, chase: Arc<ChaseTarget>) -> Self {
+        self.chase.push(chase);
+        self
+    }
+
+    /// Skips logging to channels no client is subscribed to. Messages are still read and paced,
+    /// so time keeps moving, and still exported, echoed, and chased.
+    pub fn with_subscriptions(mut self, subscriptions: Arc<ClientTracker>) -> Self {
+        self.subscriptions = Some(subscriptions);
+        self
+    }
+
+    /// Records throughput for every published message.
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
     /// Streams the file content until `done` is set.
     pub fn stream_until(
         mut self,
@@ -189,12 +580,48 @@ impl<'a> FileStream<'a> {
             .get_or_insert_with(|| TimeTracker::start(header.log_time));
 
         tt.sleep_until(header.log_time);
+        let on_time = tt.catch_up(header.log_time);
 
         if let Some(timestamp) = tt.notify() {
             server.broadcast_time(timestamp);
         }
 
-        if let Some(channel) = self.channels.get(&header.channel_id) {
+        if on_time {
+            self.publish(&header, data);
+        }
+    }
+
+    /// Logs the message data to its channel without pacing.
+    pub fn publish(&mut self, header: &MessageHeader, data: &[u8]) {
+        let channel = self.channels.read().get(&header.channel_id).cloned();
+        if let Some(channel) = channel {
+            if let Some(json_export) = &self.json_export {
+                json_export.lock().write(&channel, header, data);
+            }
+            if let Some(echo) = &self.echo {
+                echo.record(u64::from(channel.id()), header.log_time, data);
+            }
+            for chase in &self.chase {
+                chase.observe(&channel, header.log_time, data);
+            }
+            if self.muted.as_ref().is_some_and(|m| m.read().contains(&u64::from(channel.id()))) {
+                return;
+            }
+            if self
+                .subscriptions
+                .as_ref()
+                .is_some_and(|s| !s.is_subscribed(u64::from(channel.id())))
+            {
+                return;
+            }
+            if let Some(throttle) = &mut self.throttle {
+                if !throttle.admit(channel.topic(), data.len()) {
+                    if let Some(metrics) = &self.metrics {
+                        metrics.record_drop(channel.topic());
+                    }
+                    return;
+                }
+            }
             channel.log_with_meta(
                 data,
                 PartialMetadata {
@@ -203,17 +630,381 @@ impl<'a> FileStream<'a> {
                     publish_time: Some(header.publish_time),
                 },
             );
+            if let Some(metrics) = &self.metrics {
+                metrics.record_message(channel.topic(), data.len());
+            }
+        }
+    }
+}
+
+/// A message read from the file, detached from the reader's buffer.
+pub struct OwnedMessage {
+    pub header: MessageHeader,
+    pub data: Bytes,
+}
+
+/// A message that is due for publishing, with an optional time reference to broadcast first.
+///
+/// `message` is `None` when the message was dropped by [`LatePolicy::Skip`] but the time
+/// reference still needs to go out.
+pub struct PacedMessage {
+    pub message: Option<OwnedMessage>,
+    pub timestamp: Option<u64>,
+    /// How far behind its scheduled wallclock time the message was released.
+    pub late_by: Duration,
+}
+
+/// How often a waiting pacer re-checks for pause and rate changes.
+const CONTROL_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// How much earlier than a deadline to stop sleeping and start spinning, on top of the measured
+/// sleep overshoot. OS and tokio timers are only accurate to a millisecond or so.
+const SPIN_MARGIN: Duration = Duration::from_micros(500);
+
+/// How far behind the wallclock a message may be before the late policy applies.
+pub const DEFAULT_LATE_THRESHOLD: Duration = Duration::from_millis(100);
+
+/// What to do when replay falls behind the wallclock, e.g. after a decompression or network stall.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LatePolicy {
+    /// Drop messages that are too late until replay catches up.
+    Skip,
+    /// Re-anchor the clock so replay continues at normal speed from the late message.
+    Stretch,
+    /// Publish late messages as fast as possible until caught up.
+    #[default]
+    Burst,
+}
+
+/// Size of the read-ahead buffer used by the buffered reader.
+const READ_BUFFER_SIZE: usize = 1 << 20;
+
+/// How the file is read during replay.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IoMode {
+    /// Async reads through a read-ahead buffer.
+    #[default]
+    Buffered,
+    /// Memory-map the file and parse records in place on a blocking thread.
+    Mmap,
+    /// Memory-map the file and decompress upcoming chunks on a pool of worker threads. Falls
+    /// back to `mmap` for files without a chunk index.
+    Parallel,
+}
+
+/// Returns the offset of the first chunk that may contain messages logged at or after
+/// `log_time`, or `None` if there is no chunk index to go by.
+///
+/// Every chunk before that offset ends before `log_time`, so reading can start there instead of
+/// at the top of the file.
+pub fn seek_offset(chunk_indexes: &[ChunkIndex], log_time: u64) -> Option<u64> {
+    if chunk_indexes.is_empty() {
+        return None;
+    }
+    Some(
+        chunk_indexes
+            .iter()
+            .filter(|index| index.message_end_time >= log_time)
+            .map(|index| index.chunk_start_offset)
+            .min()
+            // Past the last message: nothing left to replay, so start at the end of the data.
+            .unwrap_or_else(|| {
+                chunk_indexes
+                    .iter()
+                    .map(|index| index.chunk_start_offset + index.chunk_length)
+                    .max()
+                    .unwrap_or_default()
+            }),
+    )
+}
+
+/// Where a linear read has got to, so that a bad record can be skipped by resuming at the next
+/// chunk.
+struct ReadPosition {
+    consumed: u64,
+    last_good: u64,
+    sent_until: Option<u64>,
+}
+
+impl ReadPosition {
+    fn new(start_offset: Option<u64>) -> Self {
+        let offset = start_offset.unwrap_or(0);
+        Self {
+            consumed: offset,
+            last_good: offset,
+            sent_until: None,
+        }
+    }
+
+    fn corrupt(&self, error: mcap::McapError) -> anyhow::Error {
+        CorruptRecord {
+            last_good: Some(self.last_good),
+            consumed: Some(self.consumed),
+            sent_until: self.sent_until,
+            error,
+        }
+        .into()
+    }
+}
+
+/// Creates a reader for a file read from the top, or from a record boundary at `start_offset`.
+fn reader_at(start_offset: Option<u64>) -> LinearReader {
+    LinearReader::new_with_options(LinearReaderOptions {
+        skip_start_magic: start_offset.is_some(),
+        ..Default::default()
+    })
+}
+
+/// Reads messages from the file and forwards them until the file ends, `done` is set, or the
+/// receiver hangs up.
+///
+/// Messages logged before `skip_until` are dropped, which is how seeking is implemented. When
+/// `start_offset` is given (see [`seek_offset`]), reading starts at that record instead of the
+/// top of the file, so a seek doesn't have to decompress every chunk before the target.
+///
+/// Records that can't be read end the read with a [`CorruptRecord`] error.
+pub async fn read_messages(
+    path: PathBuf,
+    tx: mpsc::Sender<OwnedMessage>,
+    done: Arc<AtomicBool>,
+    skip_until: Option<u64>,
+    start_offset: Option<u64>,
+) -> Result<()> {
+    tokio::task::spawn_blocking(move || {
+        let mut file = File::open(&path)?;
+        if let Some(offset) = start_offset {
+            file.seek(SeekFrom::Start(offset)).context("seek chunk")?;
+        }
+        let input = BufReader::with_capacity(READ_BUFFER_SIZE, file);
+        read_linear(input, &tx, &done, skip_until, start_offset)
+    })
+    .await
+    .context("buffered reader")?
+}
+
+/// Reads messages with a linear reader from `input`, which is positioned at the top of the file
+/// or at the record boundary `start_offset`. This blocks, so run it with `spawn_blocking`.
+fn read_linear<R: Read>(
+    mut input: R,
+    tx: &mpsc::Sender<OwnedMessage>,
+    done: &AtomicBool,
+    skip_until: Option<u64>,
+    start_offset: Option<u64>,
+) -> Result<()> {
+    let mut reader = reader_at(start_offset);
+    let mut position = ReadPosition::new(start_offset);
+    while let Some(action) = reader.next_action() {
+        if done.load(Ordering::Relaxed) {
+            break;
+        }
+        match action.map_err(|e| position.corrupt(e))? {
+            ReadAction::NeedMore(count) => {
+                let count = std::io::Read::read(&mut input, reader.insert(count))?;
+                reader.set_written(count);
+                position.consumed += count as u64;
+            }
+            ReadAction::GetRecord { data, opcode } => {
+                let record = mcap::parse_record(opcode, data).map_err(|e| position.corrupt(e))?;
+                position.last_good = position.consumed;
+                if let Record::Message { header, data } = record {
+                    if skip_until.is_some_and(|t| header.log_time < t) {
+                        continue;
+                    }
+                    position.sent_until = Some(header.log_time);
+                    let message = OwnedMessage {
+                        header,
+                        data: Bytes::copy_from_slice(&data),
+                    };
+                    if tx.blocking_send(message).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Opens and memory-maps a file for reading.
+pub fn map_file(path: &Path) -> Result<Mmap> {
+    let file = File::open(path).with_context(|| format!("open {}", path.display()))?;
+    // SAFETY: the mapping is read-only. As with any mmap, truncating the file while it is mapped
+    // is undefined behavior; we accept that for finished recordings.
+    unsafe { Mmap::map(&file) }.with_context(|| format!("mmap {}", path.display()))
+}
+
+/// Reads messages from a memory-mapped file. This blocks, so run it with `spawn_blocking`.
+///
+/// Records are parsed straight out of the mapping instead of being copied into the reader's
+/// buffer first, which keeps up with dense logs far better than the buffered path.
+///
+/// Records that can't be read end the read with a [`CorruptRecord`] error. Its offsets are only
+/// known when reading from `start_offset`.
+pub fn read_messages_mmap(
+    path: &Path,
+    tx: mpsc::Sender<OwnedMessage>,
+    done: Arc<AtomicBool>,
+    skip_until: Option<u64>,
+    start_offset: Option<u64>,
+) -> Result<()> {
+    let mmap = map_file(path)?;
+    #[cfg(unix)]
+    if let Err(e) = mmap.advise(memmap2::Advice::Sequential) {
+        debug!("madvise failed: {e}");
+    }
+    if let Some(offset) = start_offset {
+        // MessageStream needs the leading magic, so read from the chunk with a linear reader.
+        let input = mmap.get(offset as usize..).context("seek chunk")?;
+        return read_linear(input, &tx, &done, skip_until, start_offset);
+    }
+    let mut sent_until = None;
+    for message in mcap::MessageStream::new(&mmap).context("read data")? {
+        if done.load(Ordering::Relaxed) {
+            break;
+        }
+        let message = message.map_err(|error| CorruptRecord {
+            last_good: None,
+            consumed: None,
+            sent_until,
+            error,
+        })?;
+        if skip_until.is_some_and(|t| message.log_time < t) {
+            continue;
+        }
+        sent_until = Some(message.log_time);
+        let message = OwnedMessage {
+            header: MessageHeader {
+                channel_id: message.channel.id,
+                sequence: message.sequence,
+                log_time: message.log_time,
+                publish_time: message.publish_time,
+            },
+            data: Bytes::copy_from_slice(&message.data),
+        };
+        if tx.blocking_send(message).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Reads a non-seekable MCAP stream front to back. This blocks, so run it with `spawn_blocking`.
+///
+/// Schema and Channel records are passed to `summary` as they appear, so channels become
+/// available to clients just before their first message.
+pub fn read_messages_streaming<R: Read>(
+    mut input: R,
+    summary: &mut Summary,
+    tx: mpsc::Sender<OwnedMessage>,
+    done: Arc<AtomicBool>,
+) -> Result<()> {
+    let mut reader = LinearReader::new();
+    let mut closed = false;
+    while !done.load(Ordering::Relaxed)
+        && !closed
+        && advance_reader(&mut reader, &mut input, |record| {
+            match record {
+                Record::Message { header, data } => {
+                    let message = OwnedMessage {
+                        header,
+                        data: Bytes::copy_from_slice(&data),
+                    };
+                    closed = tx.blocking_send(message).is_err();
+                }
+                record => summary.handle_record(record)?,
+            }
+            Ok(())
+        })
+        .context("read data")?
+    {}
+    Ok(())
+}
+
+/// Holds each message back until its log time is due relative to the wallclock, honoring the
+/// pause state and rate in `control`, and `late_policy` when replay falls behind.
+pub async fn pace_messages(
+    mut rx: mpsc::Receiver<OwnedMessage>,
+    tx: mpsc::Sender<PacedMessage>,
+    control: Arc<PlaybackControl>,
+    late_policy: LatePolicy,
+    late_threshold: Duration,
+) {
+    let mut time_tracker: Option<TimeTracker> = None;
+    let mut skipping = false;
+    while let Some(message) = rx.recv().await {
+        let log_time = message.header.log_time;
+        let tt = time_tracker.get_or_insert_with(|| {
+            TimeTracker::start(log_time).with_late_policy(late_policy, late_threshold)
+        });
+        loop {
+            if control.is_paused() {
+                // Keep the clock anchored to "now" so playback resumes where it left off.
+                tokio::time::sleep(CONTROL_POLL_INTERVAL).await;
+                tt.reanchor();
+                continue;
+            }
+            tt.set_rate(control.rate());
+            let delay = tt.delay_until(log_time);
+            if delay < Duration::from_micros(1) {
+                break;
+            }
+            let window = tt.spin_window();
+            if delay > window {
+                let nap = (delay - window).min(CONTROL_POLL_INTERVAL);
+                let wake = Instant::now() + nap;
+                tokio::time::sleep(nap).await;
+                tt.record_sleep(wake);
+            } else {
+                // Too close to the deadline for the timer; spin, but let other tasks run.
+                tokio::task::yield_now().await;
+            }
+        }
+        let late_by = tt.record_release(log_time);
+        let on_time = tt.catch_up(log_time);
+        tt.set_now(log_time);
+        let timestamp = tt.notify();
+        if on_time == skipping {
+            skipping = !on_time;
+            if skipping {
+                warn!("Replay is {late_by:?} behind, skipping late messages");
+            } else {
+                info!("Replay caught up");
+            }
+        }
+        if !on_time && timestamp.is_none() {
+            continue;
+        }
+        let paced = PacedMessage {
+            message: on_time.then_some(message),
+            timestamp,
+            late_by,
+        };
+        if tx.send(paced).await.is_err() {
+            break;
         }
     }
 }
 
 /// Helper for keep tracking of the relationship between a file timestamp and the wallclock.
+///
+/// Deadlines are computed from a fixed anchor rather than from the previous message, so sleep
+/// errors don't accumulate over a long replay. Waits sleep until shortly before the deadline and
+/// spin for the rest; the sleep's typical overshoot is measured and folded into how early the
+/// spin starts.
 pub struct TimeTracker {
     start: Instant,
     offset_ns: u64,
     now_ns: u64,
+    rate: f64,
+    late_policy: LatePolicy,
+    late_threshold: Duration,
     notify_interval_ns: u64,
     notify_last: u64,
+    sleep_overshoot: Duration,
+    total_drift: Duration,
+    released: u32,
 }
 impl TimeTracker {
     /// Initializes a new time tracker, treating "now" as the specified offset from epoch.
@@ -222,18 +1013,113 @@ impl TimeTracker {
             start: Instant::now(),
             offset_ns,
             now_ns: offset_ns,
+            rate: 1.0,
+            late_policy: LatePolicy::default(),
+            late_threshold: DEFAULT_LATE_THRESHOLD,
             notify_interval_ns: 1_000_000_000 / 60,
             notify_last: 0,
+            sleep_overshoot: Duration::ZERO,
+            total_drift: Duration::ZERO,
+            released: 0,
+        }
+    }
+
+    /// Sets how to handle messages that are more than `threshold` behind the wallclock.
+    pub fn with_late_policy(mut self, policy: LatePolicy, threshold: Duration) -> Self {
+        self.late_policy = policy;
+        self.late_threshold = threshold;
+        self
+    }
+
+    /// Applies the late policy to a message that is due now. Returns false if the message
+    /// should be dropped.
+    pub fn catch_up(&mut self, offset_ns: u64) -> bool {
+        if self.lateness(offset_ns) <= self.late_threshold {
+            return true;
+        }
+        match self.late_policy {
+            LatePolicy::Burst => true,
+            LatePolicy::Skip => false,
+            LatePolicy::Stretch => {
+                self.start = Instant::now();
+                self.offset_ns = offset_ns;
+                true
+            }
         }
     }
 
+    /// Treats the current file time as "now" on the wallclock, e.g. after a pause.
+    pub fn reanchor(&mut self) {
+        self.start = Instant::now();
+        self.offset_ns = self.now_ns;
+    }
+
+    /// Changes the playback rate, re-anchoring so the change only affects future messages.
+    pub fn set_rate(&mut self, rate: f64) {
+        if rate != self.rate {
+            // Anchor at the clock's current file position rather than the last message, so the
+            // schedule continues without a jump.
+            let elapsed = self.start.elapsed().mul_f64(self.rate);
+            self.start = Instant::now();
+            self.offset_ns += elapsed.as_nanos() as u64;
+            self.rate = rate;
+        }
+    }
+
+    /// Records that file time has reached the specified offset.
+    pub fn set_now(&mut self, offset_ns: u64) {
+        self.now_ns = offset_ns;
+    }
+
+    /// Returns how long to wait before the specified offset is due.
+    pub fn delay_until(&self, offset_ns: u64) -> Duration {
+        let abs = Duration::from_nanos(offset_ns.saturating_sub(self.offset_ns)).div_f64(self.rate);
+        abs.saturating_sub(self.start.elapsed())
+    }
+
+    /// Returns how far the wallclock has run past the specified offset.
+    pub fn lateness(&self, offset_ns: u64) -> Duration {
+        let abs = Duration::from_nanos(offset_ns.saturating_sub(self.offset_ns)).div_f64(self.rate);
+        self.start.elapsed().saturating_sub(abs)
+    }
+
+    /// How long before a deadline to stop sleeping and start spinning.
+    pub fn spin_window(&self) -> Duration {
+        SPIN_MARGIN + self.sleep_overshoot
+    }
+
+    /// Records how late a sleep that should have ended at `wake` actually ended.
+    pub fn record_sleep(&mut self, wake: Instant) {
+        let overshoot = Instant::now().saturating_duration_since(wake);
+        self.sleep_overshoot = (self.sleep_overshoot * 7 + overshoot) / 8;
+    }
+
+    /// Records that the message at the specified offset was released, returning how late it was.
+    pub fn record_release(&mut self, offset_ns: u64) -> Duration {
+        let late_by = self.lateness(offset_ns);
+        self.total_drift += late_by;
+        self.released = self.released.saturating_add(1);
+        late_by
+    }
+
+    /// Average lateness of released messages.
+    pub fn mean_drift(&self) -> Duration {
+        self.total_drift / self.released.max(1)
+    }
+
     /// Sleeps until the specified offset.
     pub fn sleep_until(&mut self, offset_ns: u64) {
-        let abs = Duration::from_nanos(offset_ns.saturating_sub(self.offset_ns));
-        let delta = abs.saturating_sub(self.start.elapsed());
-        if delta >= Duration::from_micros(1) {
-            std::thread::sleep(delta);
+        let delay = self.delay_until(offset_ns);
+        let window = self.spin_window();
+        if delay > window {
+            let wake = Instant::now() + (delay - window);
+            std::thread::sleep(delay - window);
+            self.record_sleep(wake);
         }
+        while self.delay_until(offset_ns) >= Duration::from_micros(1) {
+            std::hint::spin_loop();
+        }
+        self.record_release(offset_ns);
         self.now_ns = offset_ns;
     }
 
@@ -247,3 +1133,36 @@ impl TimeTracker {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::chunk_index as chunk;
+
+    #[test]
+    fn seek_offset_without_index() {
+        assert_eq!(seek_offset(&[], 10), None);
+    }
+
+    #[test]
+    fn seek_offset_starts_at_first_chunk_reaching_log_time() {
+        let chunks = [chunk(8, 0, 10), chunk(108, 10, 20), chunk(208, 20, 30)];
+        assert_eq!(seek_offset(&chunks, 0), Some(8));
+        assert_eq!(seek_offset(&chunks, 10), Some(8));
+        assert_eq!(seek_offset(&chunks, 15), Some(108));
+        assert_eq!(seek_offset(&chunks, 30), Some(208));
+    }
+
+    #[test]
+    fn seek_offset_with_overlapping_chunks() {
+        // The second chunk in the file overlaps the third in time.
+        let chunks = [chunk(8, 0, 10), chunk(108, 10, 40), chunk(208, 20, 30)];
+        assert_eq!(seek_offset(&chunks, 25), Some(108));
+    }
+
+    #[test]
+    fn seek_offset_past_the_end() {
+        let chunks = [chunk(8, 0, 10), chunk(108, 10, 20)];
+        assert_eq!(seek_offset(&chunks, 21), Some(208));
+    }
+}
@@ -0,0 +1,220 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use foxglove::schemas::log::Level;
+use foxglove::{Channel, ChannelBuilder, Schema};
+use parking_lot::Mutex;
+use schemars::{schema_for, JsonSchema};
+use serde::Serialize;
+use tracing::warn;
+
+use crate::client_tracker::ClientTracker;
+use crate::diagnostics;
+
+/// Topic the periodic statistics are published on.
+pub const STATS_TOPIC: &str = "/sdk-stats";
+
+/// Throughput and timing counters for a replay session.
+///
+/// The replay loop records every published message and how late it was relative to its
+/// scheduled wallclock time. [`Metrics::publish`] is called once per second to emit a
+/// [`StatsSnapshot`] on [`STATS_TOPIC`], report new drops on `/sdk-diagnostics`, and start a new
+/// measurement window.
+pub struct Metrics {
+    state: Mutex<State>,
+    clients: Option<Arc<ClientTracker>>,
+    stats_channel: Option<Arc<Channel>>,
+}
+
+struct State {
+    window_start: Instant,
+    topics: BTreeMap<String, TopicCounters>,
+    drift: Duration,
+    max_drift: Duration,
+}
+
+impl State {
+    fn topic(&mut self, topic: &str) -> &mut TopicCounters {
+        if !self.topics.contains_key(topic) {
+            self.topics.insert(topic.to_string(), TopicCounters::default());
+        }
+        self.topics.get_mut(topic).expect("inserted above")
+    }
+}
+
+#[derive(Default)]
+struct TopicCounters {
+    total_messages: u64,
+    total_bytes: u64,
+    window_messages: u64,
+    window_bytes: u64,
+    dropped_messages: u64,
+    /// Drops already reported on `/sdk-diagnostics`.
+    reported_drops: u64,
+}
+
+/// Statistics published on [`STATS_TOPIC`].
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct StatsSnapshot {
+    /// Length of the measurement window in seconds.
+    pub window_secs: f64,
+    /// How far the most recent message lagged behind its scheduled wallclock time.
+    pub replay_drift_ms: f64,
+    /// Largest lag seen during the window.
+    pub max_replay_drift_ms: f64,
+    /// Number of clients with at least one subscription.
+    pub subscribed_clients: usize,
+    pub topics: Vec<TopicStats>,
+}
+
+/// Per-topic throughput within a [`StatsSnapshot`].
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct TopicStats {
+    pub topic: String,
+    pub messages_per_sec: f64,
+    pub bytes_per_sec: f64,
+    pub total_messages: u64,
+    pub total_bytes: u64,
+    /// Messages dropped by throttling since the start of the session.
+    pub dropped_messages: u64,
+}
+
+impl Metrics {
+    /// Creates the metrics and advertises the stats channel.
+    pub fn new(clients: Option<Arc<ClientTracker>>) -> Arc<Self> {
+        let schema = serde_json::to_vec(&schema_for!(StatsSnapshot)).unwrap_or_default();
+        let stats_channel = ChannelBuilder::new(STATS_TOPIC)
+            .message_encoding("json")
+            .schema(Schema::new("sdk.Stats", "jsonschema", schema))
+            .build()
+            .inspect_err(|e| warn!("Failed to create stats channel: {e}"))
+            .ok();
+        Arc::new(Self {
+            state: Mutex::new(State {
+                window_start: Instant::now(),
+                topics: BTreeMap::new(),
+                drift: Duration::ZERO,
+                max_drift: Duration::ZERO,
+            }),
+            clients,
+            stats_channel,
+        })
+    }
+
+    /// Records a published message.
+    pub fn record_message(&self, topic: &str, bytes: usize) {
+        let mut state = self.state.lock();
+        let counters = state.topic(topic);
+        counters.total_messages += 1;
+        counters.total_bytes += bytes as u64;
+        counters.window_messages += 1;
+        counters.window_bytes += bytes as u64;
+    }
+
+    /// Records a message that was dropped instead of published.
+    pub fn record_drop(&self, topic: &str) {
+        self.state.lock().topic(topic).dropped_messages += 1;
+    }
+
+    /// Records how late a message was published relative to its scheduled time.
+    pub fn record_drift(&self, late_by: Duration) {
+        let mut state = self.state.lock();
+        state.drift = late_by;
+        state.max_drift = state.max_drift.max(late_by);
+    }
+
+    /// Summarizes the current window and starts a new one.
+    pub fn snapshot(&self) -> StatsSnapshot {
+        let mut state = self.state.lock();
+        let window = state.window_start.elapsed();
+        let secs = window.as_secs_f64().max(f64::EPSILON);
+        let topics = state
+            .topics
+            .iter_mut()
+            .map(|(topic, counters)| {
+                let stats = TopicStats {
+                    topic: topic.clone(),
+                    messages_per_sec: counters.window_messages as f64 / secs,
+                    bytes_per_sec: counters.window_bytes as f64 / secs,
+                    total_messages: counters.total_messages,
+                    total_bytes: counters.total_bytes,
+                    dropped_messages: counters.dropped_messages,
+                };
+                counters.window_messages = 0;
+                counters.window_bytes = 0;
+                stats
+            })
+            .collect();
+        let snapshot = StatsSnapshot {
+            window_secs: window.as_secs_f64(),
+            replay_drift_ms: state.drift.as_secs_f64() * 1e3,
+            max_replay_drift_ms: state.max_drift.as_secs_f64() * 1e3,
+            subscribed_clients: self.subscribed_clients(),
+            topics,
+        };
+        state.window_start = Instant::now();
+        state.max_drift = Duration::ZERO;
+        snapshot
+    }
+
+    /// Publishes a snapshot on the stats channel and starts a new window.
+    pub fn publish(&self) {
+        let snapshot = self.snapshot();
+        if let Some(channel) = &self.stats_channel {
+            match serde_json::to_vec(&snapshot) {
+                Ok(data) => channel.log(&data),
+                Err(e) => warn!("Failed to serialize stats: {e}"),
+            }
+        }
+        self.report_drops();
+    }
+
+    /// Publishes the number of messages dropped on each topic since the last report on
+    /// `/sdk-diagnostics`.
+    fn report_drops(&self) {
+        let mut state = self.state.lock();
+        for (topic, counters) in &mut state.topics {
+            let dropped = counters.dropped_messages - counters.reported_drops;
+            if dropped > 0 {
+                counters.reported_drops = counters.dropped_messages;
+                diagnostics::publish(
+                    Level::Warning,
+                    "throttle",
+                    format!("Dropped {dropped} messages on {topic} (throttled)"),
+                );
+            }
+        }
+    }
+
+    /// Renders the cumulative counters in the Prometheus text exposition format.
+    pub fn prometheus(&self) -> String {
+        let state = self.state.lock();
+        let mut out = String::new();
+        let _ = writeln!(out, "# TYPE sdk_messages_total counter");
+        for (topic, counters) in &state.topics {
+            let _ = writeln!(out, "sdk_messages_total{{topic={topic:?}}} {}", counters.total_messages);
+        }
+        let _ = writeln!(out, "# TYPE sdk_bytes_total counter");
+        for (topic, counters) in &state.topics {
+            let _ = writeln!(out, "sdk_bytes_total{{topic={topic:?}}} {}", counters.total_bytes);
+        }
+        let _ = writeln!(out, "# TYPE sdk_dropped_messages_total counter");
+        for (topic, counters) in &state.topics {
+            let _ = writeln!(out, "sdk_dropped_messages_total{{topic={topic:?}}} {}", counters.dropped_messages);
+        }
+        let _ = writeln!(out, "# TYPE sdk_replay_drift_seconds gauge");
+        let _ = writeln!(out, "sdk_replay_drift_seconds {}", state.drift.as_secs_f64());
+        let _ = writeln!(out, "# TYPE sdk_subscribed_clients gauge");
+        let _ = writeln!(out, "sdk_subscribed_clients {}", self.subscribed_clients());
+        out
+    }
+
+    fn subscribed_clients(&self) -> usize {
+        self.clients
+            .as_ref()
+            .map(|c| c.subscribed_client_count())
+            .unwrap_or_default()
+    }
+}
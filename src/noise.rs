@@ -0,0 +1,173 @@
+use std::time::Instant;
+
+use serde::Deserialize;
+
+/// Kind of disturbance added to the published camera transform.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NoiseKind {
+    #[default]
+    None,
+    /// Smooth, wandering motion, like a handheld camera.
+    Perlin,
+    /// Band-limited random jitter, like vehicle vibration.
+    Gaussian,
+}
+
+/// Camera noise settings (config file `[noise]` section).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct NoiseSettings {
+    pub kind: NoiseKind,
+    /// Typical position offset in meters.
+    pub position_amplitude: f64,
+    /// Typical roll/pitch/yaw offset in radians.
+    pub orientation_amplitude: f64,
+    /// How quickly the noise changes, in Hz.
+    pub frequency: f64,
+    /// Seed, so disturbances can be reproduced between runs.
+    pub seed: u64,
+}
+
+impl Default for NoiseSettings {
+    fn default() -> Self {
+        Self {
+            kind: NoiseKind::None,
+            position_amplitude: 0.01,
+            orientation_amplitude: 0.005,
+            frequency: 8.0,
+            seed: 0,
+        }
+    }
+}
+
+/// Number of noise channels: x, y, z, roll, pitch, yaw.
+const CHANNELS: usize = 6;
+
+/// Perlin octaves summed for the handheld motion.
+const OCTAVES: u32 = 3;
+
+/// Time-varying disturbance applied to the camera transform when it is published.
+///
+/// Noise is a pure function of the time since creation, so sampling doesn't need mutable access
+/// and the same seed produces the same disturbance.
+#[derive(Debug, Clone)]
+pub struct CameraNoise {
+    settings: NoiseSettings,
+    start: Instant,
+}
+
+impl CameraNoise {
+    /// Creates the noise source, or `None` if the settings disable noise.
+    pub fn new(settings: NoiseSettings) -> Option<Self> {
+        (settings.kind != NoiseKind::None).then(|| Self {
+            settings,
+            start: Instant::now(),
+        })
+    }
+
+    /// Returns the position offset and the (roll, pitch, yaw) offset for the current time.
+    pub fn sample(&self) -> ([f64; 3], [f64; 3]) {
+        let t = self.start.elapsed().as_secs_f64() * self.settings.frequency;
+        let mut values = [0.0; CHANNELS];
+        for (channel, value) in values.iter_mut().enumerate() {
+            let seed = self.settings.seed ^ (channel as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+            *value = match self.settings.kind {
+                NoiseKind::None => 0.0,
+                NoiseKind::Perlin => fractal_perlin(seed, t),
+                NoiseKind::Gaussian => smooth_gaussian(seed, t),
+            };
+        }
+        let pos = self.settings.position_amplitude;
+        let rot = self.settings.orientation_amplitude;
+        (
+            [values[0] * pos, values[1] * pos, values[2] * pos],
+            [values[3] * rot, values[4] * rot, values[5] * rot],
+        )
+    }
+
+    /// Returns the translation and rotation quaternion (x, y, z, w) with noise applied.
+    ///
+    /// The orientation noise is applied in the camera's own frame, so it reads as shake rather
+    /// than as a change of heading.
+    pub fn apply(&self, translation: &[f64], rotation: &[f64]) -> (Vec<f64>, Vec<f64>) {
+        let ([dx, dy, dz], [roll, pitch, yaw]) = self.sample();
+        let translation = vec![translation[0] + dx, translation[1] + dy, translation[2] + dz];
+        let rotation = quaternion_multiply(rotation, &quaternion_from_euler(roll, pitch, yaw));
+        (translation, rotation)
+    }
+}
+
+/// Quaternion (x, y, z, w) for rotations about the camera's Z (roll), X (pitch), and Y (yaw) axes.
+fn quaternion_from_euler(roll: f64, pitch: f64, yaw: f64) -> Vec<f64> {
+    let (sr, cr) = (roll / 2.0).sin_cos();
+    let (sp, cp) = (pitch / 2.0).sin_cos();
+    let (sy, cy) = (yaw / 2.0).sin_cos();
+    let yaw = [0.0, sy, 0.0, cy];
+    let pitch = [sp, 0.0, 0.0, cp];
+    let roll = [0.0, 0.0, sr, cr];
+    quaternion_multiply(&quaternion_multiply(&yaw, &pitch), &roll)
+}
+
+/// Hamilton product of two (x, y, z, w) quaternions.
+fn quaternion_multiply(a: &[f64], b: &[f64]) -> Vec<f64> {
+    let (ax, ay, az, aw) = (a[0], a[1], a[2], a[3]);
+    let (bx, by, bz, bw) = (b[0], b[1], b[2], b[3]);
+    vec![
+        aw * bx + ax * bw + ay * bz - az * by,
+        aw * by - ax * bz + ay * bw + az * bx,
+        aw * bz + ax * by - ay * bx + az * bw,
+        aw * bw - ax * bx - ay * by - az * bz,
+    ]
+}
+
+/// SplitMix64 hash, used as a stateless random number generator.
+fn hash(seed: u64, index: i64) -> u64 {
+    let mut z = seed.wrapping_add((index as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Uniform value in (0, 1].
+fn uniform(seed: u64, index: i64) -> f64 {
+    ((hash(seed, index) >> 11) as f64 + 1.0) / (1u64 << 53) as f64
+}
+
+/// 1D gradient noise, roughly in [-1, 1].
+fn perlin(seed: u64, x: f64) -> f64 {
+    let i = x.floor();
+    let t = x - i;
+    let i = i as i64;
+    let gradient = |i: i64| uniform(seed, i) * 2.0 - 1.0;
+    let fade = t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+    let a = gradient(i) * t;
+    let b = gradient(i + 1) * (t - 1.0);
+    2.0 * (a + fade * (b - a))
+}
+
+/// Several octaves of Perlin noise, normalized to roughly [-1, 1].
+fn fractal_perlin(seed: u64, x: f64) -> f64 {
+    let mut sum = 0.0;
+    let mut norm = 0.0;
+    for octave in 0..OCTAVES {
+        let scale = (1 << octave) as f64;
+        sum += perlin(seed.wrapping_add(octave as u64), x * scale) / scale;
+        norm += 1.0 / scale;
+    }
+    sum / norm
+}
+
+/// Standard normal samples at integer times, linearly interpolated between them.
+fn smooth_gaussian(seed: u64, x: f64) -> f64 {
+    // Box-Muller transform from two uniform samples.
+    let normal = |i: i64| {
+        let u1 = uniform(seed, 2 * i);
+        let u2 = uniform(seed, 2 * i + 1);
+        (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+    };
+    let i = x.floor();
+    let t = x - i;
+    let i = i as i64;
+    normal(i) * (1.0 - t) + normal(i + 1) * t
+}
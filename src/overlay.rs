@@ -0,0 +1,151 @@
+use chrono::{TimeZone, Utc};
+use font8x8::{UnicodeFonts, BASIC_FONTS};
+use serde::Deserialize;
+
+use crate::camera_state::CameraPose;
+
+/// Glyph size of the built-in font, in font pixels.
+const GLYPH_SIZE: usize = 8;
+/// Space between the HUD and the image edges, and around the text, in image pixels.
+const MARGIN: usize = 4;
+
+/// HUD drawn onto /sdk-image (config file `[overlay_settings]` section)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct OverlaySettings {
+    // extra lines drawn under the HUD, e.g. the scenario under review
+    pub text: Vec<String>,
+    // size of a font pixel in image pixels
+    pub scale: u32,
+    // RGBA color of the text
+    pub color: [u8; 4],
+    // RGBA color of the box behind the text; the rest of the image stays transparent
+    pub background: [u8; 4],
+}
+
+impl Default for OverlaySettings {
+    fn default() -> Self {
+        Self {
+            text: Vec::new(),
+            scale: 2,
+            color: [255, 255, 255, 255],
+            background: [0, 0, 0, 160],
+        }
+    }
+}
+
+/// Playback state shown on the HUD, sampled once per camera tick.
+#[derive(Debug, Clone, Default)]
+pub struct PlaybackStatus {
+    /// Session clock time the frame is stamped with.
+    pub now_ns: u64,
+    /// Seconds of file time since the start of the current pass.
+    pub replay_secs: f64,
+    pub rate: f64,
+    pub paused: bool,
+}
+
+/// A heads-up display burned into the synthetic image, so frames of a recording are
+/// self-describing: the timestamp, replay time and rate, camera position and heading, a frame
+/// counter, and any custom text.
+#[derive(Debug, Clone)]
+pub struct Overlay {
+    settings: OverlaySettings,
+    lines: Vec<String>,
+    frames: u64,
+}
+
+impl Overlay {
+    pub fn new(settings: OverlaySettings) -> Self {
+        Self {
+            settings,
+            lines: Vec::new(),
+            frames: 0,
+        }
+    }
+
+    /// Prepares the HUD for the next frame.
+    pub fn update(&mut self, status: &PlaybackStatus, pose: &CameraPose, velocity: f64) {
+        self.frames += 1;
+        let time = Utc.timestamp_nanos(status.now_ns as i64);
+        let rate = if status.paused {
+            "paused".to_string()
+        } else {
+            format!("{:.2}x", status.rate)
+        };
+        self.lines = vec![
+            time.format("%Y-%m-%d %H:%M:%S%.3f").to_string(),
+            format!("t {:.3}s  {rate}", status.replay_secs),
+            format!("pos {:.2} {:.2} {:.2}", pose.x, pose.y, pose.z),
+            format!(
+                "hdg {:.1} roll {:.1} v {:.2}",
+                pose.heading.to_degrees(),
+                pose.roll.to_degrees(),
+                velocity
+            ),
+            format!("frame {}", self.frames),
+        ];
+        self.lines.extend(self.settings.text.iter().cloned());
+    }
+
+    /// Draws the HUD into the top-left corner of an RGBA image. Text that doesn't fit is
+    /// clipped.
+    pub fn draw(&self, data: &mut [u8], width: usize, height: usize) {
+        if self.lines.is_empty() {
+            return;
+        }
+        let scale = self.settings.scale.max(1) as usize;
+        let glyph = GLYPH_SIZE * scale;
+        let columns = self.lines.iter().map(|line| line.chars().count()).max().unwrap_or(0);
+        let mut canvas = Canvas {
+            data,
+            width,
+            height,
+        };
+        canvas.fill(
+            MARGIN,
+            MARGIN,
+            columns * glyph + 2 * MARGIN,
+            self.lines.len() * glyph + 2 * MARGIN,
+            self.settings.background,
+        );
+        for (row, line) in self.lines.iter().enumerate() {
+            let y = 2 * MARGIN + row * glyph;
+            for (column, c) in line.chars().enumerate() {
+                let x = 2 * MARGIN + column * glyph;
+                canvas.glyph(x, y, c, scale, self.settings.color);
+            }
+        }
+    }
+}
+
+struct Canvas<'a> {
+    data: &'a mut [u8],
+    width: usize,
+    height: usize,
+}
+
+impl Canvas<'_> {
+    fn fill(&mut self, x: usize, y: usize, w: usize, h: usize, color: [u8; 4]) {
+        for py in y..(y + h).min(self.height) {
+            for px in x..(x + w).min(self.width) {
+                let i = (py * self.width + px) * 4;
+                self.data[i..i + 4].copy_from_slice(&color);
+            }
+        }
+    }
+
+    fn glyph(&mut self, x: usize, y: usize, c: char, scale: usize, color: [u8; 4]) {
+        let Some(bitmap) = BASIC_FONTS.get(c).or_else(|| BASIC_FONTS.get('?')) else {
+            return;
+        };
+        for (row, bits) in bitmap.iter().enumerate() {
+            for column in 0..GLYPH_SIZE {
+                // bit 0 is the leftmost pixel
+                if bits & (1 << column) != 0 {
+                    self.fill(x + column * scale, y + row * scale, scale, scale, color);
+                }
+            }
+        }
+    }
+}
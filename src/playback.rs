@@ -0,0 +1,83 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use parking_lot::Mutex;
+
+use crate::camera_state::CameraPose;
+
+/// Playback and camera requests shared between the replay loop and remote controllers.
+///
+/// Controllers (HTTP, services, the TUI) only record requests here; the replay loop and the
+/// camera tick pick them up on their next iteration.
+pub struct PlaybackControl {
+    paused: AtomicBool,
+    rate: Mutex<f64>,
+    seek: Mutex<Option<u64>>,
+    pose_request: Mutex<Option<CameraPose>>,
+    pose: Mutex<CameraPose>,
+}
+
+impl Default for PlaybackControl {
+    fn default() -> Self {
+        Self {
+            paused: AtomicBool::new(false),
+            rate: Mutex::new(1.0),
+            seek: Mutex::new(None),
+            pose_request: Mutex::new(None),
+            pose: Mutex::new(CameraPose::default()),
+        }
+    }
+}
+
+impl PlaybackControl {
+    /// Returns true if playback is paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    /// Pauses or resumes playback.
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
+    }
+
+    /// Returns the playback rate, where 1.0 is realtime.
+    pub fn rate(&self) -> f64 {
+        *self.rate.lock()
+    }
+
+    /// Sets the playback rate. Non-positive rates are ignored.
+    pub fn set_rate(&self, rate: f64) {
+        if rate > 0.0 && rate.is_finite() {
+            *self.rate.lock() = rate;
+        }
+    }
+
+    /// Requests a seek to the given log time.
+    pub fn seek(&self, log_time: u64) {
+        *self.seek.lock() = Some(log_time);
+    }
+
+    /// Takes the pending seek request, if any.
+    pub fn take_seek(&self) -> Option<u64> {
+        self.seek.lock().take()
+    }
+
+    /// Requests that the camera jump to the given pose.
+    pub fn request_pose(&self, pose: CameraPose) {
+        *self.pose_request.lock() = Some(pose);
+    }
+
+    /// Takes the pending camera pose request, if any.
+    pub fn take_pose_request(&self) -> Option<CameraPose> {
+        self.pose_request.lock().take()
+    }
+
+    /// Records the camera's current pose for controllers to read.
+    pub fn report_pose(&self, pose: CameraPose) {
+        *self.pose.lock() = pose;
+    }
+
+    /// Returns the most recently reported camera pose.
+    pub fn pose(&self) -> CameraPose {
+        self.pose.lock().clone()
+    }
+}
@@ -0,0 +1,156 @@
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use bytes::Bytes;
+use foxglove::websocket::service::{Service, ServiceSchema};
+use foxglove::websocket::{Client, ClientChannel};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::listeners::Listener;
+use crate::playback::PlaybackControl;
+use crate::replay_session::ReplayClock;
+
+/// Client-publish topic, and service name, for remote playback commands.
+pub const PLAYBACK_CONTROL_TOPIC: &str = "/sdk-playback-control";
+
+/// A playback command, e.g. `{"seek_to": 12.5, "rate": 2.0, "pause": false}`.
+///
+/// Every field is optional; missing fields leave that part of the playback state unchanged.
+#[derive(Debug, Default, Deserialize)]
+pub struct PlaybackCommand {
+    /// Seconds from the start of the file.
+    pub seek_to: Option<f64>,
+    pub rate: Option<f64>,
+    pub pause: Option<bool>,
+}
+
+/// Playback state returned by the service after applying a command.
+#[derive(Debug, Serialize)]
+pub struct PlaybackState {
+    pub paused: bool,
+    pub rate: f64,
+    pub log_time_ns: u64,
+    pub elapsed_secs: f64,
+}
+
+/// Lets Foxglove panels scrub the replay, either by publishing JSON [`PlaybackCommand`]s on
+/// [`PLAYBACK_CONTROL_TOPIC`] or by calling the service of the same name, which replies with the
+/// resulting [`PlaybackState`].
+///
+/// The remote has to be registered when the websocket server is built, before the replay
+/// session exists, so commands are rejected until [`PlaybackRemote::attach`] is called.
+#[derive(Default)]
+pub struct PlaybackRemote {
+    target: OnceLock<Target>,
+}
+
+struct Target {
+    control: Arc<PlaybackControl>,
+    clock: Arc<ReplayClock>,
+    start_time: Option<u64>,
+}
+
+impl PlaybackRemote {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// Connects the remote to a session's controls. `start_time` is the log time that
+    /// `seek_to` is relative to; when unknown, the first replayed message is used instead.
+    pub fn attach(&self, control: Arc<PlaybackControl>, clock: Arc<ReplayClock>, start_time: Option<u64>) {
+        let _ = self.target.set(Target {
+            control,
+            clock,
+            start_time,
+        });
+    }
+
+    fn target(&self) -> Result<&Target> {
+        self.target.get().ok_or_else(|| anyhow!("replay has not started"))
+    }
+
+    /// Parses and applies a JSON command.
+    pub fn handle_json(&self, payload: &[u8]) -> Result<PlaybackState> {
+        let command: PlaybackCommand =
+            serde_json::from_slice(payload).context("invalid playback command")?;
+        self.apply(&command)?;
+        self.state()
+    }
+
+    /// Applies a command. Nothing is changed if any field is invalid.
+    pub fn apply(&self, command: &PlaybackCommand) -> Result<()> {
+        let target = self.target()?;
+        if let Some(rate) = command.rate {
+            if rate <= 0.0 || !rate.is_finite() {
+                return Err(anyhow!("rate must be positive"));
+            }
+        }
+        let seek_target = command
+            .seek_to
+            .map(|seek_to| {
+                let offset = Duration::try_from_secs_f64(seek_to.max(0.0))
+                    .map_err(|_| anyhow!("seek_to must be a number of seconds"))?;
+                let start = target.start_time.unwrap_or_else(|| target.clock.start_ns());
+                u64::try_from(offset.as_nanos())
+                    .ok()
+                    .and_then(|offset| start.checked_add(offset))
+                    .ok_or_else(|| anyhow!("seek_to is past the end of time"))
+            })
+            .transpose()?;
+
+        if let Some(paused) = command.pause {
+            target.control.set_paused(paused);
+        }
+        if let Some(rate) = command.rate {
+            target.control.set_rate(rate);
+        }
+        if let Some(log_time) = seek_target {
+            target.control.seek(log_time);
+        }
+        Ok(())
+    }
+
+    /// Returns the current playback state.
+    pub fn state(&self) -> Result<PlaybackState> {
+        let target = self.target()?;
+        Ok(PlaybackState {
+            paused: target.control.is_paused(),
+            rate: target.control.rate(),
+            log_time_ns: target.clock.now_ns(),
+            elapsed_secs: target.clock.elapsed_secs(),
+        })
+    }
+
+    /// Builds the service, to be registered with the websocket server alongside the `json`
+    /// encoding.
+    pub fn service(self: Arc<Self>) -> Service {
+        let remote = self;
+        Service::builder(PLAYBACK_CONTROL_TOPIC, ServiceSchema::new("PlaybackControl")).handler_fn(
+            move |request| {
+                remote
+                    .handle_json(request.payload())
+                    .and_then(|state| Ok(Bytes::from(serde_json::to_vec(&state)?)))
+                    .map_err(|e| format!("{e:#}"))
+            },
+        )
+    }
+}
+
+impl Listener for PlaybackRemote {
+    fn on_client_advertise(&self, _client: Client, channel: &ClientChannel) {
+        if channel.topic == PLAYBACK_CONTROL_TOPIC {
+            info!("Client advertised {PLAYBACK_CONTROL_TOPIC}");
+        }
+    }
+
+    fn on_message_data(&self, _client: Client, channel: &ClientChannel, payload: &[u8]) {
+        if channel.topic != PLAYBACK_CONTROL_TOPIC {
+            return;
+        }
+        if let Err(err) = self.handle_json(payload) {
+            warn!("Ignoring {PLAYBACK_CONTROL_TOPIC} message: {err:#}");
+        }
+    }
+}
@@ -0,0 +1,13 @@
+use indicatif::{ProgressBar, ProgressStyle};
+
+/// Returns a progress bar on stderr for reading `len` bytes, with throughput and ETA.
+///
+/// The bar is hidden when stderr isn't a terminal.
+pub fn bytes_bar(len: u64, message: &'static str) -> ProgressBar {
+    let style = ProgressStyle::with_template(
+        "{msg} [{bar:40}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta} left)",
+    )
+    .expect("valid progress template")
+    .progress_chars("=> ");
+    ProgressBar::new(len).with_style(style).with_message(message)
+}
@@ -0,0 +1,266 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context, Result};
+use foxglove::{Channel, FoxgloveError, Metadata, Sink, SinkId};
+use mcap::records::MessageHeader;
+use parking_lot::Mutex;
+use tracing::{info, warn};
+
+use crate::ChannelId;
+
+/// When to start a new segment of a split recording.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SplitPolicy {
+    /// Start a new file once the current one reaches this many bytes.
+    pub max_bytes: Option<u64>,
+    /// Start a new file once the current one has been written for this long.
+    pub max_duration: Option<Duration>,
+}
+
+impl SplitPolicy {
+    pub fn is_enabled(&self) -> bool {
+        self.max_bytes.is_some() || self.max_duration.is_some()
+    }
+}
+
+/// Output MCAP file that is finalized however the program exits.
+///
+/// Closing the writer flushes the open chunk and writes the summary section and footer; a file
+/// without them can only be recovered by a linear scan. The writer is closed by [`close`], or
+/// on drop, which also covers early returns and panics that unwind through `main`.
+///
+/// A split recording writes numbered segments (`prefix-0001.mcap`, `prefix-0002.mcap`, ...),
+/// each a complete file. Every segment registers the schemas and channels it uses, since a new
+/// writer is told about all existing channels and writes each one before its first message.
+///
+/// [`close`]: Recording::close
+pub struct Recording {
+    path: PathBuf,
+    handle: Option<Arc<FileSink>>,
+    split: Option<Split>,
+    /// Files finished so far.
+    finished: Vec<PathBuf>,
+}
+
+struct Split {
+    prefix: String,
+    policy: SplitPolicy,
+    segment: u32,
+    started: Instant,
+}
+
+impl Split {
+    fn path(&self) -> PathBuf {
+        PathBuf::from(format!("{}-{:04}.mcap", self.prefix, self.segment))
+    }
+}
+
+impl Recording {
+    /// Creates the file and starts logging every channel into it.
+    pub fn create(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_owned();
+        Ok(Self {
+            handle: Some(open(&path)?),
+            path,
+            split: None,
+            finished: Vec::new(),
+        })
+    }
+
+    /// Creates the first segment of a recording that is split according to `policy`. Call
+    /// [`Recording::rotate_if_due`] regularly to move on to the next segment.
+    pub fn create_split(prefix: impl Into<String>, policy: SplitPolicy) -> Result<Self> {
+        let split = Split {
+            prefix: prefix.into(),
+            policy,
+            segment: 1,
+            started: Instant::now(),
+        };
+        let path = split.path();
+        Ok(Self {
+            handle: Some(open(&path)?),
+            path,
+            split: Some(split),
+            finished: Vec::new(),
+        })
+    }
+
+    /// Finishes the current segment and starts the next one if it has grown past the split
+    /// size or duration.
+    ///
+    /// Messages logged while rotating are lost, so call this from the thread that publishes.
+    pub fn rotate_if_due(&mut self) -> Result<()> {
+        let Some(split) = &self.split else {
+            return Ok(());
+        };
+        let too_big = split.policy.max_bytes.is_some_and(|max| {
+            std::fs::metadata(&self.path).is_ok_and(|m| m.len() >= max)
+        });
+        let too_long = split
+            .policy
+            .max_duration
+            .is_some_and(|max| split.started.elapsed() >= max);
+        if !too_big && !too_long {
+            return Ok(());
+        }
+
+        self.finish()?;
+        let Some(split) = &mut self.split else {
+            return Ok(());
+        };
+        split.segment += 1;
+        split.started = Instant::now();
+        self.path = split.path();
+        info!("Writing to {}", self.path.display());
+        self.handle = Some(open(&self.path)?);
+        Ok(())
+    }
+
+    /// Writes a metadata record to the current file.
+    pub fn write_metadata(&self, name: &str, metadata: BTreeMap<String, String>) -> Result<()> {
+        let Some(handle) = &self.handle else {
+            return Ok(());
+        };
+        handle
+            .write_metadata(name, metadata)
+            .with_context(|| format!("write metadata to {}", self.path.display()))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Finishes the file, reporting any error. Returns every file written, which for a split
+    /// recording is each of its segments.
+    pub fn close(mut self) -> Result<Vec<PathBuf>> {
+        self.finish()?;
+        Ok(std::mem::take(&mut self.finished))
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        let Some(handle) = self.handle.take() else {
+            return Ok(());
+        };
+        foxglove::Context::get_default().remove_sink(handle.id());
+        handle
+            .finish()
+            .with_context(|| format!("finish {}", self.path.display()))?;
+        info!("Wrote {}", self.path.display());
+        self.finished.push(self.path.clone());
+        Ok(())
+    }
+}
+
+/// Creates the file and registers it with the SDK to receive every logged message.
+fn open(path: &Path) -> Result<Arc<FileSink>> {
+    let file = File::create_new(path).with_context(|| format!("create {}", path.display()))?;
+    let writer = mcap::Writer::new(BufWriter::new(file))
+        .with_context(|| format!("start writing {}", path.display()))?;
+    let sink = Arc::new(FileSink {
+        id: SinkId::next(),
+        state: Mutex::new(Some(SinkState {
+            writer,
+            channels: HashMap::new(),
+        })),
+    });
+    foxglove::Context::get_default().add_sink(sink.clone());
+    Ok(sink)
+}
+
+/// MCAP output that the SDK logs every channel into.
+///
+/// This stands in for the SDK's own MCAP writer, which can't write metadata records; it writes
+/// the schemas, channels, and messages the same way.
+struct FileSink {
+    id: SinkId,
+    /// `None` once the file is finished.
+    state: Mutex<Option<SinkState>>,
+}
+
+struct SinkState {
+    writer: mcap::Writer<BufWriter<File>>,
+    /// MCAP channel id for each SDK channel, added before its first message.
+    channels: HashMap<ChannelId, u16>,
+}
+
+impl FileSink {
+    fn write_metadata(&self, name: &str, metadata: BTreeMap<String, String>) -> Result<()> {
+        let mut state = self.state.lock();
+        let state = state.as_mut().ok_or_else(|| anyhow!("file already finished"))?;
+        state.writer.write_metadata(&mcap::records::Metadata {
+            name: name.to_string(),
+            metadata,
+        })?;
+        Ok(())
+    }
+
+    fn finish(&self) -> Result<()> {
+        let Some(mut state) = self.state.lock().take() else {
+            return Ok(());
+        };
+        state.writer.finish()?;
+        Ok(())
+    }
+}
+
+impl SinkState {
+    fn log(&mut self, channel: &Channel, msg: &[u8], metadata: &Metadata) -> mcap::McapResult<()> {
+        let id = u64::from(channel.id());
+        let channel_id = match self.channels.get(&id) {
+            Some(&channel_id) => channel_id,
+            None => {
+                let schema_id = match channel.schema() {
+                    Some(schema) => {
+                        self.writer
+                            .add_schema(&schema.name, &schema.encoding, &schema.data)?
+                    }
+                    None => 0,
+                };
+                let channel_id = self.writer.add_channel(
+                    schema_id,
+                    channel.topic(),
+                    channel.message_encoding(),
+                    channel.metadata(),
+                )?;
+                self.channels.insert(id, channel_id);
+                channel_id
+            }
+        };
+        self.writer.write_to_known_channel(
+            &MessageHeader {
+                channel_id,
+                sequence: metadata.sequence,
+                log_time: metadata.log_time,
+                publish_time: metadata.publish_time,
+            },
+            msg,
+        )
+    }
+}
+
+impl Sink for FileSink {
+    fn id(&self) -> SinkId {
+        self.id
+    }
+
+    fn log(&self, channel: &Channel, msg: &[u8], metadata: &Metadata) -> Result<(), FoxgloveError> {
+        let mut state = self.state.lock();
+        let state = state.as_mut().ok_or(FoxgloveError::SinkClosed)?;
+        state
+            .log(channel, msg, metadata)
+            .map_err(|e| FoxgloveError::Unspecified(e.into()))
+    }
+}
+
+impl Drop for Recording {
+    fn drop(&mut self) {
+        if let Err(err) = self.finish() {
+            warn!("{err:#}");
+        }
+    }
+}
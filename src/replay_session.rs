@@ -0,0 +1,540 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use foxglove::WebSocketServerBlockingHandle;
+use parking_lot::Mutex;
+use tokio::sync::mpsc;
+use tokio::time::MissedTickBehavior;
+use tracing::{info, warn};
+
+use crate::bad_records::{BadRecords, ErrorPolicy};
+use crate::clock::Clock;
+use crate::compare::{CompareOptions, CompareSource};
+use crate::decode_pool::DecodePoolConfig;
+use crate::chase::ChaseTarget;
+use crate::client_tracker::ClientTracker;
+use crate::json_export::JsonExport;
+use crate::mcap_replay::{
+    pace_messages, IoMode, LatePolicy, MutedChannels, PacedMessage, Summary, SummaryOptions,
+    TopicEcho, DEFAULT_LATE_THRESHOLD,
+};
+use crate::metrics::Metrics;
+use crate::playback::PlaybackControl;
+use crate::schema_override::SchemaOverride;
+use crate::source::{self, MessageSource};
+use crate::throttle::ThrottleConfig;
+
+/// Number of messages buffered between each stage of the replay pipeline.
+const CHANNEL_CAPACITY: usize = 1024;
+
+/// How often statistics are published when metrics are enabled.
+const STATS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Playback position of a running session, readable from other threads.
+#[derive(Default)]
+pub struct ReplayClock {
+    start_ns: AtomicU64,
+    now_ns: AtomicU64,
+}
+
+impl ReplayClock {
+    /// Records that a message with the given log time was published.
+    pub fn advance(&self, log_time: u64) {
+        let _ = self
+            .start_ns
+            .compare_exchange(0, log_time, Ordering::Relaxed, Ordering::Relaxed);
+        self.now_ns.store(log_time, Ordering::Relaxed);
+    }
+
+    /// Forgets the start time, e.g. when the file loops.
+    pub fn reset(&self) {
+        self.start_ns.store(0, Ordering::Relaxed);
+        self.now_ns.store(0, Ordering::Relaxed);
+    }
+
+    /// Log time of the first message in the current pass, or 0 before playback starts.
+    pub fn start_ns(&self) -> u64 {
+        self.start_ns.load(Ordering::Relaxed)
+    }
+
+    /// Log time of the most recently published message, or 0 before playback starts.
+    pub fn now_ns(&self) -> u64 {
+        self.now_ns.load(Ordering::Relaxed)
+    }
+
+    /// Seconds of file time played since the start of the current pass.
+    pub fn elapsed_secs(&self) -> f64 {
+        let start = self.start_ns.load(Ordering::Relaxed);
+        Duration::from_nanos(self.now_ns().saturating_sub(start)).as_secs_f64()
+    }
+}
+
+/// Replays an MCAP file to a websocket server while periodically ticking a camera callback.
+pub struct ReplaySession {
+    summary: Summary,
+    looping: bool,
+    camera_interval: Duration,
+    done: Arc<AtomicBool>,
+    replay_clock: Arc<ReplayClock>,
+    timestamp_clock: Arc<Clock>,
+    control: Arc<PlaybackControl>,
+    muted: MutedChannels,
+    echo: Arc<TopicEcho>,
+    chase: Option<Arc<ChaseTarget>>,
+    frames: Option<Arc<ChaseTarget>>,
+    subscriptions: Option<Arc<ClientTracker>>,
+    metrics: Option<Arc<Metrics>>,
+    json_export: Option<Arc<Mutex<JsonExport>>>,
+    source: Arc<dyn MessageSource>,
+    throttle: ThrottleConfig,
+    late_policy: LatePolicy,
+    late_threshold: Duration,
+    bad_records: BadRecords,
+}
+
+/// Builder for a [`ReplaySession`].
+pub struct ReplaySessionBuilder {
+    path: PathBuf,
+    looping: bool,
+    camera_interval: Duration,
+    done: Option<Arc<AtomicBool>>,
+    timestamp_clock: Option<Arc<Clock>>,
+    summary_options: SummaryOptions,
+    metrics: Option<Arc<Metrics>>,
+    json_exports: Vec<(String, PathBuf)>,
+    chase: Option<String>,
+    track_frames: bool,
+    subscriptions: Option<Arc<ClientTracker>>,
+    io: IoMode,
+    decode_pool: DecodePoolConfig,
+    source: Option<Arc<dyn MessageSource>>,
+    compare: Option<(PathBuf, CompareOptions)>,
+    throttle: ThrottleConfig,
+    late_policy: LatePolicy,
+    late_threshold: Duration,
+    error_policy: ErrorPolicy,
+}
+
+impl ReplaySessionBuilder {
+    /// Whether to restart the file when it finishes.
+    pub fn looping(mut self, looping: bool) -> Self {
+        self.looping = looping;
+        self
+    }
+
+    /// How often the camera callback is invoked while streaming.
+    pub fn camera_interval(mut self, interval: Duration) -> Self {
+        self.camera_interval = interval;
+        self
+    }
+
+    /// Shares a flag that stops the session when set.
+    pub fn done_flag(mut self, done: Arc<AtomicBool>) -> Self {
+        self.done = Some(done);
+        self
+    }
+
+    /// Shares the clock that stamps time broadcasts, so the camera topics can use the same one.
+    pub fn timestamp_clock(mut self, clock: Arc<Clock>) -> Self {
+        self.timestamp_clock = Some(clock);
+        self
+    }
+
+    /// Restricts the replay to the given topics.
+    pub fn topics(mut self, topics: Vec<String>) -> Self {
+        self.summary_options.topics = Some(topics.into_iter().collect());
+        self
+    }
+
+    /// Replaces (or supplies) the schema of `topic`.
+    pub fn schema_override(mut self, topic: impl Into<String>, schema: SchemaOverride) -> Self {
+        self.summary_options
+            .schema_overrides
+            .insert(topic.into(), schema);
+        self
+    }
+
+    /// Collects throughput and drift statistics, publishing them once per second.
+    pub fn metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Appends every message on `topic` to `path` as a line of JSON, as it is replayed. A
+    /// looping session appends each pass.
+    pub fn export_json(mut self, topic: impl Into<String>, path: impl Into<PathBuf>) -> Self {
+        self.json_exports.push((topic.into(), path.into()));
+        self
+    }
+
+    /// Tracks `frame_id` in the replayed `foxglove.FrameTransform(s)` messages, for a chase
+    /// camera to follow.
+    pub fn chase(mut self, frame_id: impl Into<String>) -> Self {
+        self.chase = Some(frame_id.into());
+        self
+    }
+
+    /// Tracks every frame in the replayed `foxglove.FrameTransform(s)` messages, for camera
+    /// scripts to look up.
+    pub fn track_frames(mut self) -> Self {
+        self.track_frames = true;
+        self
+    }
+
+    /// Only logs messages on channels that a client of the server tracked by `clients` is
+    /// subscribed to, saving serialization for topics nobody is viewing. Don't use this while
+    /// recording the replayed channels, since the recording would miss them too.
+    pub fn skip_unsubscribed(mut self, clients: Arc<ClientTracker>) -> Self {
+        self.subscriptions = Some(clients);
+        self
+    }
+
+    /// Shows a progress bar on stderr if the file has no summary section and has to be scanned
+    /// while building the session.
+    pub fn show_progress(mut self, show: bool) -> Self {
+        self.summary_options.show_progress = show;
+        self
+    }
+
+    /// Fails to build (or, for streamed input, stops) on channels whose encodings Foxglove
+    /// can't decode, instead of advertising them with a warning.
+    pub fn strict_encodings(mut self, strict: bool) -> Self {
+        self.summary_options.strict_encodings = strict;
+        self
+    }
+
+    /// Selects how the file is read.
+    pub fn io(mut self, io: IoMode) -> Self {
+        self.io = io;
+        self
+    }
+
+    /// Sizes the chunk decompression pool used with [`IoMode::Parallel`].
+    pub fn decode_pool(mut self, config: DecodePoolConfig) -> Self {
+        self.decode_pool = config;
+        self
+    }
+
+    /// Replays from a custom source instead of opening the path.
+    pub fn source(mut self, source: Arc<dyn MessageSource>) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    /// Replays a second file alongside the first, with its topics prefixed and its messages
+    /// shifted onto the first file's timeline.
+    pub fn compare(mut self, path: impl Into<PathBuf>, options: CompareOptions) -> Self {
+        self.compare = Some((path.into(), options));
+        self
+    }
+
+    /// Drops messages to stay within bandwidth and per-topic rate limits.
+    pub fn throttle(mut self, throttle: ThrottleConfig) -> Self {
+        self.throttle = throttle;
+        self
+    }
+
+    /// Sets how to handle messages that are more than `threshold` behind the wallclock.
+    pub fn late_policy(mut self, policy: LatePolicy, threshold: Duration) -> Self {
+        self.late_policy = policy;
+        self.late_threshold = threshold;
+        self
+    }
+
+    /// Sets what happens to records of an MCAP file that can't be read.
+    pub fn error_policy(mut self, policy: ErrorPolicy) -> Self {
+        self.error_policy = policy;
+        self
+    }
+
+    /// Loads the file summary and returns the session.
+    pub fn build(self) -> Result<ReplaySession> {
+        let bad_records = BadRecords::new(self.error_policy);
+        let source = match self.source {
+            Some(source) => source,
+            None => source::open(&self.path, self.io, self.decode_pool, bad_records.clone())?,
+        };
+        let source = match self.compare {
+            Some((path, options)) => {
+                let other =
+                    source::open(&path, self.io, self.decode_pool, bad_records.clone())?;
+                Arc::new(CompareSource::new(source, other, options))
+            }
+            None => source,
+        };
+        let json_export = if self.json_exports.is_empty() {
+            None
+        } else {
+            let export = JsonExport::create(&self.json_exports)?;
+            Some(Arc::new(Mutex::new(export)))
+        };
+        info!("Loading summary");
+        let summary = source
+            .load_summary(&self.summary_options)
+            .context("load summary")?;
+        if source.is_streaming() {
+            info!("Input is not seekable, discovering channels while streaming");
+            if self.looping {
+                warn!("Looping is not supported for streamed input");
+            }
+        }
+        let control = Arc::<PlaybackControl>::default();
+        let timestamp_clock =
+            self.timestamp_clock.unwrap_or_else(|| Clock::new(Default::default()));
+        timestamp_clock.attach(control.clone());
+        Ok(ReplaySession {
+            summary,
+            looping: self.looping,
+            camera_interval: self.camera_interval,
+            done: self.done.unwrap_or_default(),
+            replay_clock: Arc::default(),
+            timestamp_clock,
+            control,
+            muted: MutedChannels::default(),
+            echo: Arc::default(),
+            chase: self.chase.map(|frame_id| Arc::new(ChaseTarget::new(frame_id))),
+            frames: self.track_frames.then(|| Arc::new(ChaseTarget::all_frames())),
+            subscriptions: self.subscriptions,
+            metrics: self.metrics,
+            json_export,
+            source,
+            throttle: self.throttle,
+            late_policy: self.late_policy,
+            late_threshold: self.late_threshold,
+            bad_records,
+        })
+    }
+}
+
+impl ReplaySession {
+    /// Starts building a session for the MCAP file at `path`.
+    pub fn builder(path: impl Into<PathBuf>) -> ReplaySessionBuilder {
+        ReplaySessionBuilder {
+            path: path.into(),
+            looping: false,
+            camera_interval: Duration::from_millis(33),
+            done: None,
+            timestamp_clock: None,
+            summary_options: SummaryOptions::default(),
+            metrics: None,
+            json_exports: Vec::new(),
+            chase: None,
+            track_frames: false,
+            subscriptions: None,
+            io: IoMode::default(),
+            decode_pool: DecodePoolConfig::default(),
+            source: None,
+            compare: None,
+            throttle: ThrottleConfig::default(),
+            late_policy: LatePolicy::default(),
+            late_threshold: DEFAULT_LATE_THRESHOLD,
+            error_policy: ErrorPolicy::default(),
+        }
+    }
+
+    /// Returns the path of the file being replayed.
+    pub fn path(&self) -> &Path {
+        self.source.path()
+    }
+
+    /// Returns true if the input is read once, front to back, with channels discovered as it
+    /// is read.
+    pub fn is_streaming(&self) -> bool {
+        self.source.is_streaming()
+    }
+
+    /// Returns the loaded file summary.
+    pub fn summary(&self) -> &Summary {
+        &self.summary
+    }
+
+    /// Returns the flag that stops the session when set.
+    pub fn done_flag(&self) -> Arc<AtomicBool> {
+        self.done.clone()
+    }
+
+    /// Returns the shared playback clock.
+    pub fn clock(&self) -> Arc<ReplayClock> {
+        self.replay_clock.clone()
+    }
+
+    /// Returns the clock that stamps time broadcasts.
+    pub fn timestamp_clock(&self) -> Arc<Clock> {
+        self.timestamp_clock.clone()
+    }
+
+    /// Returns the shared pause/rate/seek controls.
+    pub fn control(&self) -> Arc<PlaybackControl> {
+        self.control.clone()
+    }
+
+    /// Returns the set of channels that are currently not published. Channels can be added and
+    /// removed while the session runs.
+    pub fn muted_channels(&self) -> MutedChannels {
+        self.muted.clone()
+    }
+
+    /// Returns the latest message on the channel selected for echoing.
+    pub fn topic_echo(&self) -> Arc<TopicEcho> {
+        self.echo.clone()
+    }
+
+    /// Returns the count of records skipped because they couldn't be read.
+    pub fn bad_records(&self) -> BadRecords {
+        self.bad_records.clone()
+    }
+
+    /// Returns the chased frame's latest position, if the session was built with a chase frame.
+    pub fn chase_target(&self) -> Option<Arc<ChaseTarget>> {
+        self.chase.clone()
+    }
+
+    /// Returns the latest position of every replayed frame, if the session was built with
+    /// [`ReplaySessionBuilder::track_frames`].
+    pub fn frame_tracker(&self) -> Option<Arc<ChaseTarget>> {
+        self.frames.clone()
+    }
+
+    /// Streams the file until it finishes (or forever, when looping) or the done flag is set.
+    ///
+    /// `on_camera_tick` is called at the configured camera interval while streaming. This drives
+    /// [`ReplaySession::run_async`] on a dedicated tokio runtime, so it must not be called from
+    /// within another runtime.
+    pub fn run<F>(&self, server: &WebSocketServerBlockingHandle, on_camera_tick: F) -> Result<()>
+    where
+        F: FnMut(),
+    {
+        let runtime = tokio::runtime::Runtime::new().context("start replay runtime")?;
+        runtime.block_on(self.run_async(server, on_camera_tick))
+    }
+
+    /// Streams the file using separate tasks for reading, pacing, and publishing.
+    ///
+    /// Reading and pacing run as spawned tasks connected by bounded channels, so disk reads and
+    /// decompression overlap with websocket sends. Publishing and the camera tick run on the
+    /// calling task.
+    pub async fn run_async<F>(
+        &self,
+        server: &WebSocketServerBlockingHandle,
+        mut on_camera_tick: F,
+    ) -> Result<()>
+    where
+        F: FnMut(),
+    {
+        info!("Starting stream");
+        let mut camera_timer = tokio::time::interval(self.camera_interval);
+        camera_timer.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        let mut stats_timer = tokio::time::interval(STATS_INTERVAL);
+        stats_timer.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        let mut skip_until = None;
+        while !self.done.load(Ordering::Relaxed) {
+            let (read_tx, read_rx) = mpsc::channel(CHANNEL_CAPACITY);
+            let (paced_tx, mut paced_rx) = mpsc::channel(CHANNEL_CAPACITY);
+            // Stops this pass only, so a seek can abandon it without ending the session.
+            let stop_pass = Arc::new(AtomicBool::new(false));
+            let reader = tokio::spawn(self.source.read_messages(
+                read_tx,
+                stop_pass.clone(),
+                skip_until.take(),
+            ));
+            let pacer = tokio::spawn(pace_messages(
+                read_rx,
+                paced_tx,
+                self.control.clone(),
+                self.late_policy,
+                self.late_threshold,
+            ));
+
+            let mut file_stream = self
+                .summary
+                .file_stream()
+                .with_muted_channels(self.muted.clone())
+                .with_topic_echo(self.echo.clone());
+            if let Some(metrics) = &self.metrics {
+                file_stream = file_stream.with_metrics(metrics.clone());
+            }
+            if let Some(json_export) = &self.json_export {
+                file_stream = file_stream.with_json_export(json_export.clone());
+            }
+            for chase in self.chase.iter().chain(&self.frames) {
+                file_stream = file_stream.with_chase_target(chase.clone());
+            }
+            if let Some(subscriptions) = &self.subscriptions {
+                file_stream = file_stream.with_subscriptions(subscriptions.clone());
+            }
+            if !self.throttle.is_empty() {
+                file_stream = file_stream.with_throttle(self.throttle.clone());
+            }
+            let mut seek_to = None;
+            while !self.done.load(Ordering::Relaxed) {
+                tokio::select! {
+                    paced = paced_rx.recv() => match paced {
+                        Some(PacedMessage { message, timestamp, late_by }) => {
+                            if let Some(timestamp) = timestamp {
+                                self.timestamp_clock.advance(timestamp);
+                                server.broadcast_time(self.timestamp_clock.now_ns());
+                            }
+                            if let Some(metrics) = &self.metrics {
+                                metrics.record_drift(late_by);
+                            }
+                            if let Some(message) = message {
+                                file_stream.publish(&message.header, &message.data);
+                                self.replay_clock.advance(message.header.log_time);
+                                self.timestamp_clock.advance(message.header.log_time);
+                            }
+                        }
+                        None => break,
+                    },
+                    _ = camera_timer.tick() => on_camera_tick(),
+                    _ = stats_timer.tick(), if self.metrics.is_some() => {
+                        if let Some(metrics) = &self.metrics {
+                            metrics.publish();
+                        }
+                    }
+                }
+                if let Some(target) = self.control.take_seek() {
+                    if self.source.is_streaming() {
+                        warn!("Seeking is not supported for streamed input");
+                        continue;
+                    }
+                    seek_to = Some(target);
+                    break;
+                }
+            }
+
+            // The reader may be skipping ahead without sending anything, so tell it to stop rather
+            // than waiting for it to notice the closed channel. The pacer may be sleeping on a
+            // distant log time, so cancel it; dropping its receiver unblocks the reader too.
+            stop_pass.store(true, Ordering::Relaxed);
+            pacer.abort();
+            reader.await.context("reading task")??;
+
+            if let Some(target) = seek_to {
+                info!("Seeking to {target}");
+                server.clear_session(None);
+                self.timestamp_clock.reset(Some(target));
+                skip_until = Some(target);
+                continue;
+            }
+
+            if !self.looping || self.source.is_streaming() {
+                self.done.store(true, Ordering::Relaxed);
+            } else {
+                info!("Looping");
+                server.clear_session(None);
+                self.replay_clock.reset();
+                self.timestamp_clock.reset(self.summary.start_time());
+            }
+
+            // Sleep to maintain a consistent frame rate
+            tokio::time::sleep(self.camera_interval).await;
+        }
+        if let Some(json_export) = &self.json_export {
+            json_export.lock().flush()?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,178 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use tracing::info;
+use zenoh::pubsub::Publisher;
+use zenoh::{Session, Wait};
+
+use crate::camera_state::CameraState;
+
+/// ROS 2 topic carrying the camera transform as `tf2_msgs/msg/TFMessage`.
+///
+/// ROS 2 names can't contain dashes, so this is the ROS counterpart of `/sdk-tf`.
+pub const TF_TOPIC: &str = "/sdk_tf";
+
+/// ROS 2 topic carrying the camera pose as `geometry_msgs/msg/PoseStamped`.
+pub const POSE_TOPIC: &str = "/sdk_camera_pose";
+
+/// Publishes the virtual camera to ROS 2 over zenoh.
+///
+/// Messages are CDR-encoded and put on the zenoh keys that `zenoh-bridge-ros2dds` (or
+/// `rmw_zenoh`) map to the ROS 2 topics [`TF_TOPIC`] and [`POSE_TOPIC`], so ROS nodes see
+/// them as native topics without this binary linking against a ROS installation.
+pub struct Ros2Bridge {
+    _session: Session,
+    tf: Publisher<'static>,
+    pose: Publisher<'static>,
+}
+
+impl Ros2Bridge {
+    /// Opens a zenoh session with the default configuration (peer mode, multicast scouting),
+    /// or the one in `ZENOH_CONFIG` if set.
+    pub fn open() -> Result<Self> {
+        let config = match std::env::var_os("ZENOH_CONFIG") {
+            Some(path) => zenoh::Config::from_file(path).map_err(|e| anyhow!("zenoh config: {e}"))?,
+            None => zenoh::Config::default(),
+        };
+        let session = zenoh::open(config)
+            .wait()
+            .map_err(|e| anyhow!("open zenoh session: {e}"))?;
+        let declare = |topic: &str| {
+            session
+                .declare_publisher(key_expr(topic))
+                .wait()
+                .map_err(|e| anyhow!("declare {topic}: {e}"))
+        };
+        let tf = declare(TF_TOPIC)?;
+        let pose = declare(POSE_TOPIC)?;
+        info!("Publishing {TF_TOPIC} and {POSE_TOPIC} to ROS 2 over zenoh");
+        Ok(Self {
+            _session: session,
+            tf,
+            pose,
+        })
+    }
+
+    /// Publishes the camera's transform and pose, stamped `now_ns` like `/sdk-tf`.
+    pub fn publish(&self, camera: &CameraState, now_ns: u64) -> Result<()> {
+        let stamp = Duration::from_nanos(now_ns);
+        let (translation, rotation) = camera.published_transform();
+
+        let mut tf = CdrWriter::new();
+        tf.u32(1); // transforms[] length
+        tf.header(stamp.as_secs() as i32, stamp.subsec_nanos(), camera.parent_frame_id());
+        tf.string(camera.frame_id());
+        tf.f64s(&translation[..3]);
+        tf.f64s(&rotation[..4]);
+        self.tf
+            .put(tf.finish())
+            .wait()
+            .map_err(|e| anyhow!("publish {TF_TOPIC}: {e}"))?;
+
+        let mut pose = CdrWriter::new();
+        pose.header(stamp.as_secs() as i32, stamp.subsec_nanos(), camera.parent_frame_id());
+        pose.f64s(&translation[..3]);
+        pose.f64s(&rotation[..4]);
+        self.pose
+            .put(pose.finish())
+            .wait()
+            .map_err(|e| anyhow!("publish {POSE_TOPIC}: {e}"))?;
+        Ok(())
+    }
+}
+
+/// Zenoh key for a ROS 2 topic, following the `zenoh-bridge-ros2dds` convention of dropping the
+/// leading slash.
+fn key_expr(topic: &str) -> String {
+    topic.trim_start_matches('/').to_string()
+}
+
+/// Minimal little-endian CDR serializer for the few message types the bridge publishes.
+struct CdrWriter {
+    buf: Vec<u8>,
+}
+
+/// CDR_LE encapsulation header.
+const ENCAPSULATION: [u8; 4] = [0x00, 0x01, 0x00, 0x00];
+
+impl CdrWriter {
+    fn new() -> Self {
+        Self {
+            buf: ENCAPSULATION.to_vec(),
+        }
+    }
+
+    /// Pads to `n` bytes; alignment is relative to the end of the encapsulation header.
+    fn align(&mut self, n: usize) {
+        let offset = self.buf.len() - ENCAPSULATION.len();
+        self.buf.resize(self.buf.len() + (n - offset % n) % n, 0);
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.align(4);
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn i32(&mut self, value: i32) {
+        self.align(4);
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn f64s(&mut self, values: &[f64]) {
+        for value in values {
+            self.align(8);
+            self.buf.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+
+    /// Strings are length-prefixed, with the length counting a trailing NUL.
+    fn string(&mut self, value: &str) {
+        self.u32(value.len() as u32 + 1);
+        self.buf.extend_from_slice(value.as_bytes());
+        self.buf.push(0);
+    }
+
+    /// `std_msgs/msg/Header`: a `builtin_interfaces/msg/Time` stamp and a frame id.
+    fn header(&mut self, sec: i32, nanosec: u32, frame_id: &str) {
+        self.i32(sec);
+        self.u32(nanosec);
+        self.string(frame_id);
+    }
+
+    fn finish(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cdr_aligns_relative_to_the_encapsulation_header() {
+        let mut cdr = CdrWriter::new();
+        cdr.string("ab");
+        cdr.f64s(&[1.0]);
+        let bytes = cdr.finish();
+        let mut expected = ENCAPSULATION.to_vec();
+        // length including the NUL, the string, then padding to 8 bytes after the header
+        expected.extend_from_slice(&3u32.to_le_bytes());
+        expected.extend_from_slice(b"ab\0");
+        expected.push(0);
+        expected.extend_from_slice(&1.0f64.to_le_bytes());
+        assert_eq!(bytes, expected);
+    }
+
+    #[test]
+    fn cdr_header_layout() {
+        let mut cdr = CdrWriter::new();
+        cdr.header(1, 2, "map");
+        let bytes = cdr.finish();
+        let mut expected = ENCAPSULATION.to_vec();
+        expected.extend_from_slice(&1i32.to_le_bytes());
+        expected.extend_from_slice(&2u32.to_le_bytes());
+        expected.extend_from_slice(&4u32.to_le_bytes());
+        expected.extend_from_slice(b"map\0");
+        assert_eq!(bytes, expected);
+    }
+}
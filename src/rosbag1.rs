@@ -0,0 +1,219 @@
+use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use mcap::records::{MessageHeader, SchemaHeader};
+use rosbag::{ChunkRecord, IndexRecord, MessageRecord, RosBag};
+use tokio::sync::mpsc;
+
+use crate::mcap_replay::{OwnedMessage, Summary, SummaryOptions};
+use crate::source::MessageSource;
+
+/// Returns true if `path` looks like a ROS1 bag.
+pub fn is_rosbag1(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "bag")
+}
+
+fn open_bag(path: &Path) -> Result<RosBag> {
+    RosBag::new(path).with_context(|| format!("open {}", path.display()))
+}
+
+struct Topic {
+    name: String,
+    type_name: String,
+    md5sum: String,
+    message_definition: String,
+}
+
+/// Reads classic ROS1 `.bag` files.
+///
+/// Each topic becomes a channel with `ros1` message encoding and a `ros1msg` schema taken from
+/// the connection header. Bags with several connections on one topic (e.g. multiple publishers)
+/// are merged into a single channel.
+pub struct Rosbag1Source {
+    path: PathBuf,
+    /// Topics in the bag; channel ids are the index plus one.
+    topics: Vec<Topic>,
+    /// Maps bag connection ids to channel ids.
+    connections: HashMap<u32, u16>,
+}
+
+impl Rosbag1Source {
+    /// Opens a bag and reads its connection headers from the index section.
+    pub fn open(path: &Path) -> Result<Self> {
+        let bag = open_bag(path)?;
+        let mut topics: Vec<Topic> = Vec::new();
+        let mut connections = HashMap::new();
+        for record in bag.index_records() {
+            let IndexRecord::Connection(conn) =
+                record.with_context(|| format!("read index of {}", path.display()))?
+            else {
+                continue;
+            };
+            let index = match topics.iter().position(|t| t.name == conn.topic) {
+                Some(index) => index,
+                None => {
+                    topics.push(Topic {
+                        name: conn.topic.to_string(),
+                        type_name: conn.tp.to_string(),
+                        md5sum: conn.md5sum.iter().map(|b| format!("{b:02x}")).collect(),
+                        message_definition: conn.message_definition.to_string(),
+                    });
+                    topics.len() - 1
+                }
+            };
+            if index >= u16::MAX as usize {
+                return Err(anyhow!("too many topics in {}", path.display()));
+            }
+            connections.insert(conn.id, index as u16 + 1);
+        }
+
+        Ok(Self {
+            path: path.to_owned(),
+            topics,
+            connections,
+        })
+    }
+
+    /// Streams the bag's messages in log time order.
+    ///
+    /// Chunks are stored in the order they were written, but their time ranges can overlap, e.g.
+    /// when a recorder writes late messages. Messages are held in a reorder buffer until no later
+    /// chunk can contain an earlier one, going by the chunk start times in the index.
+    fn read_blocking(
+        path: &Path,
+        connections: &HashMap<u32, u16>,
+        tx: mpsc::Sender<OwnedMessage>,
+        done: Arc<AtomicBool>,
+        skip_until: Option<u64>,
+    ) -> Result<()> {
+        let bag = open_bag(path)?;
+        let skip_until = skip_until.unwrap_or_default();
+
+        let mut chunk_starts = Vec::new();
+        for record in bag.index_records() {
+            if let IndexRecord::ChunkInfo(info) = record.context("read index")? {
+                chunk_starts.push((info.chunk_pos, info.start_time));
+            }
+        }
+        chunk_starts.sort_unstable();
+        // Earliest start time of each chunk and those after it, in file order.
+        let mut later_start = vec![u64::MAX; chunk_starts.len() + 1];
+        for (i, &(_, start)) in chunk_starts.iter().enumerate().rev() {
+            later_start[i] = later_start[i + 1].min(start);
+        }
+
+        // Log time, arrival order (to keep ties stable), channel, and data.
+        let mut buffer: BinaryHeap<Reverse<(u64, u64, u16, Bytes)>> = BinaryHeap::new();
+        let mut received = 0u64;
+        let mut sequences: HashMap<u16, u32> = HashMap::new();
+        // Sends buffered messages logged up to `until`; returns false once the receiver is gone.
+        let mut flush = |buffer: &mut BinaryHeap<Reverse<(u64, u64, u16, Bytes)>>, until: u64| {
+            while buffer.peek().is_some_and(|Reverse((time, ..))| *time <= until) {
+                let Some(Reverse((time, _, channel_id, data))) = buffer.pop() else {
+                    break;
+                };
+                let sequence = sequences.entry(channel_id).or_default();
+                *sequence = sequence.wrapping_add(1);
+                let message = OwnedMessage {
+                    header: MessageHeader {
+                        channel_id,
+                        sequence: *sequence,
+                        log_time: time,
+                        publish_time: time,
+                    },
+                    data,
+                };
+                if tx.blocking_send(message).is_err() {
+                    return false;
+                }
+            }
+            true
+        };
+
+        let mut chunk_index = 0;
+        for record in bag.chunk_records() {
+            let ChunkRecord::Chunk(chunk) = record.context("read chunk")? else {
+                continue;
+            };
+            for message in chunk.messages() {
+                if done.load(Ordering::Relaxed) {
+                    return Ok(());
+                }
+                let MessageRecord::MessageData(message) = message.context("read message")? else {
+                    continue;
+                };
+                if message.time < skip_until {
+                    continue;
+                }
+                let Some(&channel_id) = connections.get(&message.conn_id) else {
+                    continue;
+                };
+                received += 1;
+                let data = Bytes::copy_from_slice(message.data);
+                buffer.push(Reverse((message.time, received, channel_id, data)));
+            }
+            chunk_index += 1;
+            // Without a matching index entry, nothing is known about later chunks, so messages
+            // are held until the end.
+            let until = later_start.get(chunk_index).copied().unwrap_or(0);
+            if !flush(&mut buffer, until) {
+                return Ok(());
+            }
+        }
+        flush(&mut buffer, u64::MAX);
+        Ok(())
+    }
+}
+
+impl MessageSource for Rosbag1Source {
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn load_summary(&self, options: &SummaryOptions) -> Result<Summary> {
+        let mut summary = Summary::new(&self.path, options);
+        for (index, topic) in self.topics.iter().enumerate() {
+            let id = index as u16 + 1;
+            let header = SchemaHeader {
+                id,
+                name: topic.type_name.clone(),
+                encoding: "ros1msg".to_string(),
+            };
+            summary.handle_schema(&header, Cow::Borrowed(topic.message_definition.as_bytes()))?;
+            summary.handle_channel(mcap::records::Channel {
+                id,
+                schema_id: id,
+                topic: topic.name.clone(),
+                message_encoding: "ros1".to_string(),
+                metadata: BTreeMap::from([("md5sum".to_string(), topic.md5sum.clone())]),
+            })?;
+        }
+        Ok(summary)
+    }
+
+    fn read_messages(
+        &self,
+        tx: mpsc::Sender<OwnedMessage>,
+        done: Arc<AtomicBool>,
+        skip_until: Option<u64>,
+    ) -> BoxFuture<'static, Result<()>> {
+        let path = self.path.clone();
+        let connections = self.connections.clone();
+        async move {
+            tokio::task::spawn_blocking(move || {
+                Self::read_blocking(&path, &connections, tx, done, skip_until)
+            })
+            .await
+            .context("rosbag reader")?
+        }
+        .boxed()
+    }
+}
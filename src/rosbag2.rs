@@ -0,0 +1,289 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use mcap::records::{MessageHeader, SchemaHeader};
+use rusqlite::{Connection, OpenFlags, OptionalExtension};
+use serde::Deserialize;
+use tokio::sync::mpsc;
+use tracing::warn;
+
+use crate::mcap_replay::{OwnedMessage, Summary, SummaryOptions};
+use crate::source::MessageSource;
+
+/// Returns true if `path` is a rosbag2 sqlite bag: a `.db3` file, or a bag directory containing
+/// `.db3` files.
+pub fn is_rosbag2(path: &Path) -> bool {
+    if path.is_dir() {
+        db3_files(path).is_ok_and(|files| !files.is_empty())
+    } else {
+        path.extension().is_some_and(|ext| ext == "db3")
+    }
+}
+
+/// The part of a bag directory's `metadata.yaml` that lists its files.
+#[derive(Deserialize)]
+struct BagMetadata {
+    rosbag2_bagfile_information: BagFileInformation,
+}
+
+#[derive(Deserialize)]
+struct BagFileInformation {
+    #[serde(default)]
+    relative_file_paths: Vec<PathBuf>,
+}
+
+/// Lists the `.db3` files of a bag directory in order.
+///
+/// The order comes from `metadata.yaml` when the bag has one. Otherwise files are sorted by the
+/// `_<N>` suffix rosbag2 gives split files, since sorting by name would put `bag_10.db3` before
+/// `bag_2.db3`.
+fn db3_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let metadata = dir.join("metadata.yaml");
+    if metadata.exists() {
+        let yaml = std::fs::read_to_string(&metadata)
+            .with_context(|| format!("read {}", metadata.display()))?;
+        let info: BagMetadata = serde_yaml::from_str(&yaml)
+            .with_context(|| format!("parse {}", metadata.display()))?;
+        let files = info
+            .rosbag2_bagfile_information
+            .relative_file_paths
+            .into_iter()
+            .filter(|p| p.extension().is_some_and(|ext| ext == "db3"))
+            .map(|p| dir.join(p))
+            .collect::<Vec<_>>();
+        if !files.is_empty() {
+            return Ok(files);
+        }
+    }
+
+    let mut files = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|ext| ext == "db3"))
+        .collect::<Vec<_>>();
+    files.sort_by_cached_key(|p| (split_index(p), p.clone()));
+    Ok(files)
+}
+
+/// Returns the `N` of a `<name>_<N>.db3` split file, if it has one.
+fn split_index(path: &Path) -> Option<u64> {
+    let stem = path.file_stem()?.to_str()?;
+    stem.rsplit_once('_')?.1.parse().ok()
+}
+
+fn open_db(path: &Path) -> Result<Connection> {
+    Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("open {}", path.display()))
+}
+
+struct Topic {
+    name: String,
+    type_name: String,
+    serialization_format: String,
+}
+
+/// Reads ROS 2 rosbag2 sqlite bags.
+///
+/// Each topic becomes a channel with the bag's serialization format (normally `cdr`) as the
+/// message encoding. Schemas come from the `message_definitions` table written by rosbag2 on
+/// ROS 2 Iron and later; topics in older bags are advertised without a schema.
+pub struct Rosbag2Source {
+    path: PathBuf,
+    files: Vec<PathBuf>,
+    /// Topics across all files; channel ids are the index plus one.
+    topics: Vec<Topic>,
+}
+
+impl Rosbag2Source {
+    /// Opens a `.db3` file or a bag directory of split `.db3` files.
+    pub fn open(path: &Path) -> Result<Self> {
+        let files = if path.is_dir() {
+            db3_files(path)?
+        } else {
+            vec![path.to_owned()]
+        };
+        if files.is_empty() {
+            return Err(anyhow!("no .db3 files in {}", path.display()));
+        }
+
+        let mut topics: Vec<Topic> = Vec::new();
+        for file in &files {
+            let db = open_db(file)?;
+            let mut stmt = db.prepare("SELECT name, type, serialization_format FROM topics")?;
+            let rows = stmt.query_map([], |row| {
+                Ok(Topic {
+                    name: row.get(0)?,
+                    type_name: row.get(1)?,
+                    serialization_format: row.get(2)?,
+                })
+            })?;
+            for topic in rows {
+                let topic = topic?;
+                if !topics.iter().any(|t| t.name == topic.name) {
+                    topics.push(topic);
+                }
+            }
+        }
+        if topics.len() > u16::MAX as usize {
+            return Err(anyhow!("too many topics in {}", path.display()));
+        }
+
+        Ok(Self {
+            path: path.to_owned(),
+            files,
+            topics,
+        })
+    }
+
+    /// Loads message definitions keyed by type name, as (encoding, definition).
+    fn message_definitions(&self) -> Result<HashMap<String, (String, String)>> {
+        let mut definitions = HashMap::new();
+        for file in &self.files {
+            let db = open_db(file)?;
+            let has_table = db
+                .query_row(
+                    "SELECT name FROM sqlite_master WHERE type = 'table' AND name = 'message_definitions'",
+                    [],
+                    |_| Ok(()),
+                )
+                .optional()?
+                .is_some();
+            if !has_table {
+                continue;
+            }
+            let mut stmt = db.prepare(
+                "SELECT topic_type, encoding, encoded_message_definition FROM message_definitions",
+            )?;
+            let rows = stmt.query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?))
+            })?;
+            for row in rows {
+                let (type_name, encoding, definition) = row?;
+                if !definition.is_empty() {
+                    definitions.entry(type_name).or_insert((encoding, definition));
+                }
+            }
+        }
+        Ok(definitions)
+    }
+
+    fn read_blocking(
+        files: &[PathBuf],
+        channel_ids: &HashMap<String, u16>,
+        tx: mpsc::Sender<OwnedMessage>,
+        done: Arc<AtomicBool>,
+        skip_until: Option<u64>,
+    ) -> Result<()> {
+        let mut sequences: HashMap<u16, u32> = HashMap::new();
+        for file in files {
+            let db = open_db(file)?;
+
+            // Topic ids are local to each file of a split bag.
+            let mut local_ids = HashMap::new();
+            let mut stmt = db.prepare("SELECT id, name FROM topics")?;
+            let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?;
+            for row in rows {
+                let (id, name) = row?;
+                if let Some(channel_id) = channel_ids.get(&name) {
+                    local_ids.insert(id, *channel_id);
+                }
+            }
+
+            let mut stmt = db.prepare(
+                "SELECT topic_id, timestamp, data FROM messages WHERE timestamp >= ?1 ORDER BY timestamp",
+            )?;
+            let start = skip_until.unwrap_or_default().min(i64::MAX as u64) as i64;
+            let mut rows = stmt.query([start])?;
+            while let Some(row) = rows.next()? {
+                if done.load(Ordering::Relaxed) {
+                    return Ok(());
+                }
+                let Some(&channel_id) = local_ids.get(&row.get::<_, i64>(0)?) else {
+                    continue;
+                };
+                let log_time = row.get::<_, i64>(1)?.max(0) as u64;
+                let sequence = sequences.entry(channel_id).or_default();
+                *sequence = sequence.wrapping_add(1);
+                let message = OwnedMessage {
+                    header: MessageHeader {
+                        channel_id,
+                        sequence: *sequence,
+                        log_time,
+                        publish_time: log_time,
+                    },
+                    data: Bytes::copy_from_slice(row.get_ref(2)?.as_blob()?),
+                };
+                if tx.blocking_send(message).is_err() {
+                    return Ok(());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl MessageSource for Rosbag2Source {
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn load_summary(&self, options: &SummaryOptions) -> Result<Summary> {
+        let definitions = self.message_definitions()?;
+        let mut summary = Summary::new(&self.path, options);
+        for (index, topic) in self.topics.iter().enumerate() {
+            let id = index as u16 + 1;
+            let schema_id = match definitions.get(&topic.type_name) {
+                Some((encoding, definition)) => {
+                    let header = SchemaHeader {
+                        id,
+                        name: topic.type_name.clone(),
+                        encoding: encoding.clone(),
+                    };
+                    summary.handle_schema(&header, Cow::Borrowed(definition.as_bytes()))?;
+                    id
+                }
+                None => {
+                    warn!("No message definition for {} ({}), advertising without a schema", topic.name, topic.type_name);
+                    0
+                }
+            };
+            summary.handle_channel(mcap::records::Channel {
+                id,
+                schema_id,
+                topic: topic.name.clone(),
+                message_encoding: topic.serialization_format.clone(),
+                metadata: BTreeMap::new(),
+            })?;
+        }
+        Ok(summary)
+    }
+
+    fn read_messages(
+        &self,
+        tx: mpsc::Sender<OwnedMessage>,
+        done: Arc<AtomicBool>,
+        skip_until: Option<u64>,
+    ) -> BoxFuture<'static, Result<()>> {
+        let files = self.files.clone();
+        let channel_ids = self
+            .topics
+            .iter()
+            .enumerate()
+            .map(|(index, t)| (t.name.clone(), index as u16 + 1))
+            .collect::<HashMap<_, _>>();
+        async move {
+            tokio::task::spawn_blocking(move || {
+                Self::read_blocking(&files, &channel_ids, tx, done, skip_until)
+            })
+            .await
+            .context("rosbag2 reader")?
+        }
+        .boxed()
+    }
+}
@@ -0,0 +1,187 @@
+use std::f64::consts::FRAC_1_SQRT_2;
+use std::sync::Arc;
+
+use foxglove::schemas::{
+    line_primitive, ArrowPrimitive, Color, LinePrimitive, Point3, Pose, Quaternion, SceneEntity,
+    SceneUpdate, Vector3,
+};
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use tracing::warn;
+
+use crate::client_tracker::ClientTracker;
+use crate::clock::{Clock, ClockMode};
+
+foxglove::static_typed_channel!(pub GRID, "/sdk-grid", foxglove::schemas::SceneUpdate);
+
+/// Most grid lines drawn in each direction, so a tiny spacing can't exhaust memory.
+const MAX_GRID_LINES: f64 = 10_000.0;
+
+/// Ground grid settings (config file `[grid]` section)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct GridSettings {
+    // total width of the grid in meters
+    pub size: f64,
+    // distance between grid lines in meters
+    pub spacing: f64,
+    // height of the grid in the parent frame (Y up)
+    pub height: f64,
+    // length of the axis arrows at the origin in meters
+    pub axis_length: f64,
+}
+
+impl Default for GridSettings {
+    fn default() -> Self {
+        Self {
+            size: 20.0,
+            spacing: 1.0,
+            height: 0.0,
+            axis_length: 1.0,
+        }
+    }
+}
+
+impl GridSettings {
+    /// Checks that the settings describe a grid that can be drawn.
+    pub fn validate(&self) -> Result<()> {
+        if !(self.spacing > 0.0 && self.spacing.is_finite()) {
+            return Err(anyhow!("grid spacing must be positive, got {}", self.spacing));
+        }
+        if !(self.size >= 0.0 && self.size.is_finite()) {
+            return Err(anyhow!("grid size must not be negative, got {}", self.size));
+        }
+        if self.size / self.spacing > MAX_GRID_LINES {
+            return Err(anyhow!(
+                "grid size {} with spacing {} would draw more than {MAX_GRID_LINES} lines",
+                self.size,
+                self.spacing
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A ground grid and origin axes published on /sdk-grid, to give the camera's motion a reference
+/// when the replayed data has no scene of its own.
+///
+/// The scene never changes, so it is only published at startup and again whenever a client
+/// subscribes, like a latched topic.
+pub struct Grid {
+    frame_id: String,
+    settings: GridSettings,
+    clients: Option<Arc<ClientTracker>>,
+    clock: Arc<Clock>,
+    subscribe_events: u64,
+}
+
+impl Grid {
+    pub fn new(frame_id: &str, settings: GridSettings) -> Self {
+        Self {
+            frame_id: frame_id.to_string(),
+            settings,
+            clients: None,
+            clock: Clock::new(ClockMode::Wallclock),
+            subscribe_events: 0,
+        }
+    }
+
+    /// Republishes the scene for new subscribers.
+    pub fn with_client_tracker(mut self, clients: Arc<ClientTracker>) -> Self {
+        self.clients = Some(clients);
+        self
+    }
+
+    /// Stamps the scene with the session's clock instead of the wallclock.
+    pub fn with_clock(mut self, clock: Arc<Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Publishes the scene if a client subscribed since the last call.
+    pub fn update(&mut self) {
+        let Some(clients) = &self.clients else {
+            return;
+        };
+        let events = clients.subscribe_events(u64::from(GRID.id()));
+        if events != self.subscribe_events {
+            self.subscribe_events = events;
+            self.publish();
+        }
+    }
+
+    /// Publishes the grid and axes.
+    pub fn publish(&self) {
+        let Some(timestamp) = self.clock.timestamp() else {
+            warn!("Timestamp out of range");
+            return;
+        };
+
+        GRID.log(&SceneUpdate {
+            deletions: vec![],
+            entities: vec![SceneEntity {
+                timestamp: Some(timestamp),
+                frame_id: self.frame_id.clone(),
+                id: "grid".to_string(),
+                frame_locked: true,
+                lines: vec![self.grid_lines()],
+                arrows: self.axes(),
+                ..Default::default()
+            }],
+        });
+    }
+
+    fn grid_lines(&self) -> LinePrimitive {
+        let GridSettings { size, spacing, height, .. } = self.settings;
+        let half = size / 2.0;
+        let count = (half / spacing).floor() as i64;
+        let mut points = Vec::new();
+        for i in -count..=count {
+            let offset = i as f64 * spacing;
+            // Lines along Z, then along X, in the Y-up ground plane.
+            points.push(Point3 { x: offset, y: height, z: -half });
+            points.push(Point3 { x: offset, y: height, z: half });
+            points.push(Point3 { x: -half, y: height, z: offset });
+            points.push(Point3 { x: half, y: height, z: offset });
+        }
+        LinePrimitive {
+            r#type: line_primitive::Type::LineList as i32,
+            pose: Some(identity_pose()),
+            thickness: 1.0,
+            scale_invariant: true,
+            points,
+            color: Some(Color { r: 0.6, g: 0.6, b: 0.6, a: 0.5 }),
+            ..Default::default()
+        }
+    }
+
+    fn axes(&self) -> Vec<ArrowPrimitive> {
+        let length = self.settings.axis_length;
+        // Arrows point along +X by default; rotate them onto Y and Z.
+        let axes = [
+            ([0.0, 0.0, 0.0, 1.0], Color { r: 1.0, g: 0.0, b: 0.0, a: 1.0 }),
+            ([0.0, 0.0, FRAC_1_SQRT_2, FRAC_1_SQRT_2], Color { r: 0.0, g: 1.0, b: 0.0, a: 1.0 }),
+            ([0.0, -FRAC_1_SQRT_2, 0.0, FRAC_1_SQRT_2], Color { r: 0.0, g: 0.0, b: 1.0, a: 1.0 }),
+        ];
+        axes.into_iter()
+            .map(|([x, y, z, w], color)| ArrowPrimitive {
+                pose: Some(Pose {
+                    position: Some(Vector3 { x: 0.0, y: 0.0, z: 0.0 }),
+                    orientation: Some(Quaternion { x, y, z, w }),
+                }),
+                shaft_length: length * 0.8,
+                shaft_diameter: length * 0.04,
+                head_length: length * 0.2,
+                head_diameter: length * 0.1,
+                color: Some(color),
+            })
+            .collect()
+    }
+}
+
+fn identity_pose() -> Pose {
+    Pose {
+        position: Some(Vector3 { x: 0.0, y: 0.0, z: 0.0 }),
+        orientation: Some(Quaternion { x: 0.0, y: 0.0, z: 0.0, w: 1.0 }),
+    }
+}
@@ -0,0 +1,110 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use foxglove::Schema;
+
+/// Schema file formats accepted by `--schema-override`, picked by file extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaFormat {
+    /// `.json`: a JSON Schema.
+    JsonSchema,
+    /// `.fds`, `.desc`, `.binpb`: a serialized protobuf `FileDescriptorSet`.
+    Protobuf,
+    /// `.msg`: a ROS message definition; ROS 1 or 2 depending on the channel's message encoding.
+    RosMsg,
+    /// `.idl`: a ROS 2 IDL definition.
+    Ros2Idl,
+}
+
+impl SchemaFormat {
+    /// Infers the format from a file extension.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let ext = path.extension().and_then(|e| e.to_str()).unwrap_or_default();
+        match ext {
+            "json" => Ok(Self::JsonSchema),
+            "fds" | "desc" | "binpb" => Ok(Self::Protobuf),
+            "msg" => Ok(Self::RosMsg),
+            "idl" => Ok(Self::Ros2Idl),
+            "proto" => Err(anyhow!(
+                "{}: protobuf schemas must be a compiled FileDescriptorSet (.fds, .desc, or .binpb), \
+                 e.g. from `protoc --include_imports --descriptor_set_out`",
+                path.display()
+            )),
+            _ => Err(anyhow!(
+                "{}: can't infer the schema encoding from the extension \
+                 (expected .json, .fds, .desc, .binpb, .msg, or .idl)",
+                path.display()
+            )),
+        }
+    }
+
+    /// Returns the MCAP schema encoding for a channel with the given message encoding.
+    pub fn encoding(self, message_encoding: &str) -> &'static str {
+        match self {
+            Self::JsonSchema => "jsonschema",
+            Self::Protobuf => "protobuf",
+            Self::RosMsg if message_encoding == "ros1" => "ros1msg",
+            Self::RosMsg => "ros2msg",
+            Self::Ros2Idl => "ros2idl",
+        }
+    }
+}
+
+/// A schema loaded from a file that replaces (or supplies) the schema of a topic.
+#[derive(Debug, Clone)]
+pub struct SchemaOverride {
+    /// Schema name. Defaults to the name of the schema being replaced, or the file stem.
+    pub name: Option<String>,
+    pub format: SchemaFormat,
+    pub data: Vec<u8>,
+    stem: String,
+}
+
+impl SchemaOverride {
+    /// Loads a schema file, inferring the encoding from its extension.
+    pub fn load(path: &Path) -> Result<Self> {
+        let format = SchemaFormat::from_path(path)?;
+        let data = std::fs::read(path).with_context(|| format!("read {}", path.display()))?;
+        Ok(Self {
+            name: None,
+            format,
+            data,
+            stem: path
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        })
+    }
+
+    /// Sets the schema name, e.g. the fully qualified protobuf message name.
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Builds the schema for a channel, given the name of the schema it had in the file (if any).
+    pub fn schema(&self, replaced_name: Option<&str>, message_encoding: &str) -> Schema {
+        let name = self
+            .name
+            .as_deref()
+            .or(replaced_name)
+            .unwrap_or(&self.stem);
+        Schema::new(name, self.format.encoding(message_encoding), self.data.clone())
+    }
+}
+
+/// Parses a `<topic>=<schema-file>[#<schema-name>]` override and loads the schema file.
+pub fn parse_schema_override(s: &str) -> Result<(String, SchemaOverride)> {
+    let (topic, file) = s
+        .split_once('=')
+        .ok_or_else(|| anyhow!("expected <topic>=<schema-file>, got {s:?}"))?;
+    let (file, name) = match file.rsplit_once('#') {
+        Some((file, name)) => (file, Some(name)),
+        None => (file, None),
+    };
+    let mut schema = SchemaOverride::load(Path::new(file))?;
+    if let Some(name) = name {
+        schema = schema.with_name(name);
+    }
+    Ok((topic.to_string(), schema))
+}
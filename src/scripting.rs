@@ -0,0 +1,199 @@
+use std::cell::RefCell;
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use rhai::{Dynamic, Engine, Map, Scope, AST};
+use tracing::warn;
+
+use crate::camera_state::CameraState;
+use crate::chase::ChaseTarget;
+use crate::control_source::{ControlCommand, ControlContext, ControlSource};
+
+/// Handle to the camera given to scripts. Scripts only see the methods registered below.
+///
+/// Calls are collected into a command, and also applied to a copy of the camera so the getters
+/// reflect them within the same tick.
+#[derive(Clone)]
+struct ScriptCamera(Rc<RefCell<ScriptState>>);
+
+struct ScriptState {
+    camera: CameraState,
+    command: ControlCommand,
+    // whether the script set the position, heading, or roll
+    posed: bool,
+}
+
+impl ScriptCamera {
+    fn drive(&mut self, f: impl FnOnce(&mut ScriptState)) {
+        f(&mut self.0.borrow_mut());
+    }
+}
+
+/// A user script driving the camera through an `on_tick(t, camera)` callback.
+///
+/// `t` is the replay clock in seconds since the start of the file. A script that fails is
+/// disabled for the rest of the session.
+pub struct CameraScript {
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+    failed: bool,
+}
+
+impl CameraScript {
+    /// Compiles the script at `path` and runs its top-level statements once.
+    ///
+    /// `frames` lets the script look up replayed transforms with `frame(id)`.
+    pub fn load(path: &Path, frames: Option<Arc<ChaseTarget>>) -> Result<Self> {
+        let engine = Self::engine(frames);
+        let ast = engine
+            .compile_file(path.into())
+            .map_err(|e| anyhow!("compile {}: {e}", path.display()))?;
+        let mut scope = Scope::new();
+        engine
+            .run_ast_with_scope(&mut scope, &ast)
+            .map_err(|e| anyhow!("run {}: {e}", path.display()))?;
+        Ok(Self {
+            engine,
+            ast,
+            scope,
+            failed: false,
+        })
+    }
+
+    /// Calls the script's `on_tick` with the replay time and the camera, returning what the
+    /// script asked the camera to do.
+    pub fn on_tick(&mut self, t: f64, camera: &CameraState) -> Result<ControlCommand> {
+        let handle = ScriptCamera(Rc::new(RefCell::new(ScriptState {
+            camera: camera.clone(),
+            command: ControlCommand::default(),
+            posed: false,
+        })));
+        self.engine
+            .call_fn::<()>(&mut self.scope, &self.ast, "on_tick", (t, handle.clone()))
+            .map_err(|e| anyhow!("on_tick: {e}"))?;
+        let state = handle.0.borrow();
+        let mut command = state.command.clone();
+        if state.posed {
+            command.pose = Some(state.camera.pose());
+        }
+        Ok(command)
+    }
+
+    fn engine(frames: Option<Arc<ChaseTarget>>) -> Engine {
+        let mut engine = Engine::new();
+        // Scripts run on the camera tick, so a runaway loop or allocation must fail the script
+        // rather than stall or exhaust the replay.
+        engine.set_max_operations(1_000_000);
+        engine.set_max_call_levels(64);
+        engine.set_max_string_size(64 * 1024);
+        engine.set_max_array_size(10_000);
+        engine.set_max_map_size(10_000);
+        engine.register_type_with_name::<ScriptCamera>("Camera");
+
+        engine.register_fn("accelerate", |c: &mut ScriptCamera, f: f64| {
+            c.drive(|s| {
+                s.camera.accelerate(f);
+                s.command.throttle += f;
+            })
+        });
+        engine.register_fn("decelerate", |c: &mut ScriptCamera, f: f64| {
+            c.drive(|s| {
+                s.camera.decelerate(f);
+                s.command.throttle -= f;
+            })
+        });
+        engine.register_fn("steer_left", |c: &mut ScriptCamera, f: f64| {
+            c.drive(|s| {
+                s.camera.steer_left(f);
+                s.command.steer -= f;
+            })
+        });
+        engine.register_fn("steer_right", |c: &mut ScriptCamera, f: f64| {
+            c.drive(|s| {
+                s.camera.steer_right(f);
+                s.command.steer += f;
+            })
+        });
+        engine.register_fn("roll_counterclockwise", |c: &mut ScriptCamera, f: f64| {
+            c.drive(|s| {
+                s.camera.roll_counterclockwise(f);
+                s.command.roll -= f;
+            })
+        });
+        engine.register_fn("roll_clockwise", |c: &mut ScriptCamera, f: f64| {
+            c.drive(|s| {
+                s.camera.roll_clockwise(f);
+                s.command.roll += f;
+            })
+        });
+        engine.register_fn("stop", |c: &mut ScriptCamera| {
+            c.drive(|s| {
+                s.camera.stop();
+                s.command.stop = true;
+            })
+        });
+        engine.register_fn("set_position", |c: &mut ScriptCamera, x: f64, y: f64, z: f64| {
+            c.drive(|s| {
+                s.camera.set_translation(x, y, z);
+                s.posed = true;
+            })
+        });
+        engine.register_fn("set_heading", |c: &mut ScriptCamera, h: f64| {
+            c.drive(|s| {
+                s.camera.set_heading(h);
+                s.posed = true;
+            })
+        });
+        engine.register_fn("set_roll", |c: &mut ScriptCamera, r: f64| {
+            c.drive(|s| {
+                s.camera.set_roll(r);
+                s.posed = true;
+            })
+        });
+
+        engine.register_get("x", |c: &mut ScriptCamera| c.0.borrow().camera.get_translation()[0]);
+        engine.register_get("y", |c: &mut ScriptCamera| c.0.borrow().camera.get_translation()[1]);
+        engine.register_get("z", |c: &mut ScriptCamera| c.0.borrow().camera.get_translation()[2]);
+        engine.register_get("heading", |c: &mut ScriptCamera| c.0.borrow().camera.get_heading());
+        engine.register_get("roll", |c: &mut ScriptCamera| c.0.borrow().camera.get_roll());
+        engine.register_get("velocity", |c: &mut ScriptCamera| c.0.borrow().camera.get_velocity());
+
+        // Where a replayed frame was last seen, or `()` if it hasn't been (or isn't tracked).
+        engine.register_fn("frame", move |frame_id: &str| -> Dynamic {
+            let Some(sample) = frames.as_ref().and_then(|f| f.sample(frame_id)) else {
+                return Dynamic::UNIT;
+            };
+            let [x, y, z] = sample.position;
+            let [vx, vy, vz] = sample.velocity;
+            let mut map = Map::new();
+            for (key, value) in [("x", x), ("y", y), ("z", z), ("vx", vx), ("vy", vy), ("vz", vz)] {
+                map.insert(key.into(), value.into());
+            }
+            map.insert("parent".into(), sample.parent_frame_id.into());
+            map.into()
+        });
+
+        engine
+    }
+}
+
+impl ControlSource for CameraScript {
+    fn name(&self) -> &str {
+        "script"
+    }
+
+    fn poll(&mut self, context: &ControlContext) -> Option<ControlCommand> {
+        if self.failed {
+            return None;
+        }
+        self.on_tick(context.replay_secs, context.camera)
+            .inspect_err(|err| {
+                warn!("Camera script failed, disabling it: {err:#}");
+                self.failed = true;
+            })
+            .ok()
+    }
+}
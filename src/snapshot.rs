@@ -0,0 +1,294 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use mcap::records::{ChunkIndex, MessageIndexEntry};
+use mcap::{Channel, Message, Schema};
+use serde::Serialize;
+use serde_json::Value;
+use tracing::{info, warn};
+
+use crate::decode::MessageDecoder;
+use crate::mcap_replay::map_file;
+
+/// When to take a snapshot.
+#[derive(Debug, Clone, Copy)]
+pub enum SnapshotTime {
+    /// Time since the first message in the file.
+    Offset(Duration),
+    /// Absolute log time in nanoseconds.
+    LogTime(u64),
+}
+
+/// What to write a snapshot as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SnapshotFormat {
+    /// An MCAP file with one message per topic, keeping the original channels and log times.
+    Mcap,
+    /// A JSON array with one entry per topic, with messages decoded where possible.
+    Json,
+}
+
+impl SnapshotFormat {
+    /// Picks the format from the output file extension, defaulting to MCAP.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("json") => Self::Json,
+            _ => Self::Mcap,
+        }
+    }
+}
+
+/// One topic's message in a JSON snapshot.
+#[derive(Debug, Serialize)]
+struct SnapshotEntry {
+    topic: String,
+    channel_id: u16,
+    message_encoding: String,
+    schema_name: Option<String>,
+    sequence: u32,
+    log_time: u64,
+    publish_time: u64,
+    /// The decoded message, for encodings that can be decoded.
+    message: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    decode_error: Option<String>,
+}
+
+/// The messages included in a snapshot.
+#[derive(Debug, Default)]
+pub struct SnapshotReport {
+    /// Log time of the snapshot.
+    pub log_time: u64,
+    /// Log time of the message kept for each topic.
+    pub topics: BTreeMap<String, u64>,
+}
+
+impl fmt::Display for SnapshotReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (topic, log_time) in &self.topics {
+            let age = Duration::from_nanos(self.log_time - log_time).as_secs_f64();
+            writeln!(f, "{age:>10.3}s old  {topic}")?;
+        }
+        write!(f, "{} topics at log time {}", self.topics.len(), self.log_time)
+    }
+}
+
+/// Writes the last message at or before `at` on every topic of `input` to `output`.
+///
+/// The chunk and message indexes in the summary section locate the messages, so only the chunks
+/// holding them are decompressed. Files without a summary section are scanned instead.
+pub fn snapshot(
+    input: &Path,
+    output: &Path,
+    at: SnapshotTime,
+    format: SnapshotFormat,
+) -> Result<SnapshotReport> {
+    let mmap = map_file(input)?;
+    let summary = match mcap::Summary::read(&mmap) {
+        Ok(summary) => summary,
+        Err(e) => {
+            warn!("Failed to read the summary section: {e}");
+            None
+        }
+    };
+
+    // Writers may leave out message indexes, and a chunk listing none can't be searched.
+    let indexed = summary.as_ref().is_some_and(|summary| {
+        !summary.chunk_indexes.is_empty()
+            && summary
+                .chunk_indexes
+                .iter()
+                .all(|c| !c.message_index_offsets.is_empty())
+    });
+    let (log_time, mut messages) = match summary {
+        Some(summary) if indexed => {
+            let start = summary.stats.as_ref().map_or_else(
+                || summary.chunk_indexes.iter().map(|c| c.message_start_time).min(),
+                |stats| Some(stats.message_start_time),
+            );
+            let log_time = resolve(at, start.unwrap_or_default());
+            (log_time, from_indexes(&summary, &mmap, log_time)?)
+        }
+        _ => {
+            warn!("{} has no message index, scanning messages", input.display());
+            scan(&mmap, at)?
+        }
+    };
+    messages.sort_by_key(|m| m.log_time);
+
+    match format {
+        SnapshotFormat::Mcap => write_mcap(output, &messages)?,
+        SnapshotFormat::Json => write_json(output, &messages)?,
+    }
+    info!("Wrote {}", output.display());
+    Ok(SnapshotReport {
+        log_time,
+        topics: messages
+            .iter()
+            .map(|m| (m.channel.topic.clone(), m.log_time))
+            .collect(),
+    })
+}
+
+fn resolve(at: SnapshotTime, start: u64) -> u64 {
+    match at {
+        SnapshotTime::Offset(offset) => {
+            start.saturating_add(offset.as_nanos().try_into().unwrap_or(u64::MAX))
+        }
+        SnapshotTime::LogTime(log_time) => log_time,
+    }
+}
+
+/// Finds each channel's last message at or before `log_time` through the message indexes.
+fn from_indexes(
+    summary: &mcap::Summary,
+    mmap: &[u8],
+    log_time: u64,
+) -> Result<Vec<Message<'static>>> {
+    // Latest chunks first, so earlier chunks can mostly be skipped without reading their
+    // message indexes.
+    let mut chunks: Vec<&ChunkIndex> = summary
+        .chunk_indexes
+        .iter()
+        .filter(|c| c.message_start_time <= log_time)
+        .collect();
+    chunks.sort_by_key(|c| std::cmp::Reverse(c.message_end_time.min(log_time)));
+
+    let mut latest: HashMap<u16, (&ChunkIndex, MessageIndexEntry)> = HashMap::new();
+    for chunk in chunks {
+        let newest_in_chunk = chunk.message_end_time.min(log_time);
+        let superseded = chunk.message_index_offsets.keys().all(|id| {
+            latest
+                .get(id)
+                .is_some_and(|(_, entry)| entry.log_time >= newest_in_chunk)
+        });
+        if superseded {
+            continue;
+        }
+        let indexes = summary
+            .read_message_indexes(mmap, chunk)
+            .context("read message index")?;
+        for (channel, entries) in indexes {
+            let Some(entry) = entries
+                .into_iter()
+                .filter(|e| e.log_time <= log_time)
+                .max_by_key(|e| e.log_time)
+            else {
+                continue;
+            };
+            match latest.get(&channel.id) {
+                Some((_, prev)) if prev.log_time >= entry.log_time => {}
+                _ => {
+                    latest.insert(channel.id, (chunk, entry));
+                }
+            }
+        }
+    }
+
+    latest
+        .values()
+        .map(|(chunk, entry)| {
+            summary
+                .seek_message(mmap, chunk, entry)
+                .map(into_owned)
+                .context("read message")
+        })
+        .collect()
+}
+
+/// Finds each channel's last message at or before `at` by reading every message. Without a
+/// Statistics record, offsets are relative to the first message in the data section.
+fn scan(mmap: &[u8], at: SnapshotTime) -> Result<(u64, Vec<Message<'static>>)> {
+    let mut log_time = None;
+    let mut latest: HashMap<u16, Message<'static>> = HashMap::new();
+    for message in mcap::MessageStream::new(mmap).context("read data")? {
+        let message = message.context("read message")?;
+        let log_time = *log_time.get_or_insert_with(|| resolve(at, message.log_time));
+        if message.log_time > log_time {
+            continue;
+        }
+        let newer = latest
+            .get(&message.channel.id)
+            .is_none_or(|prev| prev.log_time <= message.log_time);
+        if newer {
+            latest.insert(message.channel.id, into_owned(message));
+        }
+    }
+    let log_time = log_time.ok_or_else(|| anyhow!("file has no messages"))?;
+    Ok((log_time, latest.into_values().collect()))
+}
+
+/// Copies a message out of the mapped file, along with its channel and schema.
+fn into_owned(message: Message<'_>) -> Message<'static> {
+    let channel = &message.channel;
+    let channel = Arc::new(Channel {
+        id: channel.id,
+        topic: channel.topic.clone(),
+        schema: channel.schema.as_ref().map(|schema| {
+            Arc::new(Schema {
+                id: schema.id,
+                name: schema.name.clone(),
+                encoding: schema.encoding.clone(),
+                data: schema.data.clone().into_owned().into(),
+            })
+        }),
+        message_encoding: channel.message_encoding.clone(),
+        metadata: channel.metadata.clone(),
+    });
+    Message {
+        channel,
+        sequence: message.sequence,
+        log_time: message.log_time,
+        publish_time: message.publish_time,
+        data: message.data.into_owned().into(),
+    }
+}
+
+fn write_mcap(output: &Path, messages: &[Message<'static>]) -> Result<()> {
+    let out = File::create(output).with_context(|| format!("create {}", output.display()))?;
+    let mut writer = mcap::Writer::new(BufWriter::new(out)).context("start mcap writer")?;
+    for message in messages {
+        writer.write(message).context("write message")?;
+    }
+    writer.finish().context("finish output")?;
+    Ok(())
+}
+
+fn write_json(output: &Path, messages: &[Message<'static>]) -> Result<()> {
+    let entries: Vec<SnapshotEntry> = messages.iter().map(entry).collect();
+    let out = File::create(output).with_context(|| format!("create {}", output.display()))?;
+    let mut writer = BufWriter::new(out);
+    serde_json::to_writer_pretty(&mut writer, &entries).context("write snapshot")?;
+    writer.flush().context("write snapshot")?;
+    Ok(())
+}
+
+fn entry(message: &Message<'static>) -> SnapshotEntry {
+    let channel = &message.channel;
+    let schema = channel.schema.as_ref().map(|schema| {
+        foxglove::Schema::new(&schema.name, &schema.encoding, schema.data.clone().into_owned())
+    });
+    let decoded = MessageDecoder::new(&channel.message_encoding, schema.as_ref())
+        .and_then(|decoder| decoder.decode(&message.data));
+    let (message_json, decode_error) = match decoded {
+        Ok(value) => (Some(value), None),
+        Err(e) => (None, Some(format!("{e:#}"))),
+    };
+    SnapshotEntry {
+        topic: channel.topic.clone(),
+        channel_id: channel.id,
+        message_encoding: channel.message_encoding.clone(),
+        schema_name: channel.schema.as_ref().map(|s| s.name.clone()),
+        sequence: message.sequence,
+        log_time: message.log_time,
+        publish_time: message.publish_time,
+        message: message_json,
+        decode_error,
+    }
+}
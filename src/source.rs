@@ -0,0 +1,283 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use mcap::records::ChunkIndex;
+use parking_lot::Mutex;
+use tokio::sync::mpsc;
+use tracing::{debug, warn};
+
+use crate::bad_records::{BadRecords, CorruptRecord, ErrorPolicy};
+use crate::decode_pool::{read_messages_parallel, DecodePoolConfig};
+use crate::mcap_replay::{
+    read_messages, read_messages_mmap, read_messages_streaming, seek_offset, IoMode, OwnedMessage,
+    Summary, SummaryOptions,
+};
+
+/// A recording that can be replayed: something that knows its channels up front and can stream
+/// its messages in log time order.
+///
+/// The replay, pacing, and camera layers only deal with [`Summary`] and [`OwnedMessage`], so
+/// they don't care which container the data came from.
+pub trait MessageSource: Send + Sync {
+    /// Path of the recording.
+    fn path(&self) -> &Path;
+
+    /// Loads schema and channel information, creating Foxglove channels as configured by
+    /// `options`.
+    fn load_summary(&self, options: &SummaryOptions) -> Result<Summary>;
+
+    /// Sends every message logged at or after `skip_until` to `tx`, in log time order, until
+    /// the recording ends, `done` is set, or the receiver hangs up.
+    fn read_messages(
+        &self,
+        tx: mpsc::Sender<OwnedMessage>,
+        done: Arc<AtomicBool>,
+        skip_until: Option<u64>,
+    ) -> BoxFuture<'static, Result<()>>;
+
+    /// Returns true if the source can only be read once, front to back. Such sources can't be
+    /// looped or seeked, and their channels only appear once reading starts.
+    fn is_streaming(&self) -> bool {
+        false
+    }
+}
+
+/// Returns true if `path` can only be read front to back: standard input (`-`) or a pipe.
+pub fn is_stream(path: &Path) -> bool {
+    path == Path::new("-") || std::fs::metadata(path).is_ok_and(|m| !m.is_file() && !m.is_dir())
+}
+
+/// Opens a recording, picking the source implementation from the path. `decode_pool` sizes the
+/// worker pool of [`IoMode::Parallel`]; `bad_records` decides what happens to corrupt records in
+/// MCAP files.
+pub fn open(
+    path: &Path,
+    io: IoMode,
+    decode_pool: DecodePoolConfig,
+    bad_records: BadRecords,
+) -> Result<Arc<dyn MessageSource>> {
+    #[cfg(feature = "rosbag1")]
+    if crate::rosbag1::is_rosbag1(path) {
+        return Ok(Arc::new(crate::rosbag1::Rosbag1Source::open(path)?));
+    }
+    #[cfg(feature = "rosbag2")]
+    if crate::rosbag2::is_rosbag2(path) {
+        return Ok(Arc::new(crate::rosbag2::Rosbag2Source::open(path)?));
+    }
+    if is_stream(path) {
+        return Ok(Arc::new(StreamSource::new(path)));
+    }
+    Ok(Arc::new(
+        McapSource::new(path, io)
+            .with_decode_pool(decode_pool)
+            .with_bad_records(bad_records),
+    ))
+}
+
+/// Reads MCAP files.
+///
+/// Seeks start reading at the first chunk that can hold the target time, found through the
+/// chunk index in the summary section. The chunk index is also how reading resynchronizes
+/// after a corrupt record, unless the error policy is [`ErrorPolicy::Stop`].
+///
+/// [`ErrorPolicy::Stop`]: crate::bad_records::ErrorPolicy::Stop
+pub struct McapSource {
+    path: PathBuf,
+    io: IoMode,
+    decode_pool: DecodePoolConfig,
+    bad_records: BadRecords,
+    chunk_indexes: Mutex<Vec<ChunkIndex>>,
+}
+
+impl McapSource {
+    pub fn new(path: &Path, io: IoMode) -> Self {
+        Self {
+            path: path.to_owned(),
+            io,
+            decode_pool: DecodePoolConfig::default(),
+            bad_records: BadRecords::default(),
+            chunk_indexes: Mutex::default(),
+        }
+    }
+
+    /// Sizes the worker pool used with [`IoMode::Parallel`].
+    pub fn with_decode_pool(mut self, config: DecodePoolConfig) -> Self {
+        self.decode_pool = config;
+        self
+    }
+
+    /// Skips or stops at corrupt records according to the policy of `bad_records`, counting
+    /// skipped records in it.
+    pub fn with_bad_records(mut self, bad_records: BadRecords) -> Self {
+        self.bad_records = bad_records;
+        self
+    }
+}
+
+impl MessageSource for McapSource {
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn load_summary(&self, options: &SummaryOptions) -> Result<Summary> {
+        let summary = Summary::load_from_mcap_with_options(&self.path, options)?;
+        *self.chunk_indexes.lock() = summary.chunk_indexes().to_vec();
+        Ok(summary)
+    }
+
+    fn read_messages(
+        &self,
+        tx: mpsc::Sender<OwnedMessage>,
+        done: Arc<AtomicBool>,
+        skip_until: Option<u64>,
+    ) -> BoxFuture<'static, Result<()>> {
+        let path = self.path.clone();
+        let start_offset = skip_until.and_then(|t| seek_offset(&self.chunk_indexes.lock(), t));
+        if let Some(offset) = start_offset {
+            debug!("Starting read at chunk offset {offset}");
+        }
+        let io = if self.io == IoMode::Parallel && self.chunk_indexes.lock().is_empty() {
+            warn!("{} has no chunk index, reading it on one thread", path.display());
+            IoMode::Mmap
+        } else {
+            self.io
+        };
+        let mut chunk_indexes = self.chunk_indexes.lock().clone();
+        chunk_indexes.sort_by_key(|index| index.chunk_start_offset);
+        let config = self.decode_pool;
+        let bad_records = self.bad_records.clone();
+        async move {
+            let (mut skip_until, mut start_offset) = (skip_until, start_offset);
+            loop {
+                let result = match io {
+                    IoMode::Buffered => {
+                        let (path, tx, done) = (path.clone(), tx.clone(), done.clone());
+                        read_messages(path, tx, done, skip_until, start_offset).await
+                    }
+                    IoMode::Mmap => {
+                        let (path, tx, done) = (path.clone(), tx.clone(), done.clone());
+                        tokio::task::spawn_blocking(move || {
+                            read_messages_mmap(&path, tx, done, skip_until, start_offset)
+                        })
+                        .await
+                        .context("mmap reader")?
+                    }
+                    IoMode::Parallel => {
+                        // Chunks are decoded separately, so bad ones are skipped in place.
+                        let bad_records = bad_records.clone();
+                        return tokio::task::spawn_blocking(move || {
+                            read_messages_parallel(
+                                &path,
+                                &chunk_indexes,
+                                config,
+                                tx,
+                                done,
+                                skip_until,
+                                &bad_records,
+                            )
+                        })
+                        .await
+                        .context("parallel reader")?;
+                    }
+                };
+                let Err(err) = result else {
+                    return Ok(());
+                };
+                let Some(corrupt) = err.downcast_ref::<CorruptRecord>() else {
+                    return Err(err);
+                };
+                if bad_records.policy() == ErrorPolicy::Stop {
+                    return Err(err.context("read data"));
+                }
+                if corrupt.consumed.is_none() {
+                    // Read again up to the bad record with a reader that tracks offsets.
+                    debug!("Locating bad record: {corrupt}");
+                    let Some(first) = chunk_indexes.first() else {
+                        let then = "no chunk index to resume with, ending the pass";
+                        return bad_records.skip(err, then);
+                    };
+                    start_offset = Some(first.chunk_start_offset);
+                    skip_until = skip_until.max(corrupt.sent_until.map(|t| t + 1));
+                    continue;
+                }
+                match corrupt.resume_offset(&chunk_indexes) {
+                    Some(offset) => {
+                        let then = format!("resuming at the chunk at offset {offset}");
+                        bad_records.skip(err, &then)?;
+                        start_offset = Some(offset);
+                    }
+                    None => {
+                        return bad_records.skip(err, "no chunk follows it, ending the pass");
+                    }
+                }
+            }
+        }
+        .boxed()
+    }
+}
+
+/// Reads an MCAP stream that can't be seeked, such as standard input or a pipe.
+///
+/// The summary section at the end of the file is out of reach, so schemas and channels are
+/// discovered as they appear during the linear pass instead of being loaded up front.
+pub struct StreamSource {
+    path: PathBuf,
+    /// Registers discovered channels into the summary handed out by `load_summary`.
+    discovered: Mutex<Option<Summary>>,
+}
+
+impl StreamSource {
+    /// Creates a source reading `path`, or standard input if the path is `-`.
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_owned(),
+            discovered: Mutex::default(),
+        }
+    }
+}
+
+impl MessageSource for StreamSource {
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn load_summary(&self, options: &SummaryOptions) -> Result<Summary> {
+        let summary = Summary::new(&self.path, options);
+        *self.discovered.lock() = Some(summary.shared());
+        Ok(summary)
+    }
+
+    fn read_messages(
+        &self,
+        tx: mpsc::Sender<OwnedMessage>,
+        done: Arc<AtomicBool>,
+        _skip_until: Option<u64>,
+    ) -> BoxFuture<'static, Result<()>> {
+        let path = self.path.clone();
+        let discovered = self.discovered.lock().take();
+        async move {
+            let mut summary =
+                discovered.ok_or_else(|| anyhow!("{} can only be read once", path.display()))?;
+            tokio::task::spawn_blocking(move || {
+                if path == Path::new("-") {
+                    read_messages_streaming(std::io::stdin().lock(), &mut summary, tx, done)
+                } else {
+                    let file = std::fs::File::open(&path)
+                        .with_context(|| format!("open {}", path.display()))?;
+                    read_messages_streaming(file, &mut summary, tx, done)
+                }
+            })
+            .await
+            .context("stream reader")?
+        }
+        .boxed()
+    }
+
+    fn is_streaming(&self) -> bool {
+        true
+    }
+}
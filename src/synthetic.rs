@@ -0,0 +1,158 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use mcap::records::{MessageHeader, Record, Statistics};
+use tokio::sync::mpsc;
+
+use crate::mcap_replay::{OwnedMessage, Summary, SummaryOptions};
+use crate::source::MessageSource;
+
+/// Log time of the first synthetic message, fixed so runs are comparable.
+const START_NS: u64 = 1_700_000_000_000_000_000;
+
+/// Shape of the fabricated load.
+#[derive(Debug, Clone)]
+pub struct SyntheticOptions {
+    /// Number of topics, named `/synthetic/0`, `/synthetic/1`, ...
+    pub topics: usize,
+    /// Messages per second on each topic.
+    pub rate_hz: f64,
+    /// Size of each message in bytes.
+    pub payload_bytes: usize,
+    /// Log time covered by the messages.
+    pub duration: Duration,
+}
+
+impl Default for SyntheticOptions {
+    fn default() -> Self {
+        Self {
+            topics: 10,
+            rate_hz: 100.0,
+            payload_bytes: 1024,
+            duration: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Fabricates schemaless JSON messages on many topics at fixed rates, for load testing the
+/// replay path without a recording of the right shape.
+///
+/// Topics publish in turn, evenly spread over each period, so messages are in log time order.
+pub struct SyntheticSource {
+    path: PathBuf,
+    options: SyntheticOptions,
+}
+
+impl SyntheticSource {
+    pub fn new(options: SyntheticOptions) -> Result<Self> {
+        if options.topics == 0 || options.topics > u16::MAX as usize {
+            return Err(anyhow!("topic count must be between 1 and {}", u16::MAX));
+        }
+        if !options.rate_hz.is_finite() || options.rate_hz <= 0.0 {
+            return Err(anyhow!("rate must be positive"));
+        }
+        Ok(Self {
+            path: PathBuf::from("synthetic"),
+            options,
+        })
+    }
+
+    /// Number of messages on each topic.
+    fn ticks(&self) -> u64 {
+        (self.options.duration.as_secs_f64() * self.options.rate_hz) as u64
+    }
+
+    /// Time between consecutive messages across all topics, in nanoseconds.
+    fn spacing_ns(&self) -> f64 {
+        1e9 / self.options.rate_hz / self.options.topics as f64
+    }
+
+    /// Returns a JSON object padded to the payload size (or just the topic index, if smaller).
+    fn payload(&self, topic: usize) -> Bytes {
+        let prefix = format!("{{\"topic\":{topic},\"pad\":\"");
+        let suffix = "\"}";
+        let pad = self
+            .options
+            .payload_bytes
+            .saturating_sub(prefix.len() + suffix.len());
+        Bytes::from(format!("{prefix}{}{suffix}", "x".repeat(pad)))
+    }
+}
+
+impl MessageSource for SyntheticSource {
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn load_summary(&self, options: &SummaryOptions) -> Result<Summary> {
+        let mut summary = Summary::new(&self.path, options);
+        for topic in 0..self.options.topics {
+            summary.handle_channel(mcap::records::Channel {
+                id: topic as u16 + 1,
+                schema_id: 0,
+                topic: format!("/synthetic/{topic}"),
+                message_encoding: "json".to_string(),
+                metadata: BTreeMap::new(),
+            })?;
+        }
+        let count = self.ticks() * self.options.topics as u64;
+        summary.handle_record(Record::Statistics(Statistics {
+            message_count: count,
+            channel_count: self.options.topics as u32,
+            message_start_time: START_NS,
+            message_end_time: START_NS + (count.saturating_sub(1) as f64 * self.spacing_ns()) as u64,
+            ..Default::default()
+        }))?;
+        Ok(summary)
+    }
+
+    fn read_messages(
+        &self,
+        tx: mpsc::Sender<OwnedMessage>,
+        done: Arc<AtomicBool>,
+        skip_until: Option<u64>,
+    ) -> BoxFuture<'static, Result<()>> {
+        let topics = self.options.topics;
+        let payloads: Vec<Bytes> = (0..topics).map(|topic| self.payload(topic)).collect();
+        let count = self.ticks() * topics as u64;
+        let spacing_ns = self.spacing_ns();
+        async move {
+            tokio::task::spawn_blocking(move || {
+                let first = skip_until.map_or(0, |t| {
+                    (t.saturating_sub(START_NS) as f64 / spacing_ns).ceil() as u64
+                });
+                for index in first..count {
+                    if done.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let topic = (index % topics as u64) as usize;
+                    let log_time = START_NS + (index as f64 * spacing_ns) as u64;
+                    let message = OwnedMessage {
+                        header: MessageHeader {
+                            channel_id: topic as u16 + 1,
+                            sequence: (index / topics as u64) as u32,
+                            log_time,
+                            publish_time: log_time,
+                        },
+                        data: payloads[topic].clone(),
+                    };
+                    if tx.blocking_send(message).is_err() {
+                        break;
+                    }
+                }
+                Ok(())
+            })
+            .await
+            .context("synthetic generator")?
+        }
+        .boxed()
+    }
+}
+
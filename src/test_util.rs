@@ -0,0 +1,18 @@
+//! Fixtures shared by unit tests.
+
+use mcap::records::ChunkIndex;
+
+/// A chunk index entry for a 100-byte chunk at `offset` covering the given log times.
+pub(crate) fn chunk_index(offset: u64, start_time: u64, end_time: u64) -> ChunkIndex {
+    ChunkIndex {
+        message_start_time: start_time,
+        message_end_time: end_time,
+        chunk_start_offset: offset,
+        chunk_length: 100,
+        message_index_offsets: Default::default(),
+        message_index_length: 0,
+        compression: String::new(),
+        compressed_size: 0,
+        uncompressed_size: 0,
+    }
+}
@@ -0,0 +1,86 @@
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context, Result};
+
+/// Limits on how much data is sent to clients.
+#[derive(Debug, Clone, Default)]
+pub struct ThrottleConfig {
+    /// Total bandwidth budget in bytes per second.
+    pub max_bytes_per_sec: Option<f64>,
+    /// Maximum message rate in Hz for individual topics.
+    pub topic_rates: HashMap<String, f64>,
+    /// Topics that are never dropped for exceeding the bandwidth budget.
+    pub priority_topics: HashSet<String>,
+}
+
+impl ThrottleConfig {
+    /// Returns true if no limits are configured.
+    pub fn is_empty(&self) -> bool {
+        self.max_bytes_per_sec.is_none() && self.topic_rates.is_empty()
+    }
+}
+
+/// Parses a `<topic>=<hz>` rate cap.
+pub fn parse_topic_rate(s: &str) -> Result<(String, f64)> {
+    let (topic, hz) = s
+        .rsplit_once('=')
+        .ok_or_else(|| anyhow!("expected <topic>=<hz>, got {s:?}"))?;
+    let hz: f64 = hz.parse().with_context(|| format!("invalid rate in {s:?}"))?;
+    if hz <= 0.0 || !hz.is_finite() {
+        return Err(anyhow!("rate must be positive in {s:?}"));
+    }
+    Ok((topic.to_string(), hz))
+}
+
+/// Decides which messages to drop to stay within a [`ThrottleConfig`].
+///
+/// The bandwidth budget is a token bucket holding up to one second of data, so short bursts
+/// pass through while sustained overload is shed. Priority topics always pass, but still
+/// consume budget so that other topics back off.
+pub struct Throttle {
+    config: ThrottleConfig,
+    tokens: f64,
+    last_refill: Instant,
+    last_sent: HashMap<String, Instant>,
+}
+
+impl Throttle {
+    pub fn new(config: ThrottleConfig) -> Self {
+        Self {
+            tokens: config.max_bytes_per_sec.unwrap_or_default(),
+            config,
+            last_refill: Instant::now(),
+            last_sent: HashMap::new(),
+        }
+    }
+
+    /// Returns true if a message of `bytes` on `topic` should be sent.
+    pub fn admit(&mut self, topic: &str, bytes: usize) -> bool {
+        let now = Instant::now();
+        if let Some(hz) = self.config.topic_rates.get(topic) {
+            let interval = Duration::from_secs_f64(1.0 / hz);
+            if let Some(last) = self.last_sent.get(topic) {
+                if now.duration_since(*last) < interval {
+                    return false;
+                }
+            }
+        }
+
+        if let Some(rate) = self.config.max_bytes_per_sec {
+            let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+            self.tokens = (self.tokens + elapsed * rate).min(rate);
+            self.last_refill = now;
+            let bytes = bytes as f64;
+            if self.tokens < bytes && !self.config.priority_topics.contains(topic) {
+                return false;
+            }
+            self.tokens -= bytes;
+        }
+
+        if self.config.topic_rates.contains_key(topic) {
+            self.last_sent.insert(topic.to_string(), now);
+        }
+        true
+    }
+}
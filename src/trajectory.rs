@@ -0,0 +1,108 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+/// File format for exported camera trajectories.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrajectoryFormat {
+    /// `timestamp tx ty tz qx qy qz qw` per line, timestamps in seconds.
+    #[default]
+    Tum,
+    /// Row-major 3x4 `[R|t]` matrix per line, with timestamps in a separate `*_times.txt` file.
+    Kitti,
+}
+
+/// Writes timestamped camera poses for odometry and SLAM evaluation.
+///
+/// Poses are written as the session runs, one per camera tick, stamped with the replay's log
+/// time so they line up with the sensor data in the file. Ticks that don't advance the log time,
+/// e.g. while paused, are skipped so every timestamp is unique.
+pub struct TrajectoryWriter {
+    format: TrajectoryFormat,
+    poses: BufWriter<File>,
+    times: Option<BufWriter<File>>,
+    last_time: Option<u64>,
+}
+
+impl TrajectoryWriter {
+    pub fn create(path: &Path, format: TrajectoryFormat) -> Result<Self> {
+        let poses = create(path)?;
+        let times = match format {
+            TrajectoryFormat::Tum => None,
+            TrajectoryFormat::Kitti => Some(create(&times_path(path))?),
+        };
+        Ok(Self {
+            format,
+            poses,
+            times,
+            last_time: None,
+        })
+    }
+
+    /// Appends a pose. `rotation` is a quaternion (x, y, z, w).
+    pub fn write(&mut self, log_time: u64, translation: &[f64], rotation: &[f64]) -> Result<()> {
+        if log_time == 0 || self.last_time.is_some_and(|last| log_time <= last) {
+            return Ok(());
+        }
+        self.last_time = Some(log_time);
+        let secs = log_time as f64 / 1e9;
+        let (t, q) = (translation, rotation);
+        match self.format {
+            TrajectoryFormat::Tum => {
+                writeln!(
+                    self.poses,
+                    "{secs:.9} {} {} {} {} {} {} {}",
+                    t[0], t[1], t[2], q[0], q[1], q[2], q[3]
+                )?;
+            }
+            TrajectoryFormat::Kitti => {
+                let r = rotation_matrix(q);
+                writeln!(
+                    self.poses,
+                    "{} {} {} {} {} {} {} {} {} {} {} {}",
+                    r[0][0], r[0][1], r[0][2], t[0],
+                    r[1][0], r[1][1], r[1][2], t[1],
+                    r[2][0], r[2][1], r[2][2], t[2],
+                )?;
+                if let Some(times) = &mut self.times {
+                    writeln!(times, "{secs:.9}")?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Flushes buffered poses to disk.
+    pub fn finish(mut self) -> Result<()> {
+        self.poses.flush()?;
+        if let Some(times) = &mut self.times {
+            times.flush()?;
+        }
+        Ok(())
+    }
+}
+
+fn create(path: &Path) -> Result<BufWriter<File>> {
+    let file = File::create(path).with_context(|| format!("create {}", path.display()))?;
+    Ok(BufWriter::new(file))
+}
+
+/// Returns the KITTI timestamps file for a poses file: `poses.txt` -> `poses_times.txt`.
+fn times_path(path: &Path) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{stem}_times.txt"))
+}
+
+/// Rotation matrix for a unit quaternion (x, y, z, w).
+fn rotation_matrix(q: &[f64]) -> [[f64; 3]; 3] {
+    let (x, y, z, w) = (q[0], q[1], q[2], q[3]);
+    [
+        [1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y - z * w), 2.0 * (x * z + y * w)],
+        [2.0 * (x * y + z * w), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z - x * w)],
+        [2.0 * (x * z - y * w), 2.0 * (y * z + x * w), 1.0 - 2.0 * (x * x + y * y)],
+    ]
+}
@@ -0,0 +1,126 @@
+use std::fs::File;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::{info, warn};
+
+use crate::progress;
+
+/// Foxglove API used when no other URL is configured.
+pub const DEFAULT_API_URL: &str = "https://api.foxglove.dev";
+/// Environment variable holding the API key, when `--token` isn't given.
+pub const TOKEN_ENV: &str = "FOXGLOVE_API_KEY";
+
+/// Delay before the first retry; doubled after every failed attempt.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Where and as whom recordings are uploaded.
+#[derive(Debug, Clone)]
+pub struct UploadSettings {
+    pub device_id: String,
+    pub token: String,
+    pub api_url: String,
+    /// Attempts per file before giving up.
+    pub attempts: u32,
+}
+
+#[derive(Deserialize)]
+struct UploadLink {
+    link: String,
+}
+
+/// Uploads finished recordings to Foxglove Data Platform.
+///
+/// Each file is uploaded in two steps: the API hands out a signed upload link for the file name
+/// and device, and the file is then sent to that link. Failed attempts are retried with
+/// exponential backoff, except for client errors (bad token, unknown device), which won't go
+/// away by retrying.
+pub struct Uploader {
+    agent: ureq::Agent,
+    settings: UploadSettings,
+}
+
+impl Uploader {
+    pub fn new(settings: UploadSettings) -> Self {
+        Self {
+            agent: ureq::AgentBuilder::new()
+                .timeout_connect(Duration::from_secs(10))
+                .build(),
+            settings,
+        }
+    }
+
+    /// Uploads one file, retrying failed attempts and reporting progress on stderr.
+    pub fn upload(&self, path: &Path) -> Result<()> {
+        let mut backoff = INITIAL_BACKOFF;
+        let mut attempt = 1;
+        loop {
+            match self.try_upload(path) {
+                Ok(()) => {
+                    info!("Uploaded {}", path.display());
+                    return Ok(());
+                }
+                Err(err) if attempt < self.settings.attempts && is_retryable(&err) => {
+                    warn!(
+                        "Upload of {} failed (attempt {attempt}/{}), retrying in {}s: {err:#}",
+                        path.display(),
+                        self.settings.attempts,
+                        backoff.as_secs()
+                    );
+                    thread::sleep(backoff);
+                    backoff = (backoff * 2).min(MAX_BACKOFF);
+                    attempt += 1;
+                }
+                Err(err) => {
+                    return Err(err.context(format!("upload {}", path.display())));
+                }
+            }
+        }
+    }
+
+    fn try_upload(&self, path: &Path) -> Result<()> {
+        let filename = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| anyhow!("invalid file name {}", path.display()))?;
+        let response: UploadLink = self
+            .agent
+            .post(&format!("{}/v1/data/upload", self.settings.api_url))
+            .set("Authorization", &format!("Bearer {}", self.settings.token))
+            .send_json(json!({
+                "filename": filename,
+                "deviceId": self.settings.device_id,
+            }))
+            .context("request upload link")?
+            .into_json()
+            .context("parse upload link")?;
+
+        let file = File::open(path).with_context(|| format!("open {}", path.display()))?;
+        let len = file.metadata().context("read file size")?.len();
+        let bar = progress::bytes_bar(len, "Uploading");
+        let result = self
+            .agent
+            .put(&response.link)
+            .set("Content-Type", "application/octet-stream")
+            .set("Content-Length", &len.to_string())
+            .send(bar.wrap_read(file));
+        bar.finish_and_clear();
+        result.context("send file")?;
+        Ok(())
+    }
+}
+
+/// Returns true for network errors, rate limiting, and server errors.
+fn is_retryable(err: &anyhow::Error) -> bool {
+    match err.downcast_ref::<ureq::Error>() {
+        Some(ureq::Error::Status(status, _)) => *status == 429 || *status >= 500,
+        Some(ureq::Error::Transport(_)) => true,
+        // Reading the file or parsing the response.
+        None => false,
+    }
+}
@@ -0,0 +1,125 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use anyhow::Result;
+use mcap::records::Record;
+use mcap::sans_io::read::{LinearReader, LinearReaderOptions};
+
+use crate::mcap_replay::advance_reader;
+
+/// Result of checking an MCAP file with [`validate`].
+#[derive(Debug, Default)]
+pub struct ValidationReport {
+    /// Number of messages per topic.
+    pub topics: BTreeMap<String, u64>,
+    /// Everything found to be wrong with the file.
+    pub problems: Vec<String>,
+}
+
+impl ValidationReport {
+    /// Returns true if no problems were found.
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    /// Total number of messages across all topics.
+    pub fn message_count(&self) -> u64 {
+        self.topics.values().sum()
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} messages", self.message_count())?;
+        for (topic, count) in &self.topics {
+            writeln!(f, "  {topic}: {count}")?;
+        }
+        if self.is_ok() {
+            write!(f, "OK")
+        } else {
+            writeln!(f, "{} problem(s):", self.problems.len())?;
+            for problem in &self.problems {
+                writeln!(f, "  {problem}")?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Reads the whole file, checking chunk, data section, and summary section CRCs, that every
+/// channel refers to a known schema and every message to a known channel, and that the
+/// Statistics record (if any) matches what was found.
+///
+/// Only I/O errors are returned as `Err`; anything wrong with the file itself ends up in
+/// [`ValidationReport::problems`]. Reading stops at the first CRC or framing error, since
+/// nothing after it can be trusted.
+pub fn validate(path: &Path) -> Result<ValidationReport> {
+    let mut file = BufReader::new(File::open(path)?);
+    let mut reader = LinearReader::new_with_options(LinearReaderOptions {
+        validate_chunk_crcs: true,
+        validate_data_section_crc: true,
+        validate_summary_section_crc: true,
+        ..Default::default()
+    });
+
+    let mut report = ValidationReport::default();
+    let mut schemas = HashSet::new();
+    let mut channels: HashMap<u16, String> = HashMap::new();
+    let mut unknown_channels: BTreeMap<u16, u64> = BTreeMap::new();
+    let mut statistics = None;
+    let mut in_summary = false;
+    loop {
+        let more = advance_reader(&mut reader, &mut file, |record| {
+            match record {
+                Record::Schema { header, .. } => {
+                    schemas.insert(header.id);
+                }
+                Record::Channel(channel) => {
+                    if channel.schema_id != 0 && !schemas.contains(&channel.schema_id) {
+                        report.problems.push(format!(
+                            "channel {} ({}) refers to unknown schema {}",
+                            channel.id, channel.topic, channel.schema_id
+                        ));
+                    }
+                    channels.insert(channel.id, channel.topic);
+                }
+                Record::Message { header, .. } => match channels.get(&header.channel_id) {
+                    Some(topic) => *report.topics.entry(topic.clone()).or_default() += 1,
+                    None => *unknown_channels.entry(header.channel_id).or_default() += 1,
+                },
+                Record::DataEnd(_) => in_summary = true,
+                Record::Statistics(stats) => statistics = Some(stats),
+                _ => {}
+            }
+            Ok(())
+        });
+        match more {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(e) => {
+                let section = if in_summary { "summary" } else { "data" };
+                report.problems.push(format!("corrupt {section} section: {e:#}"));
+                break;
+            }
+        }
+    }
+
+    for (id, count) in unknown_channels {
+        report
+            .problems
+            .push(format!("{count} message(s) refer to unknown channel {id}"));
+    }
+    if let Some(stats) = statistics {
+        if stats.message_count != report.message_count() {
+            report.problems.push(format!(
+                "statistics record lists {} messages, found {}",
+                stats.message_count,
+                report.message_count()
+            ));
+        }
+    }
+    Ok(report)
+}